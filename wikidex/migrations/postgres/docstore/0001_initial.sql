@@ -0,0 +1,20 @@
+-- Indexes are created by `wikidex docstore migrate` once the rows are copied.
+CREATE TABLE IF NOT EXISTS article (
+  id BIGINT PRIMARY KEY, title TEXT NOT NULL,
+  access_date BIGINT NOT NULL, modification_date BIGINT NOT NULL,
+  page_id BIGINT NOT NULL, revision_id BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS document (
+  id BIGINT PRIMARY KEY, text BYTEA NOT NULL, article BIGINT NOT NULL,
+  heading TEXT NOT NULL, ordinal BIGINT NOT NULL, codec TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS article_category (
+  article BIGINT NOT NULL, category TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS compression_dictionary (
+  dictionary BYTEA NOT NULL
+);
+CREATE TABLE IF NOT EXISTS schema_version (
+  version BIGINT NOT NULL
+);
+INSERT INTO schema_version (version) VALUES (1);
@@ -0,0 +1,2 @@
+-- The ingest checkpoint of SQLite docstores is not copied.
+UPDATE schema_version SET version = 3;
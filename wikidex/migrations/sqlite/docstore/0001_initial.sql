@@ -0,0 +1,36 @@
+CREATE TABLE IF NOT EXISTS article (
+  id INTEGER PRIMARY KEY NOT NULL, title TEXT NOT NULL,
+  access_date INTEGER NOT NULL, modification_date INTEGER NOT NULL,
+  page_id INTEGER NOT NULL, revision_id INTEGER NOT NULL,
+  unique(id)
+);
+CREATE INDEX IF NOT EXISTS article_title ON article (title);
+CREATE INDEX IF NOT EXISTS article_page_id ON article (page_id);
+CREATE TABLE IF NOT EXISTS document (
+  id INTEGER PRIMARY KEY NOT NULL,
+  text BLOB NOT NULL,
+  article INTEGER NOT NULL,
+  heading TEXT NOT NULL,
+  ordinal INTEGER NOT NULL,
+  codec TEXT NOT NULL,
+  FOREIGN KEY(article) REFERENCES article(id),
+  unique(id)
+);
+CREATE INDEX IF NOT EXISTS document_article ON document (article);
+CREATE TABLE IF NOT EXISTS compression_dictionary (
+  dictionary BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS article_category (
+  article INTEGER NOT NULL,
+  category TEXT NOT NULL,
+  FOREIGN KEY(article) REFERENCES article(id),
+  unique(article, category)
+);
+CREATE INDEX IF NOT EXISTS article_category_category ON article_category (category);
+CREATE TABLE IF NOT EXISTS completed_on (
+  db_date INTEGER NOT NULL, article_count INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS schema_version (
+  version INTEGER NOT NULL
+);
+INSERT INTO schema_version (version) VALUES (1);
@@ -0,0 +1,4 @@
+-- Articles cited as the corpus is configured keep both NULL.
+ALTER TABLE article ADD COLUMN provenance TEXT;
+ALTER TABLE article ADD COLUMN metadata TEXT;
+UPDATE schema_version SET version = 2;
@@ -0,0 +1,5 @@
+-- The number of documents the ingest split an article into. An article whose documents are all
+-- committed is checkpointed, so that an interrupted ingest can be resumed. NULL for articles
+-- written before.
+ALTER TABLE article ADD COLUMN document_count INTEGER;
+UPDATE schema_version SET version = 3;
@@ -0,0 +1,4 @@
+-- The last heading of a document's section, NULL for an article's lead. Documents written before
+-- keep NULL and their section is taken from `heading`.
+ALTER TABLE document ADD COLUMN section TEXT;
+UPDATE schema_version SET version = 4;
@@ -0,0 +1,27 @@
+CREATE TABLE IF NOT EXISTS embeddings (
+  id INTEGER PRIMARY KEY NOT NULL, gte_small BLOB NOT NULL,
+  unique(id)
+);
+CREATE TABLE IF NOT EXISTS embedding_profile (
+  name TEXT NOT NULL, query_prefix TEXT NOT NULL,
+  document_prefix TEXT NOT NULL, normalize INTEGER NOT NULL,
+  max_input_chars INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tombstones (
+  id INTEGER PRIMARY KEY NOT NULL
+);
+CREATE TABLE IF NOT EXISTS quantization (
+  kind TEXT NOT NULL, binary_codes INTEGER NOT NULL,
+  dimensions INTEGER NOT NULL, minimum BLOB NOT NULL,
+  scale BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS embedding_codes (
+  id INTEGER PRIMARY KEY NOT NULL, code BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS completed_on (
+  db_date INTEGER NOT NULL, article_count INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS schema_version (
+  version INTEGER NOT NULL
+);
+INSERT INTO schema_version (version) VALUES (1);
@@ -0,0 +1,6 @@
+-- The run writing the databases: whether it was incremental and the first article it wrote, so
+-- that a resumed run replaces articles as the interrupted one did.
+CREATE TABLE IF NOT EXISTS ingest_run (
+  incremental INTEGER NOT NULL, first_article_id INTEGER NOT NULL
+);
+UPDATE schema_version SET version = 2;
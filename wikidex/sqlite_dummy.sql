@@ -7,14 +7,55 @@ CREATE TABLE IF NOT EXISTS wiki_markup (
 );
 CREATE TABLE IF NOT EXISTS article (
   id INTEGER PRIMARY KEY NOT NULL, title TEXT NOT NULL, 
-  access_date INTEGER NOT NULL, modification_date INTEGER NOT NULL
+  access_date INTEGER NOT NULL, modification_date INTEGER NOT NULL, 
+  page_id INTEGER NOT NULL, revision_id INTEGER NOT NULL, 
+  provenance TEXT, metadata TEXT, document_count INTEGER
 );
+CREATE INDEX IF NOT EXISTS article_title ON article (title);
+CREATE INDEX IF NOT EXISTS article_page_id ON article (page_id);
 CREATE TABLE IF NOT EXISTS document (
   id INTEGER PRIMARY KEY NOT NULL, 
   text BLOB NOT NULL, 
   article INTEGER NOT NULL, 
+  heading TEXT NOT NULL, 
+  ordinal INTEGER NOT NULL, 
+  section TEXT, 
+  codec TEXT NOT NULL, 
   FOREIGN KEY(article) REFERENCES article(id)
 );
+CREATE INDEX IF NOT EXISTS document_article ON document (article);
+CREATE TABLE IF NOT EXISTS compression_dictionary (
+  dictionary BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS article_category (
+  article INTEGER NOT NULL, 
+  category TEXT NOT NULL, 
+  FOREIGN KEY(article) REFERENCES article(id), 
+  unique(article, category)
+);
+CREATE INDEX IF NOT EXISTS article_category_category ON article_category (category);
 CREATE TABLE IF NOT EXISTS embeddings (
   id INTEGER PRIMARY KEY NOT NULL, gte_small BLOB NOT NULL
 );
+CREATE TABLE IF NOT EXISTS tombstones (
+  id INTEGER PRIMARY KEY NOT NULL
+);
+CREATE TABLE IF NOT EXISTS embedding_profile (
+  name TEXT NOT NULL, query_prefix TEXT NOT NULL, 
+  document_prefix TEXT NOT NULL, normalize INTEGER NOT NULL, 
+  max_input_chars INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS quantization (
+  kind TEXT NOT NULL, binary_codes INTEGER NOT NULL, 
+  dimensions INTEGER NOT NULL, minimum BLOB NOT NULL, 
+  scale BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS embedding_codes (
+  id INTEGER PRIMARY KEY NOT NULL, code BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS schema_version (
+  version INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS ingest_run (
+  incremental INTEGER NOT NULL, first_article_id INTEGER NOT NULL
+);
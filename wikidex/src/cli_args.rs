@@ -3,7 +3,19 @@ use std::path::PathBuf;
 use clap::{Parser, Subcommand};
 use url::Url;
 
-use crate::llm_client::{ModelEndpoint, ModelKind};
+#[cfg(all(feature = "pgvector", feature = "sqlite"))]
+use crate::index::PgVectorIndexKind;
+#[cfg(all(feature = "faiss", feature = "sqlite"))]
+use crate::ingest::pipeline::IndexMetric;
+#[cfg(feature = "ingest")]
+use crate::ingest::pipeline::StepLimit;
+#[cfg(feature = "server")]
+use crate::{corpus::CorpusConfig, docstore::DocumentCacheKind, formatter::ProvenanceKind};
+use crate::{
+    embedding_client::{EmbeddingProfile, EmbeddingQuantization},
+    llm_client::{ModelEndpoint, ModelKind},
+    text_codec::TextCodec,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -18,6 +30,119 @@ pub(crate) enum Commands {
     Server(ServerArgs),
     #[cfg(feature = "ingest")]
     Wikipedia(WikipediaIngestArgs),
+    #[cfg(all(feature = "sqlite", any(feature = "faiss", feature = "pgvector")))]
+    Index(IndexArgs),
+    #[cfg(all(feature = "sqlite", any(feature = "ingest", feature = "migrate")))]
+    Docstore(DocstoreArgs),
+}
+
+#[cfg(all(feature = "sqlite", any(feature = "faiss", feature = "pgvector")))]
+#[derive(Parser, Debug)]
+pub(crate) struct IndexArgs {
+    #[command(subcommand)]
+    pub(crate) command: IndexCommands,
+}
+
+#[cfg(all(feature = "sqlite", any(feature = "faiss", feature = "pgvector")))]
+#[derive(Subcommand, Debug)]
+pub(crate) enum IndexCommands {
+    /// Builds a faiss index from the ingest's embeddings.
+    #[cfg(feature = "faiss")]
+    Build(IndexBuildArgs),
+    /// Copies the ingest's embeddings into a pgvector table and indexes them.
+    #[cfg(feature = "pgvector")]
+    Pgvector(PgVectorArgs),
+}
+
+#[cfg(all(feature = "faiss", feature = "sqlite"))]
+#[derive(Parser, Debug)]
+pub(crate) struct IndexBuildArgs {
+    /// The `wikipedia_index.sqlite` written by the ingest.
+    #[arg(long)]
+    pub(crate) index_database_url: Url,
+    /// Where to write the index, its metadata is written next to it with a `.json` suffix.
+    #[arg(long)]
+    pub(crate) output: PathBuf,
+    /// A faiss index factory string, e.g. `PCA128,IVF4096,PQ32`.
+    #[arg(long, default_value_t = String::from("PCA128,Flat"))]
+    pub(crate) factory: String,
+    #[arg(long, default_value = "l2")]
+    pub(crate) metric: IndexMetric,
+    /// Embeddings to train the index on, evenly spaced over the table.
+    #[arg(long, default_value_t = 100_000)]
+    pub(crate) train_sample: usize,
+    /// Embeddings read and added to the index at once.
+    #[arg(long, default_value_t = 65_536)]
+    pub(crate) chunk_size: usize,
+}
+
+#[cfg(all(feature = "pgvector", feature = "sqlite"))]
+#[derive(Parser, Debug)]
+pub(crate) struct PgVectorArgs {
+    /// The `wikipedia_index.sqlite` written by the ingest.
+    #[arg(long)]
+    pub(crate) index_database_url: Url,
+    /// The Postgres database to write, later given to the server as `--index-url`.
+    #[arg(long)]
+    pub(crate) index_url: Url,
+    #[arg(long, default_value = "hnsw")]
+    pub(crate) kind: PgVectorIndexKind,
+    /// Connections per HNSW layer.
+    #[arg(long, default_value_t = 16)]
+    pub(crate) m: usize,
+    /// Candidates considered while building the HNSW graph.
+    #[arg(long, default_value_t = 64)]
+    pub(crate) ef_construction: usize,
+    /// IVFFlat clusters, roughly the square root of the number of embeddings.
+    #[arg(long, default_value_t = 1000)]
+    pub(crate) lists: usize,
+}
+
+#[cfg(all(feature = "sqlite", any(feature = "ingest", feature = "migrate")))]
+#[derive(Parser, Debug)]
+pub(crate) struct DocstoreArgs {
+    #[command(subcommand)]
+    pub(crate) command: DocstoreCommands,
+}
+
+#[cfg(all(feature = "sqlite", any(feature = "ingest", feature = "migrate")))]
+#[derive(Subcommand, Debug)]
+pub(crate) enum DocstoreCommands {
+    /// Copies the ingest's docstore into Postgres, resuming after the last copied ids.
+    #[cfg(feature = "migrate")]
+    Migrate(DocstoreMigrateArgs),
+    /// Rewrites the documents of the ingest's docstore with another codec.
+    Recompress(DocstoreRecompressArgs),
+}
+
+#[cfg(all(feature = "migrate", feature = "sqlite"))]
+#[derive(Parser, Debug)]
+pub(crate) struct DocstoreMigrateArgs {
+    /// The `wikipedia_docstore.sqlite` written by the ingest.
+    #[arg(long)]
+    pub(crate) docstore_database_url: Url,
+    /// The Postgres database to write, later given to the server as `--docstore-url`.
+    #[arg(long)]
+    pub(crate) docstore_url: Url,
+    /// Rows copied per transaction.
+    #[arg(long, default_value_t = 10_000)]
+    pub(crate) batch_size: usize,
+}
+
+#[cfg(all(feature = "sqlite", any(feature = "ingest", feature = "migrate")))]
+#[derive(Parser, Debug)]
+pub(crate) struct DocstoreRecompressArgs {
+    /// The `wikipedia_docstore.sqlite` written by the ingest.
+    #[arg(long)]
+    pub(crate) docstore_database_url: Url,
+    #[arg(long, default_value = "zstd")]
+    pub(crate) codec: TextCodec,
+    /// Documents to train the zstd dictionary on, evenly spaced over the table.
+    #[arg(long, default_value_t = 100_000)]
+    pub(crate) train_sample: usize,
+    /// Documents rewritten per transaction.
+    #[arg(long, default_value_t = 10_000)]
+    pub(crate) batch_size: usize,
 }
 
 #[cfg(feature = "server")]
@@ -30,14 +155,43 @@ pub(crate) struct ServerArgs {
     pub(crate) port: u16,
     #[arg(long)]
     pub(crate) docstore_url: Url,
+    /// Shared by the query embedding cache and the redis document cache.
     #[arg(long)]
-    pub(crate) redis_url: Url,
+    pub(crate) redis_url: Option<Url>,
+    /// Where retrieved documents are cached: none, memory or redis.
+    #[arg(long, default_value = "memory")]
+    pub(crate) document_cache: DocumentCacheKind,
+    /// Seconds a cached document stays valid, zero never expires.
+    #[arg(long, default_value_t = 3600)]
+    pub(crate) document_cache_ttl: u64,
+    /// Bytes of documents the memory document cache holds.
+    #[arg(long, default_value_t = 256 * 1024 * 1024)]
+    pub(crate) document_cache_bytes: usize,
     #[arg(long)]
     pub(crate) system_prompt_path: PathBuf,
+    #[arg(long, default_value_t = String::from("markdown"))]
+    pub(crate) default_system_template: String,
+    #[arg(long, default_value_t = String::from("mistral"))]
+    pub(crate) default_chat_template: String,
     #[arg(long)]
     pub(crate) api_key: Option<String>,
+    /// A face index server, `file:///path/to/wikipedia_index.sqlite` to search in process, or a
+    /// `postgres://` database written by `wikidex index pgvector`.
     #[arg(long)]
     pub(crate) index_url: Url,
+    /// The `wikipedia_index.sqlite` written by the ingest, to check the embedding profile against.
+    /// Defaults to the index itself for `file://` index urls.
+    #[arg(long)]
+    pub(crate) index_database_url: Option<Url>,
+    /// Name of the corpus given by `--index-url` and `--docstore-url`, searched by default.
+    #[arg(long, default_value_t = String::from("wikipedia"))]
+    pub(crate) corpus_name: String,
+    /// How documents of the default corpus are cited.
+    #[arg(long, default_value = "wikipedia")]
+    pub(crate) provenance: ProvenanceKind,
+    /// An additional corpus, e.g. `name=handbook,index=file:///data/handbook_index.sqlite,docstore=sqlite:///data/handbook_docstore.sqlite,profile=e5`.
+    #[arg(long)]
+    pub(crate) corpus: Vec<CorpusConfig>,
     #[arg(long)]
     pub(crate) llm_kind: ModelKind,
     #[arg(long)]
@@ -52,6 +206,28 @@ pub(crate) struct ServerArgs {
     pub(crate) embed_endpoint: ModelEndpoint,
     #[arg(long)]
     pub(crate) embed_url: Url,
+    #[arg(long, default_value_t = String::from("text_input"))]
+    pub(crate) embed_input_name: String,
+    #[arg(long, default_value_t = String::from("embedding"))]
+    pub(crate) embed_output_name: String,
+    #[arg(long, default_value_t = 32)]
+    pub(crate) embed_batch_size: usize,
+    #[arg(long, default_value = "raw")]
+    pub(crate) embed_profile: EmbeddingProfile,
+    #[arg(long)]
+    pub(crate) embed_query_prefix: Option<String>,
+    #[arg(long)]
+    pub(crate) embed_document_prefix: Option<String>,
+    #[arg(long)]
+    pub(crate) embed_normalize: Option<bool>,
+    #[arg(long)]
+    pub(crate) embed_max_input_chars: Option<usize>,
+    /// Seconds a cached query embedding stays valid, zero never expires.
+    #[arg(long, default_value_t = 86400)]
+    pub(crate) embedding_cache_ttl: u64,
+    /// Query embeddings kept in process, zero only uses Redis.
+    #[arg(long, default_value_t = 4096)]
+    pub(crate) embedding_cache_capacity: usize,
 }
 #[cfg(feature = "ingest")]
 #[derive(Parser, Debug)]
@@ -77,8 +253,50 @@ pub(crate) struct WikipediaIngestArgs {
     pub(crate) embed_endpoint: ModelEndpoint,
     #[arg(long)]
     pub(crate) embed_url: Url,
+    #[arg(long, default_value_t = String::from("text_input"))]
+    pub(crate) embed_input_name: String,
+    #[arg(long, default_value_t = String::from("embedding"))]
+    pub(crate) embed_output_name: String,
+    #[arg(long, default_value_t = 32)]
+    pub(crate) embed_batch_size: usize,
+    #[arg(long, default_value = "raw")]
+    pub(crate) embed_profile: EmbeddingProfile,
+    #[arg(long)]
+    pub(crate) embed_query_prefix: Option<String>,
+    #[arg(long)]
+    pub(crate) embed_document_prefix: Option<String>,
+    #[arg(long)]
+    pub(crate) embed_normalize: Option<bool>,
+    #[arg(long)]
+    pub(crate) embed_max_input_chars: Option<usize>,
     #[arg(long, default_value_t = 0)]
     pub(crate) ingest_limit: usize,
+    /// Appends to the databases in the output directory instead of replacing them. Articles
+    /// already ingested are replaced and their documents tombstoned.
+    #[arg(long)]
+    pub(crate) incremental: bool,
+    /// Continues an interrupted ingest of the same dump into the output directory. Partly written
+    /// articles are deleted and the pages of the articles already written are skipped.
+    #[arg(long)]
+    pub(crate) resume: bool,
+    /// How the index database stores embeddings, int8 takes a quarter of the space of f32.
+    #[arg(long, default_value = "f32")]
+    pub(crate) quantization: EmbeddingQuantization,
+    /// Also stores a 1-bit code per dimension, which local indices prefilter candidates with.
+    #[arg(long)]
+    pub(crate) binary: bool,
+    /// How document text is compressed, zstd with a dictionary trained on the first batch.
+    #[arg(long, default_value = "zstd")]
+    pub(crate) compression: TextCodec,
+    /// The limits of a pipeline step, e.g. `step=parse,capacity=1024,concurrency=32`: how many
+    /// items its output channel holds and how many it transforms at once.
+    #[arg(long)]
+    pub(crate) step_limit: Vec<StepLimit>,
+    /// A local or pgvector index to apply the written embeddings and tombstones to, it must not be
+    /// the index database in the output directory.
+    #[cfg(feature = "server")]
+    #[arg(long)]
+    pub(crate) index_url: Option<Url>,
     #[arg(long)]
     pub(crate) nebula_url: Url,
     #[arg(long)]
@@ -1,6 +1,9 @@
 use crate::{
     cli_args::WikipediaIngestArgs,
+    embedding_client::{EmbeddingProfile, EmbeddingQuantization},
+    ingest::pipeline::PipelineLimits,
     llm_client::{ModelEndpoint, ModelKind},
+    text_codec::TextCodec,
 };
 use colored::Colorize;
 use std::{fmt::Display, path::PathBuf};
@@ -17,7 +20,19 @@ pub(crate) struct Config {
     pub(crate) embed_name: PathBuf,
     pub(crate) embed_endpoint: ModelEndpoint,
     pub(crate) embed_url: Url,
+    pub(crate) embed_input_name: String,
+    pub(crate) embed_output_name: String,
+    pub(crate) embed_batch_size: usize,
+    pub(crate) embed_profile: EmbeddingProfile,
     pub(crate) ingest_limit: usize,
+    pub(crate) incremental: bool,
+    pub(crate) resume: bool,
+    pub(crate) quantization: EmbeddingQuantization,
+    pub(crate) binary: bool,
+    pub(crate) compression: TextCodec,
+    pub(crate) limits: PipelineLimits,
+    #[cfg(feature = "server")]
+    pub(crate) index_url: Option<Url>,
     pub(crate) nebula_url: Url,
     pub(crate) nebula_user: String,
     pub(crate) nebula_pass: String,
@@ -30,6 +45,14 @@ impl From<WikipediaIngestArgs> for Config {
             wiki_xml: value.wiki_xml,
             output_directory: value.output_directory,
             ingest_limit: value.ingest_limit,
+            incremental: value.incremental,
+            resume: value.resume,
+            quantization: value.quantization,
+            binary: value.binary,
+            compression: value.compression,
+            limits: PipelineLimits::default().with_overrides(value.step_limit),
+            #[cfg(feature = "server")]
+            index_url: value.index_url,
             api_key: value.api_key,
             llm_kind: value.llm_kind,
             llm_name: value.llm_name,
@@ -38,6 +61,15 @@ impl From<WikipediaIngestArgs> for Config {
             embed_name: value.embed_name,
             embed_endpoint: value.embed_endpoint,
             embed_url: value.embed_url,
+            embed_input_name: value.embed_input_name,
+            embed_output_name: value.embed_output_name,
+            embed_batch_size: value.embed_batch_size,
+            embed_profile: value.embed_profile.with_overrides(
+                value.embed_query_prefix,
+                value.embed_document_prefix,
+                value.embed_normalize,
+                value.embed_max_input_chars,
+            ),
             nebula_url: value.nebula_url,
             nebula_user: value.nebula_user,
             nebula_pass: value.nebula_pass,
@@ -57,7 +89,14 @@ impl Display for Config {
             llm_url,
             embed_name,
             embed_endpoint,
+            embed_profile,
             ingest_limit,
+            incremental,
+            resume,
+            quantization,
+            binary,
+            compression,
+            limits,
             api_key: _,
             ..
         } = self;
@@ -68,6 +107,16 @@ impl Display for Config {
         let embed_endpoint = format!("{embed_endpoint}").as_str().blue();
         let embed_name = embed_name.display().to_string().bright_blue();
 
+        let mode = if *resume {
+            "Resuming"
+        } else if *incremental {
+            "Appending to"
+        } else {
+            "Writing"
+        };
+
+        let codes = if *binary { " with binary codes" } else { "" };
+
         let llm_url = llm_url.as_str().blue();
         let llm_endpoint = format!("{llm_endpoint}").as_str().blue();
         let llm_model = llm_name.display().to_string().bright_blue();
@@ -76,12 +125,16 @@ impl Display for Config {
             f,
             r###"Ingest running.
     Using wikipedia xml dump at {wiki_xml}.
-    Writing output at {output_directory}.
+    {mode} output at {output_directory}.
     Maximum {ingest_limit} articles.
+    Storing {quantization} embeddings{codes}.
+    Compressing documents with {compression}.
+    Limiting steps to {limits} (capacity/concurrency).
 Using {llm_endpoint} llm service at {llm_url}.
     Using {llm_model}.
 Using {embed_endpoint} embed service at {embed_url}.
     Using {embed_name}.
+    Using embedding profile {embed_profile}.
 "###,
         )
     }
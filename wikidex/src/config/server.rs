@@ -1,10 +1,12 @@
-use std::{fmt::Display, path::PathBuf};
+use std::{fmt::Display, path::PathBuf, time::Duration};
 
 use colored::Colorize;
 use url::Url;
 
 use crate::{
     cli_args::ServerArgs,
+    corpus::CorpusConfig,
+    docstore::DocumentCacheKind,
     llm_client::{ModelEndpoint, ModelKind},
 };
 
@@ -12,10 +14,10 @@ use crate::{
 pub(crate) struct Config {
     // Me
     pub(crate) api_key: Option<String>,
-    pub(crate) docstore_url: Url,
+    /// The default corpus first.
+    pub(crate) corpora: Vec<CorpusConfig>,
 
     pub(crate) host: String,
-    pub(crate) index_url: Url,
     pub(crate) llm_kind: ModelKind,
     pub(crate) llm_name: PathBuf,
     pub(crate) llm_endpoint: ModelEndpoint,
@@ -23,10 +25,20 @@ pub(crate) struct Config {
     pub(crate) embed_name: PathBuf,
     pub(crate) embed_endpoint: ModelEndpoint,
     pub(crate) embed_url: Url,
+    pub(crate) embed_input_name: String,
+    pub(crate) embed_output_name: String,
+    pub(crate) embed_batch_size: usize,
+    pub(crate) embedding_cache_ttl: Duration,
+    pub(crate) embedding_cache_capacity: usize,
     pub(crate) port: u16,
     pub(crate) protocol: String,
-    pub(crate) redis_url: Url,
+    pub(crate) redis_url: Option<Url>,
+    pub(crate) document_cache: DocumentCacheKind,
+    pub(crate) document_cache_ttl: Duration,
+    pub(crate) document_cache_bytes: usize,
     pub(crate) system_prompt_template_path: PathBuf,
+    pub(crate) default_system_template: String,
+    pub(crate) default_chat_template: String,
 }
 
 pub(crate) trait ConfigUrl {
@@ -48,15 +60,36 @@ impl ConfigUrl for Config {
 
 impl From<ServerArgs> for Config {
     fn from(value: ServerArgs) -> Self {
+        let default_corpus = CorpusConfig {
+            name: value.corpus_name,
+            index_url: value.index_url,
+            index_database_url: value.index_database_url,
+            docstore_url: value.docstore_url,
+            embed_profile: value.embed_profile.with_overrides(
+                value.embed_query_prefix,
+                value.embed_document_prefix,
+                value.embed_normalize,
+                value.embed_max_input_chars,
+            ),
+            provenance: value.provenance,
+        };
+        let corpora = std::iter::once(default_corpus)
+            .chain(value.corpus)
+            .collect();
+
         Config {
             api_key: value.api_key,
-            docstore_url: value.docstore_url,
+            corpora,
             host: value.host,
-            index_url: value.index_url,
             port: value.port,
             protocol: "http".to_string(),
             redis_url: value.redis_url,
+            document_cache: value.document_cache,
+            document_cache_ttl: Duration::from_secs(value.document_cache_ttl),
+            document_cache_bytes: value.document_cache_bytes,
             system_prompt_template_path: value.system_prompt_path,
+            default_system_template: value.default_system_template,
+            default_chat_template: value.default_chat_template,
             llm_kind: value.llm_kind,
             llm_name: value.llm_name,
             llm_endpoint: value.llm_endpoint,
@@ -64,6 +97,11 @@ impl From<ServerArgs> for Config {
             embed_name: value.embed_name,
             embed_endpoint: value.embed_endpoint,
             embed_url: value.embed_url,
+            embed_input_name: value.embed_input_name,
+            embed_output_name: value.embed_output_name,
+            embed_batch_size: value.embed_batch_size,
+            embedding_cache_ttl: Duration::from_secs(value.embedding_cache_ttl),
+            embedding_cache_capacity: value.embedding_cache_capacity,
         }
     }
 }
@@ -71,9 +109,11 @@ impl From<ServerArgs> for Config {
 impl Display for Config {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Config {
-            docstore_url,
-            index_url,
+            corpora,
             redis_url,
+            document_cache,
+            document_cache_ttl: _,
+            document_cache_bytes: _,
             api_key: _,
             host: _,
             llm_kind: _,
@@ -83,15 +123,28 @@ impl Display for Config {
             embed_name,
             embed_endpoint,
             embed_url,
+            embed_input_name: _,
+            embed_output_name: _,
+            embed_batch_size: _,
+            embedding_cache_ttl: _,
+            embedding_cache_capacity: _,
             port: _,
             protocol: _,
-            system_prompt_template_path: _,
+            system_prompt_template_path,
+            default_system_template,
+            default_chat_template,
         } = self;
 
-        let docstore_url = docstore_url.as_str().green();
-        let redis_url = redis_url.as_str().green();
+        let redis = match redis_url {
+            Some(redis_url) => format!("Using redis at {}.\n", redis_url.as_str().green()),
+            None => String::new(),
+        };
+        let document_cache = document_cache.to_string().green();
 
-        let index_url = index_url.as_str().green();
+        let corpora = corpora
+            .iter()
+            .map(|corpus| format!("\n    {}", corpus.to_string().green()))
+            .collect::<String>();
 
         let embed_url = embed_url.as_str().blue();
         let embed_endpoint = format!("{embed_endpoint}").as_str().blue();
@@ -101,6 +154,10 @@ impl Display for Config {
         let llm_endpoint = format!("{llm_endpoint}").as_str().blue();
         let llm_model = llm_name.display().to_string().bright_blue();
 
+        let system_prompt_template_path = system_prompt_template_path.display().to_string().green();
+        let default_system_template = default_system_template.bright_blue();
+        let default_chat_template = default_chat_template.bright_blue();
+
         let engine_url = self.url();
         let [engine_conversation_path, engine_query_path, engine_api_doc_path] = [
             engine_url.join("streaming_conversation").unwrap(),
@@ -115,13 +172,14 @@ impl Display for Config {
     Serving conversations on {engine_conversation_path}.
     Service queries on {engine_query_path}.
     Serving OpenAPI documentation on {engine_api_doc_path}.
-Using redis at {redis_url}.
-Using index at {index_url}.
-Using docstore at {docstore_url}.
+{redis}Using {document_cache} document cache.
+Using corpora:{corpora}
 Using {embed_endpoint} embedding service at {embed_url}.
     Using {embed_name}.
 Using {llm_endpoint} service at {llm_url}.
-    Using {llm_model}."#,
+    Using {llm_model}.
+Using prompt templates at {system_prompt_template_path}.
+    Using system/{default_system_template} and chat/{default_chat_template} by default."#,
         )
     }
 }
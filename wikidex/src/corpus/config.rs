@@ -0,0 +1,131 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use url::Url;
+
+use crate::{embedding_client::EmbeddingProfile, formatter::ProvenanceKind};
+
+/// Where a corpus lives and how it was embedded.
+///
+/// Additional corpora are given on the command line as comma separated `key=value` pairs, e.g.
+/// `name=handbook,index=file:///data/handbook_index.sqlite,docstore=sqlite:///data/handbook_docstore.sqlite,profile=e5`.
+/// `name`, `index` and `docstore` are required, `index_database` is only read to verify the
+/// embedding profile, `profile` defaults to `raw` and `provenance` to `internal`.
+#[derive(Debug, Clone)]
+pub(crate) struct CorpusConfig {
+    pub(crate) name: String,
+    pub(crate) index_url: Url,
+    pub(crate) index_database_url: Option<Url>,
+    pub(crate) docstore_url: Url,
+    pub(crate) embed_profile: EmbeddingProfile,
+    pub(crate) provenance: ProvenanceKind,
+}
+
+impl Display for CorpusConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let CorpusConfig {
+            name,
+            index_url,
+            index_database_url: _,
+            docstore_url,
+            embed_profile,
+            provenance,
+        } = self;
+        write!(
+            f,
+            "{name} ({provenance}): index at {index_url}, docstore at {docstore_url}, embedding profile {embed_profile}"
+        )
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ParseCorpusConfigError {
+    MissingKey(&'static str),
+    UnknownKey(String),
+    InvalidValue(String, String),
+}
+impl Error for ParseCorpusConfigError {}
+impl Display for ParseCorpusConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseCorpusConfigError::MissingKey(key) => {
+                write!(f, "Unable to parse corpus: `{key}` is required")
+            }
+            ParseCorpusConfigError::UnknownKey(key) => write!(
+                f,
+                "Unable to parse corpus: unknown key `{key}`. Must be one of [name, index, index_database, docstore, profile, provenance]"
+            ),
+            ParseCorpusConfigError::InvalidValue(key, e) => {
+                write!(f, "Unable to parse corpus: `{key}`: {e}")
+            }
+        }
+    }
+}
+
+impl FromStr for CorpusConfig {
+    type Err = ParseCorpusConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut index_url = None;
+        let mut index_database_url = None;
+        let mut docstore_url = None;
+        let mut embed_profile = EmbeddingProfile::from_str("raw").unwrap();
+        let mut provenance = ProvenanceKind::Internal;
+
+        for pair in s.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| ParseCorpusConfigError::UnknownKey(pair.to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            let invalid = |e: &dyn Display| {
+                ParseCorpusConfigError::InvalidValue(key.to_string(), e.to_string())
+            };
+            match key {
+                "name" => name = Some(value.to_string()),
+                "index" => index_url = Some(Url::parse(value).map_err(|e| invalid(&e))?),
+                "index_database" => {
+                    index_database_url = Some(Url::parse(value).map_err(|e| invalid(&e))?)
+                }
+                "docstore" => docstore_url = Some(Url::parse(value).map_err(|e| invalid(&e))?),
+                "profile" => {
+                    embed_profile = EmbeddingProfile::from_str(value).map_err(|e| invalid(&e))?
+                }
+                "provenance" => {
+                    provenance = ProvenanceKind::from_str(value).map_err(|e| invalid(&e))?
+                }
+                _ => return Err(ParseCorpusConfigError::UnknownKey(key.to_string())),
+            }
+        }
+
+        Ok(Self {
+            name: name.ok_or(ParseCorpusConfigError::MissingKey("name"))?,
+            index_url: index_url.ok_or(ParseCorpusConfigError::MissingKey("index"))?,
+            index_database_url,
+            docstore_url: docstore_url.ok_or(ParseCorpusConfigError::MissingKey("docstore"))?,
+            embed_profile,
+            provenance,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::formatter::ProvenanceKind;
+
+    use super::CorpusConfig;
+
+    #[test]
+    fn parse_corpus() {
+        let corpus = CorpusConfig::from_str(
+            "name=handbook, index=file:///data/handbook_index.sqlite,docstore=sqlite:///data/handbook_docstore.sqlite,profile=e5",
+        )
+        .unwrap();
+        assert_eq!(corpus.name, "handbook");
+        assert_eq!(corpus.embed_profile.name, "e5");
+        assert_eq!(corpus.provenance, ProvenanceKind::Internal);
+        assert!(CorpusConfig::from_str("name=handbook,docstore=sqlite:///d.sqlite").is_err());
+        assert!(CorpusConfig::from_str("name=a,index=file:///i,docstore=sqlite:///d,x=1").is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt::{self, Display, Formatter};
+
+use url::Url;
+
+use crate::{
+    docstore::DocstoreLoadError, embedding_client::EmbeddingServiceError, index::IndexLoadError,
+    schema::SchemaVersionError,
+};
+
+#[derive(Debug)]
+pub(crate) enum CorpusLoadError {
+    Docstore(DocstoreLoadError),
+    DuplicateName(String),
+    Embedding(EmbeddingServiceError),
+    Index(IndexLoadError),
+    Schema(SchemaVersionError),
+    UnsupportedDocstore(Url),
+}
+
+impl From<DocstoreLoadError> for CorpusLoadError {
+    fn from(value: DocstoreLoadError) -> Self {
+        Self::Docstore(value)
+    }
+}
+impl From<EmbeddingServiceError> for CorpusLoadError {
+    fn from(value: EmbeddingServiceError) -> Self {
+        Self::Embedding(value)
+    }
+}
+impl From<IndexLoadError> for CorpusLoadError {
+    fn from(value: IndexLoadError) -> Self {
+        Self::Index(value)
+    }
+}
+impl From<SchemaVersionError> for CorpusLoadError {
+    fn from(value: SchemaVersionError) -> Self {
+        Self::Schema(value)
+    }
+}
+
+impl std::error::Error for CorpusLoadError {}
+
+impl Display for CorpusLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CorpusLoadError::Docstore(e) => write!(f, "CorpusLoadError: {e}"),
+            CorpusLoadError::DuplicateName(name) => {
+                write!(f, "CorpusLoadError: Corpus {name} is given twice")
+            }
+            CorpusLoadError::Embedding(e) => write!(f, "CorpusLoadError: {e}"),
+            CorpusLoadError::Index(e) => write!(f, "CorpusLoadError: {e}"),
+            CorpusLoadError::Schema(e) => write!(f, "CorpusLoadError: {e}"),
+            CorpusLoadError::UnsupportedDocstore(url) => {
+                write!(f, "CorpusLoadError: Unsupported docstore {url}")
+            }
+        }
+    }
+}
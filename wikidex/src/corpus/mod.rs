@@ -0,0 +1,206 @@
+mod config;
+mod error;
+
+pub(crate) use config::CorpusConfig;
+pub(crate) use error::CorpusLoadError;
+
+use std::sync::Arc;
+
+use url::Url;
+
+#[cfg(feature = "sqlite")]
+use crate::schema::Store;
+use crate::{
+    docstore::{Docstore, DocumentStoreImpl, SharedDocumentCache},
+    embedding_client::EmbeddingProfile,
+    index::SearchServiceImpl,
+};
+
+/// A searchable collection of documents: its index, its docstore, and the embedding profile the
+/// index was built with.
+pub(crate) struct Corpus {
+    pub(crate) name: String,
+    pub(crate) index: SearchServiceImpl,
+    pub(crate) docstore: DocumentStoreImpl,
+    pub(crate) embedding_profile: EmbeddingProfile,
+}
+
+impl Corpus {
+    pub(crate) async fn new(
+        config: CorpusConfig,
+        document_cache: &Arc<SharedDocumentCache>,
+    ) -> Result<Self, CorpusLoadError> {
+        let CorpusConfig {
+            name,
+            index_url,
+            index_database_url,
+            docstore_url,
+            embed_profile,
+            provenance,
+        } = config;
+
+        let docstore = match docstore_url.scheme() {
+            #[cfg(feature = "sqlite")]
+            "sqlite" => DocumentStoreImpl::Sqlite(
+                Docstore::<sqlx::Sqlite>::new(
+                    &name,
+                    provenance,
+                    &docstore_url,
+                    document_cache.clone(),
+                )
+                .await?,
+            ),
+            #[cfg(feature = "postgres")]
+            "postgres" => DocumentStoreImpl::Postgres(
+                Docstore::<sqlx::Postgres>::new(
+                    &name,
+                    provenance,
+                    &docstore_url,
+                    document_cache.clone(),
+                )
+                .await?,
+            ),
+            _ => return Err(CorpusLoadError::UnsupportedDocstore(docstore_url)),
+        };
+
+        // A local index is read from the index database itself, a pgvector database records the
+        // profile `wikidex index pgvector` copied into it.
+        let index_database_url = match (index_database_url, index_url.scheme()) {
+            (Some(url), _) => Some(url),
+            (None, "file") => Url::parse(&format!("sqlite://{}", index_url.path())).ok(),
+            (None, "postgres") => Some(index_url.clone()),
+            (None, _) => None,
+        };
+        match &index_database_url {
+            Some(index_database_url) => {
+                #[cfg(feature = "sqlite")]
+                if index_database_url.scheme() == "sqlite" {
+                    Store::Index.verify(index_database_url).await?;
+                }
+                embed_profile.verify(index_database_url).await?;
+            }
+            None => log::warn!(
+                "No index database given for {name}, the embedding profile and schema version are not checked against the index."
+            ),
+        }
+
+        let index = SearchServiceImpl::new(index_url).await?;
+
+        Ok(Self {
+            name,
+            index,
+            docstore,
+            embedding_profile: embed_profile,
+        })
+    }
+}
+
+/// The corpora a request may search, the first is searched when a request names none.
+pub(crate) struct CorpusRegistry {
+    corpora: Vec<Corpus>,
+}
+
+impl CorpusRegistry {
+    pub(crate) fn new(corpora: Vec<Corpus>) -> Result<Self, CorpusLoadError> {
+        for (position, corpus) in corpora.iter().enumerate() {
+            if corpora[..position].iter().any(|c| c.name == corpus.name) {
+                return Err(CorpusLoadError::DuplicateName(corpus.name.clone()));
+            }
+        }
+        Ok(Self { corpora })
+    }
+
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.corpora.iter().map(|c| c.name.clone()).collect()
+    }
+
+    pub(crate) fn default_name(&self) -> Option<&str> {
+        self.corpora.first().map(|c| c.name.as_str())
+    }
+
+    /// The named corpora, or the default one. Fails on the first unknown name.
+    pub(crate) fn select(&self, names: Option<&[String]>) -> Result<Vec<&Corpus>, String> {
+        match names {
+            Some(names) if !names.is_empty() => names
+                .iter()
+                .map(|name| {
+                    self.corpora
+                        .iter()
+                        .find(|c| &c.name == name)
+                        .ok_or_else(|| name.clone())
+                })
+                .collect(),
+            _ => Ok(self.corpora.iter().take(1).collect()),
+        }
+    }
+}
+
+/// Merges the scored results of several corpora into the `k` best.
+///
+/// Scored results are ranked by their similarity, which the indices report on the same scale
+/// for the same embedding model. A list without scores only orders its results, so it is
+/// interleaved with the scored results one by one. The original scores are returned. A single
+/// list is returned as ranked by its index.
+pub(crate) fn merge<T>(results: Vec<Vec<(T, Option<f32>)>>, k: usize) -> Vec<(T, Option<f32>)> {
+    if results.len() == 1 {
+        return results.into_iter().flatten().take(k).collect();
+    }
+
+    let (scored, unscored): (Vec<_>, Vec<_>) = results
+        .into_iter()
+        .partition(|result| result.iter().all(|(_, score)| score.is_some()));
+    let mut scored = scored.into_iter().flatten().collect::<Vec<_>>();
+    scored.sort_by(|(_, a), (_, b)| b.unwrap_or(f32::MIN).total_cmp(&a.unwrap_or(f32::MIN)));
+
+    let mut lists = std::iter::once(scored)
+        .chain(unscored)
+        .map(Vec::into_iter)
+        .collect::<Vec<_>>();
+    let mut merged = Vec::with_capacity(k);
+    while merged.len() < k {
+        let before = merged.len();
+        for list in lists.iter_mut() {
+            if merged.len() == k {
+                break;
+            }
+            merged.extend(list.next());
+        }
+        if merged.len() == before {
+            break;
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod test {
+    use super::merge;
+
+    #[test]
+    fn merge_ranks_corpora_by_similarity() {
+        let wikipedia = vec![("w1", Some(0.9)), ("w2", Some(0.8)), ("w3", Some(0.7))];
+        let handbook = vec![("h1", Some(0.3)), ("h2", Some(0.1))];
+        let merged = merge(vec![wikipedia, handbook], 3);
+        assert_eq!(
+            merged,
+            vec![("w1", Some(0.9)), ("w2", Some(0.8)), ("w3", Some(0.7))]
+        );
+    }
+
+    #[test]
+    fn single_corpus_keeps_its_ranking() {
+        let merged = merge(vec![vec![("a", Some(0.5)), ("b", Some(0.4))]], 1);
+        assert_eq!(merged, vec![("a", Some(0.5))]);
+    }
+
+    #[test]
+    fn unscored_corpus_is_merged_by_rank() {
+        let wikipedia = vec![("w1", Some(0.9)), ("w2", Some(0.1))];
+        let face = vec![("f1", None), ("f2", None), ("f3", None), ("f4", None)];
+        let merged = merge(vec![wikipedia, face], 3);
+        assert_eq!(
+            merged,
+            vec![("w1", Some(0.9)), ("f1", None), ("w2", Some(0.1))]
+        );
+    }
+}
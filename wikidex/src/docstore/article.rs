@@ -0,0 +1,37 @@
+use crate::formatter::Provenance;
+
+/// An article of the docstore, as found by its title.
+#[derive(Clone, Debug)]
+pub(crate) struct Article {
+    pub(crate) id: i64,
+    pub(crate) provenance: Provenance,
+}
+
+/// A `LIKE` pattern matching titles that contain the words of `title` in order, `\` escaping the
+/// wildcards typed in `title`.
+pub(super) fn fuzzy_title_pattern(title: &str) -> String {
+    let words = title
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .flat_map(|c| match c {
+                    '%' | '_' | '\\' => vec!['\\', c],
+                    _ => vec![c],
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+    format!("%{}%", words.join("%"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::fuzzy_title_pattern;
+
+    #[test]
+    fn fuzzy_title() {
+        assert_eq!(fuzzy_title_pattern("austrian  german"), "%austrian%german%");
+        assert_eq!(fuzzy_title_pattern("100% pure_"), "%100\\%%pure\\_%");
+        assert_eq!(fuzzy_title_pattern(""), "%%");
+    }
+}
@@ -1,7 +1,20 @@
-use redis::AsyncCommands;
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use redis::{aio::MultiplexedConnection, AsyncCommands, RedisError};
 use sqlx::Database;
 
-use super::{document::Document, Docstore, DocstoreRetrieveError, DocumentStoreImpl};
+use super::{
+    document::Document, Docstore, DocstoreLoadError, DocstoreRetrieveError, DocumentStoreImpl,
+};
 
 pub(super) trait DocumentCache: Send + Sync {
     async fn insert_into_cache(
@@ -15,6 +28,222 @@ pub(super) trait DocumentCache: Send + Sync {
     ) -> Result<(Vec<Document>, Vec<i64>), DocstoreRetrieveError>;
 }
 
+/// Where retrieved documents are cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DocumentCacheKind {
+    None,
+    Memory,
+    Redis,
+}
+
+impl Display for DocumentCacheKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentCacheKind::None => write!(f, "none"),
+            DocumentCacheKind::Memory => write!(f, "memory"),
+            DocumentCacheKind::Redis => write!(f, "redis"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ParseDocumentCacheKindError(String);
+impl std::error::Error for ParseDocumentCacheKindError {}
+impl Display for ParseDocumentCacheKindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unable to parse document cache {}. Must be one of [none, memory, redis]",
+            self.0
+        )
+    }
+}
+
+impl FromStr for DocumentCacheKind {
+    type Err = ParseDocumentCacheKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(DocumentCacheKind::None),
+            "memory" => Ok(DocumentCacheKind::Memory),
+            "redis" => Ok(DocumentCacheKind::Redis),
+            _ => Err(ParseDocumentCacheKindError(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct DocumentCacheStats {
+    pub(crate) kind: DocumentCacheKind,
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    /// Documents the memory cache dropped to stay within its size.
+    pub(crate) evictions: u64,
+    pub(crate) entries: u64,
+    pub(crate) bytes: u64,
+}
+
+/// Archived documents with the time they were inserted, least recently used first out.
+struct MemoryCache {
+    entries: LruCache<String, (Instant, Vec<u8>)>,
+    bytes: usize,
+    capacity_bytes: usize,
+}
+
+enum Backend {
+    None,
+    Memory(Mutex<MemoryCache>),
+    Redis(MultiplexedConnection),
+}
+
+/// Documents retrieved from the docstores, keyed by corpus and document id so every corpus
+/// shares one cache.
+///
+/// `memory` keeps archived documents in process and evicts the least recently used once they
+/// take more than `capacity_bytes`. `redis` shares the cache between servers. A `ttl` of zero
+/// never expires entries.
+pub(crate) struct SharedDocumentCache {
+    backend: Backend,
+    ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl SharedDocumentCache {
+    pub(crate) fn new(
+        kind: DocumentCacheKind,
+        ttl: Duration,
+        capacity_bytes: usize,
+        redis: Option<MultiplexedConnection>,
+    ) -> Result<Self, DocstoreLoadError> {
+        let backend = match kind {
+            DocumentCacheKind::None => Backend::None,
+            DocumentCacheKind::Memory => Backend::Memory(Mutex::new(MemoryCache {
+                entries: LruCache::unbounded(),
+                bytes: 0,
+                capacity_bytes,
+            })),
+            DocumentCacheKind::Redis => Backend::Redis(redis.ok_or(DocstoreLoadError::NoRedis)?),
+        };
+        Ok(Self {
+            backend,
+            ttl,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        })
+    }
+
+    pub(crate) fn kind(&self) -> DocumentCacheKind {
+        match self.backend {
+            Backend::None => DocumentCacheKind::None,
+            Backend::Memory(_) => DocumentCacheKind::Memory,
+            Backend::Redis(_) => DocumentCacheKind::Redis,
+        }
+    }
+
+    pub(crate) fn stats(&self) -> DocumentCacheStats {
+        let (entries, bytes) = match &self.backend {
+            Backend::Memory(memory) => memory
+                .lock()
+                .map(|memory| (memory.entries.len() as u64, memory.bytes as u64))
+                .unwrap_or_default(),
+            _ => (0, 0),
+        };
+        DocumentCacheStats {
+            kind: self.kind(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries,
+            bytes,
+        }
+    }
+
+    /// The cached document of every key, in order.
+    async fn get(&self, keys: Vec<String>) -> Result<Vec<Option<Document>>, DocstoreRetrieveError> {
+        let documents = match &self.backend {
+            Backend::None => vec![None; keys.len()],
+            Backend::Memory(memory) => {
+                let mut memory = memory.lock().unwrap_or_else(|e| e.into_inner());
+                keys.iter().map(|key| memory.get(key, self.ttl)).collect()
+            }
+            Backend::Redis(redis) => {
+                let mut redis = redis.clone();
+                redis::cmd("MGET").arg(keys).query_async(&mut redis).await?
+            }
+        };
+        let hits = documents.iter().flatten().count() as u64;
+        self.hits.fetch_add(hits, Ordering::Relaxed);
+        self.misses
+            .fetch_add(documents.len() as u64 - hits, Ordering::Relaxed);
+        Ok(documents)
+    }
+
+    fn insert(&self, key: String, document: Document) {
+        match &self.backend {
+            Backend::None => {}
+            Backend::Memory(memory) => {
+                let mut memory = memory.lock().unwrap_or_else(|e| e.into_inner());
+                let evicted = memory.insert(key, document.to_bytes());
+                self.evictions.fetch_add(evicted, Ordering::Relaxed);
+            }
+            Backend::Redis(redis) => {
+                let mut redis = redis.clone();
+                let ttl = self.ttl.as_secs();
+                tokio::spawn(async move {
+                    let result: Result<(), RedisError> = if ttl == 0 {
+                        redis.set(key, document).await
+                    } else {
+                        redis.set_ex(key, document, ttl).await
+                    };
+                    if let Err(e) = result {
+                        log::error!("{}", DocstoreRetrieveError::Redis(e));
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl MemoryCache {
+    fn get(&mut self, key: &str, ttl: Duration) -> Option<Document> {
+        let (inserted, _) = self.entries.get(key)?;
+        if !ttl.is_zero() && inserted.elapsed() > ttl {
+            if let Some((key, (_, bytes))) = self.entries.pop_entry(key) {
+                self.bytes -= key.len() + bytes.len();
+            }
+            return None;
+        }
+        self.entries
+            .peek(key)
+            .and_then(|(_, bytes)| Document::from_bytes(bytes))
+    }
+
+    /// Returns the number of documents evicted to make room.
+    fn insert(&mut self, key: String, bytes: Vec<u8>) -> u64 {
+        let size = key.len() + bytes.len();
+        if size > self.capacity_bytes {
+            return 0;
+        }
+        self.bytes += size;
+        if let Some((key, (_, bytes))) = self.entries.push(key, (Instant::now(), bytes)) {
+            self.bytes -= key.len() + bytes.len();
+        }
+
+        let mut evicted = 0;
+        while self.bytes > self.capacity_bytes {
+            let Some((key, (_, bytes))) = self.entries.pop_lru() else {
+                break;
+            };
+            self.bytes -= key.len() + bytes.len();
+            evicted += 1;
+        }
+        evicted
+    }
+}
+
 impl DocumentCache for DocumentStoreImpl {
     async fn insert_into_cache(
         &self,
@@ -46,28 +275,20 @@ impl<DB: Database> DocumentCache for Docstore<DB> {
         &self,
         indices: &[i64],
     ) -> Result<(Vec<Document>, Vec<i64>), DocstoreRetrieveError> {
-        let mut cache = self.cache.clone();
-        let result: Vec<Option<Document>> = redis::cmd("MGET")
-            .arg(indices)
-            .query_async(&mut cache)
-            .await?;
-        let hits = result.into_iter().flatten().collect::<Vec<_>>();
-        if hits.is_empty() {
-            log::debug!("Cache Miss: {indices:?}");
-            return Ok((vec![], indices.to_vec()));
-        }
-
-        let cache_hits = hits.iter().map(|d| d.index).collect::<Vec<_>>();
-        let cache_misses = indices
+        let keys = indices
             .iter()
-            .filter_map(|index| {
-                if cache_hits.contains(index) {
-                    None
-                } else {
-                    Some(*index)
-                }
-            })
+            .map(|index| self.cache_key(*index))
             .collect::<Vec<_>>();
+        let cached = self.cache.get(keys).await?;
+
+        let mut hits = vec![];
+        let mut cache_misses = vec![];
+        for (index, document) in indices.iter().zip(cached) {
+            match document {
+                Some(document) => hits.push(document),
+                None => cache_misses.push(*index),
+            }
+        }
         if !cache_misses.is_empty() {
             log::debug!("Cache Miss: {cache_misses:?}");
         }
@@ -78,20 +299,32 @@ impl<DB: Database> DocumentCache for Docstore<DB> {
         index: i64,
         data: Document,
     ) -> Result<(), DocstoreRetrieveError> {
-        let mut cache = self.cache.clone();
-        tokio::spawn(async move {
-            let result: Result<(), DocstoreRetrieveError> = cache
-                .set(index, data)
-                .await
-                .map_err(DocstoreRetrieveError::Redis);
-            if let Err(e) = result {
-                log::error!("{e}");
-                Err(e)
-            } else {
-                Ok::<(), DocstoreRetrieveError>(())
-            }
-        });
-
+        self.cache.insert(self.cache_key(index), data);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use lru::LruCache;
+
+    use super::MemoryCache;
+
+    #[test]
+    fn memory_cache_evicts_least_recently_used() {
+        let mut memory = MemoryCache {
+            entries: LruCache::unbounded(),
+            bytes: 0,
+            capacity_bytes: 10,
+        };
+        assert_eq!(memory.insert(String::from("a"), vec![0; 4]), 0);
+        assert_eq!(memory.insert(String::from("b"), vec![0; 4]), 0);
+        let _ = memory.entries.get("a");
+        assert_eq!(memory.insert(String::from("c"), vec![0; 4]), 1);
+        assert!(memory.entries.contains("a"));
+        assert!(!memory.entries.contains("b"));
+        assert_eq!(memory.bytes, 10);
+        assert_eq!(memory.insert(String::from("d"), vec![0; 20]), 0);
+        assert_eq!(memory.entries.len(), 2);
+    }
+}
@@ -1,6 +1,8 @@
+use std::collections::HashSet;
+
 use super::{
-    cache::DocumentCache, document::Document, DocstoreRetrieveError, DocumentStore,
-    DocumentStoreImpl,
+    cache::DocumentCache, document::Document, Article, DocstoreRetrieveError, DocumentFilter,
+    DocumentStore, DocumentStoreImpl,
 };
 
 pub(super) trait DocumentDatabase: Send + Sync {
@@ -8,6 +10,23 @@ pub(super) trait DocumentDatabase: Send + Sync {
         &self,
         indices: &[i64],
     ) -> Result<Vec<Document>, DocstoreRetrieveError>;
+    /// The subset of `indices` whose documents match the filter.
+    async fn matching_from_db(
+        &self,
+        indices: &[i64],
+        filter: &DocumentFilter,
+    ) -> Result<HashSet<i64>, DocstoreRetrieveError>;
+    /// The ids of the documents of `article` in section order.
+    async fn article_documents_from_db(
+        &self,
+        article: i64,
+    ) -> Result<Vec<i64>, DocstoreRetrieveError>;
+    async fn find_articles_from_db(
+        &self,
+        title: &str,
+        fuzzy: bool,
+        limit: usize,
+    ) -> Result<Vec<Article>, DocstoreRetrieveError>;
 }
 
 impl DocumentDatabase for DocumentStoreImpl {
@@ -22,6 +41,55 @@ impl DocumentDatabase for DocumentStoreImpl {
             DocumentStoreImpl::Sqlite(docstore) => docstore.retreive_from_db(indices).await,
         }
     }
+
+    async fn matching_from_db(
+        &self,
+        indices: &[i64],
+        filter: &DocumentFilter,
+    ) -> Result<HashSet<i64>, DocstoreRetrieveError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            DocumentStoreImpl::Postgres(docstore) => {
+                docstore.matching_from_db(indices, filter).await
+            }
+            #[cfg(feature = "sqlite")]
+            DocumentStoreImpl::Sqlite(docstore) => docstore.matching_from_db(indices, filter).await,
+        }
+    }
+
+    async fn article_documents_from_db(
+        &self,
+        article: i64,
+    ) -> Result<Vec<i64>, DocstoreRetrieveError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            DocumentStoreImpl::Postgres(docstore) => {
+                docstore.article_documents_from_db(article).await
+            }
+            #[cfg(feature = "sqlite")]
+            DocumentStoreImpl::Sqlite(docstore) => {
+                docstore.article_documents_from_db(article).await
+            }
+        }
+    }
+
+    async fn find_articles_from_db(
+        &self,
+        title: &str,
+        fuzzy: bool,
+        limit: usize,
+    ) -> Result<Vec<Article>, DocstoreRetrieveError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            DocumentStoreImpl::Postgres(docstore) => {
+                docstore.find_articles_from_db(title, fuzzy, limit).await
+            }
+            #[cfg(feature = "sqlite")]
+            DocumentStoreImpl::Sqlite(docstore) => {
+                docstore.find_articles_from_db(title, fuzzy, limit).await
+            }
+        }
+    }
 }
 
 impl<T> DocumentStore for T
@@ -29,6 +97,9 @@ where
     T: DocumentDatabase + DocumentCache,
 {
     async fn retreive(&self, indices: &[i64]) -> Result<Vec<Document>, DocstoreRetrieveError> {
+        if indices.is_empty() {
+            return Ok(vec![]);
+        }
         let (cached_documents, cache_misses) = self.retreive_from_cache(indices).await?;
 
         let missed_documents = if !cache_misses.is_empty() {
@@ -52,4 +123,34 @@ where
 
         Ok(documents)
     }
+
+    async fn matching(
+        &self,
+        indices: &[i64],
+        filter: &DocumentFilter,
+    ) -> Result<HashSet<i64>, DocstoreRetrieveError> {
+        if filter.is_empty() {
+            return Ok(indices.iter().copied().collect());
+        }
+        self.matching_from_db(indices, filter).await
+    }
+
+    async fn article_documents(
+        &self,
+        article: i64,
+    ) -> Result<Vec<Document>, DocstoreRetrieveError> {
+        let indices = self.article_documents_from_db(article).await?;
+        let mut documents = self.retreive(&indices).await?;
+        documents.sort_by_key(|document| (document.ordinal, document.index));
+        Ok(documents)
+    }
+
+    async fn find_articles(
+        &self,
+        title: &str,
+        fuzzy: bool,
+        limit: usize,
+    ) -> Result<Vec<Article>, DocstoreRetrieveError> {
+        self.find_articles_from_db(title, fuzzy, limit).await
+    }
 }
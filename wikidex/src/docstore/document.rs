@@ -1,20 +1,70 @@
-use crate::formatter::{Provenance, TextFormatter};
+use crate::formatter::{Cite, Provenance, TextFormatter};
 use redis::{FromRedisValue, RedisError, RedisResult, ToRedisArgs, Value};
-use rkyv::{archived_root, Archive, Deserialize, Infallible, Serialize as RkyvSerialize};
+use rkyv::{
+    check_archived_root, AlignedVec, Archive, Deserialize, Infallible, Serialize as RkyvSerialize,
+};
 use serde::Serialize as SerdeSerialize;
 
 #[derive(Clone, RkyvSerialize, SerdeSerialize, Deserialize, Archive, Debug)]
+#[archive(check_bytes)]
 pub(crate) struct Document {
     pub(crate) index: i64,
+    /// The id of the article the document was split from.
+    pub(crate) article: i64,
     pub(crate) text: String,
+    /// The article title followed by the path of section headings, separated by `:`.
+    pub(crate) heading: String,
+    /// The last heading of the path, `None` for an article's lead and for documents written before
+    /// sections were recorded.
+    pub(crate) section: Option<String>,
+    /// The position of the section in its article, the lead section is 0.
+    pub(crate) ordinal: i64,
     pub(crate) provenance: Provenance,
 }
 
+impl Document {
+    /// The heading of the section the document was split from, `None` for an article's lead. For
+    /// documents written before sections were recorded it is guessed from `heading`, which is
+    /// wrong for headings containing `:`.
+    pub(crate) fn section(&self) -> Option<&str> {
+        if self.section.is_some() || self.ordinal == 0 {
+            return self.section.as_deref();
+        }
+        let title = self.provenance.title();
+        self.heading
+            .strip_prefix(title.as_str())?
+            .rsplit(':')
+            .next()
+            .filter(|section| !section.is_empty())
+    }
+
+    /// The url of the document's section, the article's url for its lead.
+    pub(crate) fn url(&self) -> String {
+        match self.section() {
+            Some(section) => self.provenance.section_url(section),
+            None => self.provenance.url(),
+        }
+    }
+
+    /// The archived form kept by the document caches.
+    pub(super) fn to_bytes(&self) -> Vec<u8> {
+        rkyv::to_bytes::<_, 2048>(self).unwrap().into_vec()
+    }
+
+    /// `None` unless `bytes` hold a valid archived document, so a corrupt or foreign cache entry
+    /// is a miss. The bytes are copied to meet the archive's alignment.
+    pub(super) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut aligned = AlignedVec::with_capacity(bytes.len());
+        aligned.extend_from_slice(bytes);
+        let archived = check_archived_root::<Document>(&aligned).ok()?;
+        archived.deserialize(&mut Infallible).ok()
+    }
+}
+
 impl FromRedisValue for Document {
     fn from_redis_value(v: &Value) -> RedisResult<Self> {
         if let Value::Data(bytes) = v {
-            let archived = unsafe { archived_root::<Document>(bytes.as_slice()) };
-            archived.deserialize(&mut Infallible).map_err(|_| {
+            Document::from_bytes(bytes.as_slice()).ok_or_else(|| {
                 RedisError::from((redis::ErrorKind::TypeError, "Deserialization failed"))
             })
         } else {
@@ -31,8 +81,7 @@ impl ToRedisArgs for Document {
     where
         W: ?Sized + redis::RedisWrite,
     {
-        let bytes = rkyv::to_bytes::<_, 2048>(self).unwrap();
-        out.write_arg(&bytes);
+        out.write_arg(&self.to_bytes());
     }
 }
 
@@ -41,3 +90,73 @@ impl TextFormatter for Document {
         format!("{}\n{}\n", self.index, self.text)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::Document;
+    use crate::formatter::{Provenance, Wiki};
+
+    fn document(heading: &str, section: Option<&str>) -> Document {
+        Document {
+            index: 0,
+            article: 0,
+            text: String::new(),
+            heading: heading.to_string(),
+            section: section.map(str::to_string),
+            ordinal: heading.matches(':').count() as i64,
+            provenance: Provenance::Wikipedia(
+                "Austrian German".to_string(),
+                NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+                1178374855,
+                Wiki::default(),
+            ),
+        }
+    }
+
+    #[test]
+    fn section_url() {
+        assert_eq!(document("Austrian German", None).section(), None);
+        assert_eq!(
+            document("Austrian German", None).url(),
+            "https://en.wikipedia.org/wiki/Austrian_German"
+        );
+        assert_eq!(
+            document(
+                "Austrian German:History:Standard Austrian German",
+                Some("Standard Austrian German")
+            )
+            .url(),
+            "https://en.wikipedia.org/wiki/Austrian_German#Standard_Austrian_German"
+        );
+    }
+
+    #[test]
+    fn section_may_contain_colons() {
+        let document = document(
+            "Austrian German:Grammar:Verbs: tenses",
+            Some("Verbs: tenses"),
+        );
+        assert_eq!(document.section(), Some("Verbs: tenses"));
+    }
+
+    #[test]
+    fn section_of_older_documents_is_taken_from_the_heading() {
+        assert_eq!(
+            document("Austrian German:History:Standard Austrian German", None).section(),
+            Some("Standard Austrian German")
+        );
+    }
+
+    #[test]
+    fn invalid_cache_bytes_are_a_miss() {
+        let bytes = document("Austrian German", None).to_bytes();
+        let decoded = Document::from_bytes(&bytes[1..]);
+        assert!(decoded.is_none());
+        assert!(Document::from_bytes(&[0xff; 64]).is_none());
+        let decoded = Document::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.heading, "Austrian German");
+    }
+}
@@ -1,15 +1,31 @@
 use std::fmt::{self, Debug, Display, Formatter};
 
+use crate::schema::SchemaVersionError;
+
 #[derive(Debug)]
 pub enum DocstoreLoadError {
     Database(sqlx::error::Error),
     Redis(redis::RedisError),
+    /// The redis document cache was chosen without a Redis to connect to.
+    NoRedis,
+    Schema(SchemaVersionError),
+}
+#[cfg(all(feature = "migrate", feature = "sqlite"))]
+#[derive(Debug)]
+pub enum DocstoreMigrateError {
+    Database(sqlx::error::Error),
+    Schema(SchemaVersionError),
 }
 #[derive(Debug)]
 pub enum DocstoreRetrieveError {
     IndexOutOfRange,
     Database(sqlx::error::Error),
     Redis(redis::RedisError),
+    /// A stored document whose codec is unknown or whose text does not decompress.
+    Undecodable {
+        index: i64,
+        reason: String,
+    },
 }
 
 impl From<sqlx::error::Error> for DocstoreLoadError {
@@ -22,6 +38,23 @@ impl From<redis::RedisError> for DocstoreLoadError {
         Self::Redis(value)
     }
 }
+impl From<SchemaVersionError> for DocstoreLoadError {
+    fn from(value: SchemaVersionError) -> Self {
+        Self::Schema(value)
+    }
+}
+#[cfg(all(feature = "migrate", feature = "sqlite"))]
+impl From<sqlx::error::Error> for DocstoreMigrateError {
+    fn from(value: sqlx::error::Error) -> Self {
+        Self::Database(value)
+    }
+}
+#[cfg(all(feature = "migrate", feature = "sqlite"))]
+impl From<SchemaVersionError> for DocstoreMigrateError {
+    fn from(value: SchemaVersionError) -> Self {
+        Self::Schema(value)
+    }
+}
 impl From<sqlx::error::Error> for DocstoreRetrieveError {
     fn from(value: sqlx::error::Error) -> Self {
         Self::Database(value)
@@ -35,12 +68,31 @@ impl From<redis::RedisError> for DocstoreRetrieveError {
 
 impl std::error::Error for DocstoreLoadError {}
 impl std::error::Error for DocstoreRetrieveError {}
+#[cfg(all(feature = "migrate", feature = "sqlite"))]
+impl std::error::Error for DocstoreMigrateError {}
 
 impl Display for DocstoreLoadError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             DocstoreLoadError::Database(e) => write!(f, "DocstoreLoadError: Database: {e}"),
             DocstoreLoadError::Redis(e) => write!(f, "DocstoreLoadError: Redis: {e}"),
+            DocstoreLoadError::NoRedis => {
+                write!(
+                    f,
+                    "DocstoreLoadError: The redis document cache needs --redis-url"
+                )
+            }
+            DocstoreLoadError::Schema(e) => write!(f, "DocstoreLoadError: {e}"),
+        }
+    }
+}
+
+#[cfg(all(feature = "migrate", feature = "sqlite"))]
+impl Display for DocstoreMigrateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DocstoreMigrateError::Database(e) => write!(f, "DocstoreMigrateError: Database: {e}"),
+            DocstoreMigrateError::Schema(e) => write!(f, "DocstoreMigrateError: {e}"),
         }
     }
 }
@@ -57,6 +109,12 @@ impl Display for DocstoreRetrieveError {
             DocstoreRetrieveError::Redis(e) => {
                 write!(f, "DocstoreRetrieveError: Redis: {e}")
             }
+            DocstoreRetrieveError::Undecodable { index, reason } => {
+                write!(
+                    f,
+                    "DocstoreRetrieveError: Document {index} can not be decoded: {reason}"
+                )
+            }
         }
     }
 }
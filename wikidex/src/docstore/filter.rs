@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+
+use chrono::{Days, NaiveDate};
+
+use crate::index::{IndexSearchError, SearchFilter};
+
+use super::DocumentStore;
+
+/// Metadata criteria a document must meet, every given criterion must hold.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DocumentFilter {
+    pub(crate) article_ids: Option<Vec<i64>>,
+    /// Inclusive.
+    pub(crate) modified_since: Option<NaiveDate>,
+    /// Inclusive.
+    pub(crate) modified_until: Option<NaiveDate>,
+    pub(crate) category: Option<String>,
+}
+
+impl DocumentFilter {
+    pub(crate) fn is_empty(&self) -> bool {
+        let DocumentFilter {
+            article_ids,
+            modified_since,
+            modified_until,
+            category,
+        } = self;
+        article_ids.is_none()
+            && modified_since.is_none()
+            && modified_until.is_none()
+            && category.is_none()
+    }
+
+    /// Lower bound of the modification date in milliseconds, inclusive.
+    pub(super) fn modified_since_millis(&self) -> Option<i64> {
+        self.modified_since.map(start_of_day_millis)
+    }
+
+    /// Upper bound of the modification date in milliseconds, exclusive.
+    pub(super) fn modified_before_millis(&self) -> Option<i64> {
+        self.modified_until
+            .and_then(|date| date.checked_add_days(Days::new(1)))
+            .map(start_of_day_millis)
+    }
+
+    pub(super) fn category(&self) -> Option<String> {
+        self.category.as_deref().map(normalize_category)
+    }
+}
+
+fn start_of_day_millis(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc()
+        .timestamp_millis()
+}
+
+/// `Category:Living_people` and `Living people` name the same category.
+fn normalize_category(category: &str) -> String {
+    let category = category.trim();
+    let category = category.strip_prefix("Category:").unwrap_or(category);
+    category.replace('_', " ").trim().to_string()
+}
+
+/// Evaluates a [`DocumentFilter`] against the docstore, for indices which only know ids.
+pub(crate) struct DocstoreFilter<'a, D: DocumentStore> {
+    docstore: &'a D,
+    filter: &'a DocumentFilter,
+}
+
+impl<'a, D: DocumentStore> DocstoreFilter<'a, D> {
+    pub(crate) fn new(docstore: &'a D, filter: &'a DocumentFilter) -> Self {
+        Self { docstore, filter }
+    }
+}
+
+impl<D: DocumentStore> SearchFilter for DocstoreFilter<'_, D> {
+    async fn allowed(&self, candidates: &[i64]) -> Result<HashSet<i64>, IndexSearchError> {
+        Ok(self.docstore.matching(candidates, self.filter).await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::{normalize_category, DocumentFilter};
+
+    #[test]
+    fn category_names() {
+        assert_eq!(
+            normalize_category("Category:Living_people"),
+            "Living people"
+        );
+        assert_eq!(normalize_category(" Living people "), "Living people");
+    }
+
+    #[test]
+    fn modification_range_is_inclusive() {
+        let filter = DocumentFilter {
+            modified_until: NaiveDate::from_ymd_opt(1970, 1, 1),
+            ..Default::default()
+        };
+        assert_eq!(filter.modified_before_millis(), Some(86_400_000));
+        assert!(!filter.is_empty());
+        assert!(DocumentFilter::default().is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+use futures::TryStreamExt;
+use sqlx::{postgres::PgPoolOptions, PgConnection, PgPool, Row, SqlitePool};
+use url::Url;
+
+use super::DocstoreMigrateError;
+use crate::{
+    schema::{migrate_postgres_docstore, sqlite_version, Store},
+    text_codec::TextCompressor,
+};
+
+/// Created once every row is copied, `Docstore<Postgres>` joins documents to their articles and
+/// filters by category.
+const CREATE_INDEXES: [&str; 5] = [
+    "CREATE INDEX IF NOT EXISTS article_title ON article (title)",
+    "CREATE INDEX IF NOT EXISTS article_page_id ON article (page_id)",
+    "CREATE INDEX IF NOT EXISTS document_article ON document (article)",
+    "CREATE UNIQUE INDEX IF NOT EXISTS article_category_article ON article_category (article, category)",
+    "CREATE INDEX IF NOT EXISTS article_category_category ON article_category (category)",
+];
+
+/// A field of a row in the text format of `COPY`.
+enum CopyField<'a> {
+    Integer(i64),
+    Text(&'a str),
+    /// `NULL` for `None`.
+    OptionalText(Option<&'a str>),
+    Bytes(&'a [u8]),
+}
+
+/// Rows of one table waiting to be copied, with the range of their ids.
+#[derive(Default)]
+struct CopyBatch {
+    data: Vec<u8>,
+    rows: usize,
+    first: i64,
+    last: i64,
+}
+
+impl CopyBatch {
+    fn push(&mut self, id: i64, fields: &[CopyField]) {
+        if self.rows == 0 {
+            self.first = id;
+        }
+        self.last = id;
+        self.rows += 1;
+        copy_row(&mut self.data, fields);
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+        self.rows = 0;
+    }
+}
+
+/// Copies the `article`, `article_category`, `compression_dictionary` and `document` tables of
+/// the ingest's `wikipedia_docstore.sqlite` into Postgres, then creates the indexes the server
+/// relies on.
+///
+/// The Postgres schema is brought to the version of the SQLite docstore, which must be the one
+/// this build reads.
+///
+/// Rows are streamed in id order and copied `batch_size` at a time, each batch in its own
+/// transaction. A rerun continues after the largest id already in Postgres, so an interrupted
+/// migration resumes where it stopped and a finished one only copies what an incremental ingest
+/// appended since.
+pub(crate) async fn migrate_docstore(
+    docstore_database_url: &Url,
+    docstore_url: &Url,
+    batch_size: usize,
+) -> Result<(), DocstoreMigrateError> {
+    let source = SqlitePool::connect(&format!("{docstore_database_url}?mode=ro")).await?;
+    let destination = PgPoolOptions::new().connect(docstore_url.as_str()).await?;
+    let batch_size = batch_size.max(1);
+
+    Store::Docstore.check(sqlite_version(&source).await?)?;
+    migrate_postgres_docstore(&destination).await?;
+
+    let articles = migrate_articles(&source, &destination, batch_size).await?;
+    log::info!("Copied {articles} articles");
+    migrate_dictionary(&source, &destination).await?;
+    let documents = migrate_documents(&source, &destination, batch_size).await?;
+    log::info!("Copied {documents} documents");
+
+    log::info!("Creating indexes");
+    for statement in CREATE_INDEXES {
+        sqlx::query(statement).execute(&destination).await?;
+    }
+    sqlx::query("ANALYZE article, document, article_category")
+        .execute(&destination)
+        .await?;
+    source.close().await;
+    Ok(())
+}
+
+/// Copies articles with their categories, so a batch of articles is complete once committed.
+async fn migrate_articles(
+    source: &SqlitePool,
+    destination: &PgPool,
+    batch_size: usize,
+) -> Result<usize, DocstoreMigrateError> {
+    let after = last_id(destination, "article").await?;
+    let mut copied = 0usize;
+    let mut batch = CopyBatch::default();
+    let mut rows = sqlx::query(
+        "SELECT id, title, access_date, modification_date, page_id, revision_id, provenance, metadata FROM article WHERE id > ?1 ORDER BY id",
+    )
+    .bind(after)
+    .fetch(source);
+    while let Some(row) = rows.try_next().await? {
+        let id = row.get::<i64, _>("id");
+        batch.push(
+            id,
+            &[
+                CopyField::Integer(id),
+                CopyField::Text(row.get("title")),
+                CopyField::Integer(row.get("access_date")),
+                CopyField::Integer(row.get("modification_date")),
+                CopyField::Integer(row.get("page_id")),
+                CopyField::Integer(row.get("revision_id")),
+                CopyField::OptionalText(row.get("provenance")),
+                CopyField::OptionalText(row.get("metadata")),
+            ],
+        );
+        if batch.rows == batch_size {
+            copied += write_articles(source, destination, &mut batch).await?;
+            log::info!("Copied {copied} articles");
+        }
+    }
+    copied += write_articles(source, destination, &mut batch).await?;
+    Ok(copied)
+}
+
+async fn write_articles(
+    source: &SqlitePool,
+    destination: &PgPool,
+    articles: &mut CopyBatch,
+) -> Result<usize, DocstoreMigrateError> {
+    if articles.rows == 0 {
+        return Ok(0);
+    }
+    let mut categories = vec![];
+    let mut rows = sqlx::query(
+        "SELECT article, category FROM article_category WHERE article BETWEEN ?1 AND ?2",
+    )
+    .bind(articles.first)
+    .bind(articles.last)
+    .fetch(source);
+    while let Some(row) = rows.try_next().await? {
+        copy_row(
+            &mut categories,
+            &[
+                CopyField::Integer(row.get("article")),
+                CopyField::Text(row.get("category")),
+            ],
+        );
+    }
+
+    let mut transaction = destination.begin().await?;
+    copy_in(
+        &mut transaction,
+        "COPY article (id, title, access_date, modification_date, page_id, revision_id, provenance, metadata) FROM STDIN",
+        &articles.data,
+    )
+    .await?;
+    copy_in(
+        &mut transaction,
+        "COPY article_category (article, category) FROM STDIN",
+        &categories,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    let written = articles.rows;
+    articles.clear();
+    Ok(written)
+}
+
+/// Replaces the dictionary zstd compressed documents are decompressed with.
+async fn migrate_dictionary(
+    source: &SqlitePool,
+    destination: &PgPool,
+) -> Result<(), DocstoreMigrateError> {
+    let Some(dictionary) = TextCompressor::load(&mut *source.acquire().await?).await? else {
+        return Ok(());
+    };
+    let mut transaction = destination.begin().await?;
+    sqlx::query("DELETE FROM compression_dictionary")
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query("INSERT INTO compression_dictionary (dictionary) VALUES ($1)")
+        .bind(dictionary)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+async fn migrate_documents(
+    source: &SqlitePool,
+    destination: &PgPool,
+    batch_size: usize,
+) -> Result<usize, DocstoreMigrateError> {
+    let after = last_id(destination, "document").await?;
+    let mut copied = 0usize;
+    let mut batch = CopyBatch::default();
+    let mut rows = sqlx::query(
+        "SELECT id, text, article, heading, section, ordinal, codec FROM document WHERE id > ?1 ORDER BY id",
+    )
+    .bind(after)
+    .fetch(source);
+    while let Some(row) = rows.try_next().await? {
+        let id = row.get::<i64, _>("id");
+        batch.push(
+            id,
+            &[
+                CopyField::Integer(id),
+                CopyField::Bytes(row.get("text")),
+                CopyField::Integer(row.get("article")),
+                CopyField::Text(row.get("heading")),
+                CopyField::OptionalText(row.get("section")),
+                CopyField::Integer(row.get("ordinal")),
+                CopyField::Text(row.get("codec")),
+            ],
+        );
+        if batch.rows == batch_size {
+            copied += write_documents(destination, &mut batch).await?;
+            log::info!("Copied {copied} documents");
+        }
+    }
+    copied += write_documents(destination, &mut batch).await?;
+    Ok(copied)
+}
+
+async fn write_documents(
+    destination: &PgPool,
+    documents: &mut CopyBatch,
+) -> Result<usize, DocstoreMigrateError> {
+    if documents.rows == 0 {
+        return Ok(0);
+    }
+    let mut transaction = destination.begin().await?;
+    copy_in(
+        &mut transaction,
+        "COPY document (id, text, article, heading, section, ordinal, codec) FROM STDIN",
+        &documents.data,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    let written = documents.rows;
+    documents.clear();
+    Ok(written)
+}
+
+/// The largest id already copied into `table`, -1 when it is empty.
+async fn last_id(destination: &PgPool, table: &str) -> Result<i64, sqlx::Error> {
+    let last = sqlx::query(&format!("SELECT COALESCE(MAX(id), -1) AS id FROM {table}"))
+        .fetch_one(destination)
+        .await?
+        .get::<i64, _>("id");
+    if last >= 0 {
+        log::info!("Resuming {table} after id {last}");
+    }
+    Ok(last)
+}
+
+async fn copy_in(
+    connection: &mut PgConnection,
+    statement: &str,
+    data: &[u8],
+) -> Result<u64, sqlx::Error> {
+    if data.is_empty() {
+        return Ok(0);
+    }
+    let mut copy = connection.copy_in_raw(statement).await?;
+    copy.send(data).await?;
+    copy.finish().await
+}
+
+/// Appends a row in the text format of `COPY`: tab separated, backslash escaped, `bytea` in hex.
+fn copy_row(out: &mut Vec<u8>, fields: &[CopyField]) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    for (position, field) in fields.iter().enumerate() {
+        if position > 0 {
+            out.push(b'\t');
+        }
+        match field {
+            CopyField::Integer(value) => out.extend_from_slice(value.to_string().as_bytes()),
+            CopyField::OptionalText(None) => out.extend_from_slice(b"\\N"),
+            CopyField::Text(text) | CopyField::OptionalText(Some(text)) => {
+                for byte in text.bytes() {
+                    match byte {
+                        b'\\' => out.extend_from_slice(b"\\\\"),
+                        b'\t' => out.extend_from_slice(b"\\t"),
+                        b'\n' => out.extend_from_slice(b"\\n"),
+                        b'\r' => out.extend_from_slice(b"\\r"),
+                        _ => out.push(byte),
+                    }
+                }
+            }
+            CopyField::Bytes(bytes) => {
+                out.extend_from_slice(b"\\\\x");
+                for byte in bytes.iter() {
+                    out.push(HEX[(byte >> 4) as usize]);
+                    out.push(HEX[(byte & 0x0f) as usize]);
+                }
+            }
+        }
+    }
+    out.push(b'\n');
+}
+
+#[cfg(test)]
+mod test {
+    use super::{copy_row, CopyField};
+
+    #[test]
+    fn copy_row_escapes_text_and_bytes() {
+        let mut out = vec![];
+        copy_row(
+            &mut out,
+            &[
+                CopyField::Integer(7),
+                CopyField::Text("a\tb\\c\nd"),
+                CopyField::Bytes(&[0x1f, 0xab]),
+                CopyField::OptionalText(None),
+            ],
+        );
+        assert_eq!(out, b"7\ta\\tb\\\\c\\nd\t\\\\x1fab\t\\N\n");
+    }
+}
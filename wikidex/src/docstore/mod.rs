@@ -1,28 +1,152 @@
+mod article;
 mod cache;
 mod database;
 mod document;
 mod error;
+mod filter;
+#[cfg(all(feature = "migrate", feature = "sqlite"))]
+mod migrate;
 #[cfg(feature = "postgres")]
 mod postgres;
 #[cfg(feature = "sqlite")]
 mod sqlite;
 
+pub(crate) use article::Article;
+pub(crate) use cache::{DocumentCacheKind, DocumentCacheStats, SharedDocumentCache};
 pub(crate) use document::Document;
+pub(crate) use filter::{DocstoreFilter, DocumentFilter};
 
 pub(super) use error::{DocstoreLoadError, DocstoreRetrieveError};
-use redis::aio::MultiplexedConnection;
+use std::{collections::HashSet, str::FromStr, sync::Arc};
+#[cfg(all(feature = "migrate", feature = "sqlite"))]
+pub(crate) use {error::DocstoreMigrateError, migrate::migrate_docstore};
+
+use chrono::DateTime;
+use serde::de::DeserializeOwned;
 use sqlx::{Database, Pool};
 
+use crate::{
+    formatter::{Provenance, ProvenanceKind},
+    text_codec::{TextCodec, TextCompressor},
+};
+
 #[cfg(feature = "postgres")]
 use sqlx::Postgres;
 #[cfg(feature = "sqlite")]
 use sqlx::Sqlite;
 
 pub(crate) struct Docstore<DB: Database> {
-    cache: MultiplexedConnection,
+    /// Namespaces the cache, which every corpus shares.
+    corpus: String,
+    provenance: ProvenanceKind,
+    cache: Arc<SharedDocumentCache>,
+    /// Decompresses document text with the docstore's dictionary.
+    compressor: TextCompressor,
     pool: Pool<DB>,
 }
 
+/// The columns of an article row its citation is built from, dates in milliseconds since the
+/// epoch.
+struct ArticleRow {
+    title: String,
+    access_date: i64,
+    modification_date: i64,
+    revision_id: i64,
+    /// The kind of the article's provenance, the corpus' when `NULL`.
+    provenance: Option<String>,
+    /// The JSON metadata of a wiki, web page, local file or publication.
+    metadata: Option<String>,
+}
+
+impl<DB: Database> Docstore<DB> {
+    /// The text of document `index`, stored compressed with `codec`.
+    fn decode(
+        &self,
+        index: i64,
+        codec: &str,
+        bytes: &[u8],
+    ) -> Result<String, DocstoreRetrieveError> {
+        let undecodable = |reason: String| DocstoreRetrieveError::Undecodable { index, reason };
+        let codec = codec
+            .parse::<TextCodec>()
+            .map_err(|e| undecodable(e.to_string()))?;
+        self.compressor
+            .decompress(codec, bytes)
+            .map_err(|e| undecodable(e.to_string()))
+    }
+
+    fn provenance(&self, article: ArticleRow) -> Option<Provenance> {
+        let ArticleRow {
+            title,
+            access_date,
+            modification_date,
+            revision_id,
+            provenance,
+            metadata,
+        } = article;
+        let access_date = DateTime::from_timestamp_millis(access_date)?
+            .naive_utc()
+            .date();
+        let modification_date = DateTime::from_timestamp_millis(modification_date)?
+            .naive_utc()
+            .date();
+        let kind = match provenance.as_deref().map(ProvenanceKind::from_str) {
+            Some(Ok(kind)) => kind,
+            Some(Err(e)) => {
+                log::warn!("{e}, citing {title} as {}", self.provenance);
+                self.provenance
+            }
+            None => self.provenance,
+        };
+        let provenance = match kind {
+            ProvenanceKind::Wikipedia => {
+                let wiki = metadata_of(&title, metadata.as_deref());
+                Provenance::Wikipedia(title, access_date, modification_date, revision_id, wiki)
+            }
+            ProvenanceKind::Internal => {
+                Provenance::Internal(self.corpus.clone(), title, access_date, modification_date)
+            }
+            ProvenanceKind::WebPage => {
+                let page = metadata_of(&title, metadata.as_deref());
+                Provenance::WebPage(title, page, access_date, modification_date)
+            }
+            ProvenanceKind::LocalFile => {
+                let file = metadata_of(&title, metadata.as_deref());
+                Provenance::LocalFile(title, file, access_date, modification_date)
+            }
+            ProvenanceKind::Publication => {
+                let publication = metadata_of(&title, metadata.as_deref());
+                Provenance::Publication(title, publication, access_date, modification_date)
+            }
+        };
+        Some(provenance)
+    }
+
+    fn article(&self, id: i64, article: ArticleRow) -> Option<Article> {
+        Some(Article {
+            id,
+            provenance: self.provenance(article)?,
+        })
+    }
+
+    /// Versioned so a shared cache never returns documents archived in an older layout.
+    fn cache_key(&self, index: i64) -> String {
+        format!("document:v8:{}:{index}", self.corpus)
+    }
+}
+
+/// The metadata of an article, the default when it has none or it does not parse.
+fn metadata_of<T: DeserializeOwned + Default>(title: &str, metadata: Option<&str>) -> T {
+    match metadata.map(serde_json::from_str) {
+        Some(Ok(metadata)) => metadata,
+        Some(Err(e)) => {
+            log::warn!("Unable to parse the metadata of {title}: {e}");
+            T::default()
+        }
+        None => T::default(),
+    }
+}
+
 pub(crate) enum DocumentStoreImpl {
     #[cfg(feature = "postgres")]
     Postgres(Docstore<Postgres>),
@@ -32,4 +156,20 @@ pub(crate) enum DocumentStoreImpl {
 
 pub(crate) trait DocumentStore: Send + Sync {
     async fn retreive(&self, indices: &[i64]) -> Result<Vec<Document>, DocstoreRetrieveError>;
+    async fn matching(
+        &self,
+        indices: &[i64],
+        filter: &DocumentFilter,
+    ) -> Result<HashSet<i64>, DocstoreRetrieveError>;
+    /// The documents of `article` in section order, empty for an unknown article.
+    async fn article_documents(&self, article: i64)
+        -> Result<Vec<Document>, DocstoreRetrieveError>;
+    /// At most `limit` articles titled `title`. A fuzzy search matches titles containing the
+    /// words of `title` in order regardless of case, shortest titles first.
+    async fn find_articles(
+        &self,
+        title: &str,
+        fuzzy: bool,
+        limit: usize,
+    ) -> Result<Vec<Article>, DocstoreRetrieveError>;
 }
@@ -1,12 +1,18 @@
-use std::io::Read;
+use std::{collections::HashSet, sync::Arc};
 
-use crate::{docstore::document::Document, formatter::Provenance};
-use chrono::DateTime;
-use flate2::read::GzDecoder;
-use sqlx::{postgres::PgPool, Postgres};
+use crate::{
+    docstore::document::Document,
+    formatter::ProvenanceKind,
+    schema::{postgres_version, Store},
+    text_codec::{TextCodec, TextCompressor},
+};
+use sqlx::{postgres::PgPool, Postgres, Row};
 use url::Url;
 
-use super::{database::DocumentDatabase, Docstore, DocstoreLoadError, DocstoreRetrieveError};
+use super::{
+    article::fuzzy_title_pattern, database::DocumentDatabase, Article, ArticleRow, Docstore,
+    DocstoreLoadError, DocstoreRetrieveError, DocumentFilter, SharedDocumentCache,
+};
 
 impl DocumentDatabase for Docstore<Postgres> {
     async fn retreive_from_db(
@@ -16,10 +22,18 @@ impl DocumentDatabase for Docstore<Postgres> {
         let docs_rows = sqlx::query!(
             r#" 
             SELECT document.id,
+                document.article,
                 document.text,
+                document.codec,
+                document.heading,
+                document.section,
+                document.ordinal,
                 article.title,
                 article.access_date,
-                article.modification_date
+                article.modification_date,
+                article.revision_id,
+                article.provenance,
+                article.metadata
             FROM document
             INNER JOIN article ON document.article = article.id
             WHERE document.id IN
@@ -36,55 +50,175 @@ impl DocumentDatabase for Docstore<Postgres> {
             .into_iter()
             .filter_map(|row| {
                 let index = row.id;
+                let article = row.article?;
 
                 let binary_data = row.text.unwrap();
-                let mut gz = GzDecoder::new(&*binary_data);
-                let mut document = String::new();
-                gz.read_to_string(&mut document).ok()?;
-
-                let article_title = row.title.unwrap();
-                let access_date = row.access_date.unwrap();
-                let modification_date = row.modification_date.unwrap();
-
-                let access_date = DateTime::from_timestamp_millis(access_date)?
-                    .naive_utc()
-                    .date();
-                let modification_date = DateTime::from_timestamp_millis(modification_date)?
-                    .naive_utc()
-                    .date();
-
-                let provenance =
-                    Provenance::Wikipedia(article_title, access_date, modification_date);
-                Some((index, document, provenance))
+                let codec = row.codec.unwrap_or_default();
+                let document = match self.decode(index, &codec, &binary_data) {
+                    Ok(document) => document,
+                    Err(e) => return Some(Err(e)),
+                };
+
+                let heading = row.heading?;
+                let section = row.section;
+                let ordinal = row.ordinal?;
+                let provenance = self.provenance(ArticleRow {
+                    title: row.title.unwrap(),
+                    access_date: row.access_date.unwrap(),
+                    modification_date: row.modification_date.unwrap(),
+                    revision_id: row.revision_id?,
+                    provenance: row.provenance,
+                    metadata: row.metadata,
+                })?;
+                Some(Ok(Document {
+                    index,
+                    article,
+                    text: document,
+                    heading,
+                    section,
+                    ordinal,
+                    provenance,
+                }))
             })
-            .collect::<Vec<(i64, String, Provenance)>>();
+            .collect::<Result<Vec<Document>, _>>()?;
 
         let result = indices
             .iter()
-            .filter_map(|docstore_index| {
-                let (index, doc_text, document_provenance) =
-                    docs.iter().find(|d| d.0 == *docstore_index)?;
-                Some(Document {
-                    index: *index,
-                    text: doc_text.clone(),
-                    provenance: document_provenance.clone(),
-                })
-            })
+            .filter_map(|docstore_index| docs.iter().find(|d| d.index == *docstore_index).cloned())
             .collect::<Vec<Document>>();
 
         Ok(result)
     }
+
+    async fn matching_from_db(
+        &self,
+        indices: &[i64],
+        filter: &DocumentFilter,
+    ) -> Result<HashSet<i64>, DocstoreRetrieveError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT document.id
+            FROM document
+            INNER JOIN article ON document.article = article.id
+            WHERE document.id = ANY($1::bigint[])
+                AND ($2::bigint[] IS NULL OR article.id = ANY($2::bigint[]))
+                AND ($3::bigint IS NULL OR article.modification_date >= $3)
+                AND ($4::bigint IS NULL OR article.modification_date < $4)
+                AND ($5::text IS NULL OR article.id IN
+                    (SELECT article
+                    FROM article_category
+                    WHERE category = $5))
+            "#,
+        )
+        .bind(indices)
+        .bind(filter.article_ids.as_deref())
+        .bind(filter.modified_since_millis())
+        .bind(filter.modified_before_millis())
+        .bind(filter.category())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| row.get::<i64, _>("id"))
+            .collect())
+    }
+
+    async fn article_documents_from_db(
+        &self,
+        article: i64,
+    ) -> Result<Vec<i64>, DocstoreRetrieveError> {
+        let rows = sqlx::query("SELECT id FROM document WHERE article = $1 ORDER BY ordinal, id")
+            .bind(article)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| row.get::<i64, _>("id"))
+            .collect())
+    }
+
+    async fn find_articles_from_db(
+        &self,
+        title: &str,
+        fuzzy: bool,
+        limit: usize,
+    ) -> Result<Vec<Article>, DocstoreRetrieveError> {
+        let query = if fuzzy {
+            sqlx::query(
+                r#"
+                SELECT id, title, access_date, modification_date, revision_id, provenance, metadata
+                FROM article
+                WHERE title ILIKE $1 ESCAPE '\'
+                ORDER BY length(title), title
+                LIMIT $2
+                "#,
+            )
+            .bind(fuzzy_title_pattern(title))
+        } else {
+            sqlx::query(
+                r#"
+                SELECT id, title, access_date, modification_date, revision_id, provenance, metadata
+                FROM article
+                WHERE title = $1
+                ORDER BY id
+                LIMIT $2
+                "#,
+            )
+            .bind(title)
+        };
+        let rows = query.bind(limit as i64).fetch_all(&self.pool).await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let article = ArticleRow {
+                    title: row.get::<String, _>("title"),
+                    access_date: row.get::<i64, _>("access_date"),
+                    modification_date: row.get::<i64, _>("modification_date"),
+                    revision_id: row.get::<i64, _>("revision_id"),
+                    provenance: row.get::<Option<String>, _>("provenance"),
+                    metadata: row.get::<Option<String>, _>("metadata"),
+                };
+                self.article(row.get::<i64, _>("id"), article)
+            })
+            .collect())
+    }
 }
 
 impl Docstore<Postgres> {
     pub(crate) async fn new(
+        corpus: &str,
+        provenance: ProvenanceKind,
         docstore_path: &Url,
-        redis_url: &Url,
+        cache: Arc<SharedDocumentCache>,
     ) -> Result<Self, DocstoreLoadError> {
         let docstore_path = docstore_path.as_ref();
         let pool = PgPool::connect(docstore_path).await?;
-        let client = redis::Client::open(redis_url.to_string())?;
-        let cache = client.get_multiplexed_tokio_connection().await?;
-        Ok(Docstore { pool, cache })
+        Store::Docstore.check(postgres_version(&pool).await?)?;
+        let dictionary = compression_dictionary(&pool).await?;
+        Ok(Docstore {
+            corpus: corpus.to_string(),
+            provenance,
+            pool,
+            cache,
+            compressor: TextCompressor::new(TextCodec::Zstd, dictionary),
+        })
+    }
+}
+
+/// The dictionary zstd compressed documents were written with, `None` if the docstore has none.
+async fn compression_dictionary(pool: &PgPool) -> Result<Option<Vec<u8>>, sqlx::Error> {
+    let has_table =
+        sqlx::query_scalar::<_, bool>("SELECT to_regclass('compression_dictionary') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+    if !has_table {
+        return Ok(None);
     }
+    let row = sqlx::query("SELECT dictionary FROM compression_dictionary LIMIT 1")
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|row| row.get("dictionary")))
 }
@@ -1,13 +1,16 @@
-use crate::formatter::Provenance;
-use chrono::DateTime;
-use flate2::read::GzDecoder;
-use sqlx::{Row, Sqlite, SqlitePool};
-use std::io::Read;
+use crate::{
+    formatter::ProvenanceKind,
+    schema::{sqlite_version, Store},
+    text_codec::{TextCodec, TextCompressor},
+};
+use sqlx::{sqlite::SqliteRow, Row, Sqlite, SqlitePool};
+use std::{collections::HashSet, sync::Arc};
 use url::Url;
 
 use super::{
-    database::DocumentDatabase, document::Document, Docstore, DocstoreLoadError,
-    DocstoreRetrieveError,
+    article::fuzzy_title_pattern, database::DocumentDatabase, document::Document, Article,
+    ArticleRow, Docstore, DocstoreLoadError, DocstoreRetrieveError, DocumentFilter,
+    SharedDocumentCache,
 };
 impl DocumentDatabase for Docstore<Sqlite> {
     async fn retreive_from_db(
@@ -20,7 +23,7 @@ impl DocumentDatabase for Docstore<Sqlite> {
             .collect::<Vec<_>>()
             .join(",");
 
-        let query = format!("SELECT document.id, document.text, article.title, article.access_date, article.modification_date FROM document INNER JOIN article ON document.article = article.id WHERE document.id IN ({})", ids);
+        let query = format!("SELECT document.id, document.article, document.text, document.codec, document.heading, document.section, document.ordinal, article.title, article.access_date, article.modification_date, article.revision_id, article.provenance, article.metadata FROM document INNER JOIN article ON document.article = article.id WHERE document.id IN ({})", ids);
 
         let docs_rows = sqlx::query(&query)
             .fetch_all(&self.pool)
@@ -31,53 +34,136 @@ impl DocumentDatabase for Docstore<Sqlite> {
             .into_iter()
             .filter_map(|row| {
                 let index = row.get::<i64, _>("id");
+                let article = row.get::<i64, _>("article");
 
                 let binary_data = row.get::<Vec<u8>, _>("text");
-                let mut gz = GzDecoder::new(&*binary_data);
-                let mut document = String::new();
-                gz.read_to_string(&mut document).ok()?;
-
-                let article_title = row.get::<String, _>("title");
-                let access_date = row.get::<i64, _>("access_date");
-                let modification_date = row.get::<i64, _>("modification_date");
-
-                let access_date = DateTime::from_timestamp_millis(access_date)?
-                    .naive_utc()
-                    .date();
-                let modification_date = DateTime::from_timestamp_millis(modification_date)?
-                    .naive_utc()
-                    .date();
-
-                let provenance =
-                    Provenance::Wikipedia(article_title, access_date, modification_date);
-                Some((index, document, provenance))
+                let codec = row.get::<String, _>("codec");
+                let document = match self.decode(index, &codec, &binary_data) {
+                    Ok(document) => document,
+                    Err(e) => return Some(Err(e)),
+                };
+
+                let heading = row.get::<String, _>("heading");
+                let section = row.get::<Option<String>, _>("section");
+                let ordinal = row.get::<i64, _>("ordinal");
+                let provenance = self.provenance(article_row(&row))?;
+                Some(Ok(Document {
+                    index,
+                    article,
+                    text: document,
+                    heading,
+                    section,
+                    ordinal,
+                    provenance,
+                }))
             })
-            .collect::<Vec<(i64, String, Provenance)>>();
+            .collect::<Result<Vec<Document>, _>>()?;
 
         let result = indices
             .iter()
-            .enumerate()
-            .filter_map(|(_array_index, docstore_index)| {
-                let (index, doc_text, document_provenance) =
-                    docs.iter().find(|d| d.0 == *docstore_index)?;
-                Some(Document {
-                    index: *index,
-                    text: doc_text.clone(),
-                    provenance: document_provenance.clone(),
-                })
-            })
+            .filter_map(|docstore_index| docs.iter().find(|d| d.index == *docstore_index).cloned())
             .collect::<Vec<Document>>();
 
         Ok(result)
     }
+
+    async fn matching_from_db(
+        &self,
+        indices: &[i64],
+        filter: &DocumentFilter,
+    ) -> Result<HashSet<i64>, DocstoreRetrieveError> {
+        let ids = indices
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let article_ids = filter
+            .article_ids
+            .as_ref()
+            .map(|article_ids| serde_json::to_string(article_ids).unwrap());
+
+        let query = format!("SELECT document.id FROM document INNER JOIN article ON document.article = article.id WHERE document.id IN ({ids}) AND (?1 IS NULL OR article.id IN (SELECT value FROM json_each(?1))) AND (?2 IS NULL OR article.modification_date >= ?2) AND (?3 IS NULL OR article.modification_date < ?3) AND (?4 IS NULL OR article.id IN (SELECT article FROM article_category WHERE category = ?4))");
+
+        let rows = sqlx::query(&query)
+            .bind(article_ids)
+            .bind(filter.modified_since_millis())
+            .bind(filter.modified_before_millis())
+            .bind(filter.category())
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| row.get::<i64, _>("id"))
+            .collect())
+    }
+
+    async fn article_documents_from_db(
+        &self,
+        article: i64,
+    ) -> Result<Vec<i64>, DocstoreRetrieveError> {
+        let rows = sqlx::query("SELECT id FROM document WHERE article = ?1 ORDER BY ordinal, id")
+            .bind(article)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| row.get::<i64, _>("id"))
+            .collect())
+    }
+
+    async fn find_articles_from_db(
+        &self,
+        title: &str,
+        fuzzy: bool,
+        limit: usize,
+    ) -> Result<Vec<Article>, DocstoreRetrieveError> {
+        let query = if fuzzy {
+            // LIKE ignores the case of ASCII letters.
+            sqlx::query("SELECT id, title, access_date, modification_date, revision_id, provenance, metadata FROM article WHERE title LIKE ?1 ESCAPE '\\' ORDER BY length(title), title LIMIT ?2")
+                .bind(fuzzy_title_pattern(title))
+        } else {
+            sqlx::query("SELECT id, title, access_date, modification_date, revision_id, provenance, metadata FROM article WHERE title = ?1 ORDER BY id LIMIT ?2")
+                .bind(title)
+        };
+        let rows = query.bind(limit as i64).fetch_all(&self.pool).await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| self.article(row.get::<i64, _>("id"), article_row(&row)))
+            .collect())
+    }
+}
+
+fn article_row(row: &SqliteRow) -> ArticleRow {
+    ArticleRow {
+        title: row.get::<String, _>("title"),
+        access_date: row.get::<i64, _>("access_date"),
+        modification_date: row.get::<i64, _>("modification_date"),
+        revision_id: row.get::<i64, _>("revision_id"),
+        provenance: row.get::<Option<String>, _>("provenance"),
+        metadata: row.get::<Option<String>, _>("metadata"),
+    }
 }
 
 impl Docstore<Sqlite> {
-    pub async fn new(docstore_path: &Url, redis_url: &Url) -> Result<Self, DocstoreLoadError> {
+    pub async fn new(
+        corpus: &str,
+        provenance: ProvenanceKind,
+        docstore_path: &Url,
+        cache: Arc<SharedDocumentCache>,
+    ) -> Result<Self, DocstoreLoadError> {
         let docstore_path = docstore_path.as_ref();
         let pool = SqlitePool::connect(docstore_path).await?;
-        let client = redis::Client::open(redis_url.to_string())?;
-        let cache = client.get_multiplexed_tokio_connection().await?;
-        Ok(Docstore { pool, cache })
+        Store::Docstore.check(sqlite_version(&pool).await?)?;
+        let dictionary = TextCompressor::load(&mut *pool.acquire().await?).await?;
+        Ok(Docstore {
+            corpus: corpus.to_string(),
+            provenance,
+            pool,
+            cache,
+            compressor: TextCompressor::new(TextCodec::Zstd, dictionary),
+        })
     }
 }
@@ -0,0 +1,202 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use redis::{aio::MultiplexedConnection, AsyncCommands, RedisError};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::{EmbeddingClientService, EmbeddingProfile, EmbeddingServiceError};
+
+/// Versioned, earlier builds stored embeddings as finished by the profile.
+const KEY_PREFIX: &str = "embedding:v2";
+
+type MemoryCache = Mutex<LruCache<String, (Instant, Vec<f32>)>>;
+
+#[derive(Default, Clone, Copy)]
+pub(crate) struct EmbeddingCacheStats {
+    pub(crate) memory_hits: u64,
+    pub(crate) redis_hits: u64,
+    pub(crate) misses: u64,
+}
+
+/// Query embeddings keyed by a hash of the model name and the whitespace normalized query, as
+/// prepared by the embedding profile.
+///
+/// The model output is cached as returned and finished by the profile on every lookup, so
+/// profiles that prepare a query alike but normalize differently share entries safely.
+///
+/// Lookups go to the in-process LRU first, then to Redis. Redis errors are logged and treated as
+/// misses, so the cache keeps working in-process while Redis is unavailable. A `ttl` of zero
+/// never expires entries.
+pub(crate) struct EmbeddingCache {
+    model_name: String,
+    ttl: Duration,
+    memory: Option<MemoryCache>,
+    redis: Option<MultiplexedConnection>,
+    memory_hits: AtomicU64,
+    redis_hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl EmbeddingCache {
+    pub(crate) fn new(
+        model_name: String,
+        ttl: Duration,
+        capacity: usize,
+        redis: Option<MultiplexedConnection>,
+    ) -> Self {
+        Self {
+            model_name,
+            ttl,
+            memory: NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity))),
+            redis,
+            memory_hits: AtomicU64::new(0),
+            redis_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) async fn redis_connection(
+        redis_url: &Url,
+    ) -> Result<MultiplexedConnection, RedisError> {
+        redis::Client::open(redis_url.as_str())?
+            .get_multiplexed_tokio_connection()
+            .await
+    }
+
+    pub(crate) async fn embed<E: EmbeddingClientService>(
+        &self,
+        client: &E,
+        profile: &EmbeddingProfile,
+        query: &str,
+    ) -> Result<Vec<f32>, EmbeddingServiceError> {
+        let query = profile.query(&normalize(query));
+        let key = cache_key(&self.model_name, &query);
+
+        if let Some(embedding) = self.get_memory(&key) {
+            self.memory_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(profile.finish(embedding));
+        }
+        if let Some(embedding) = self.get_redis(&key).await {
+            self.redis_hits.fetch_add(1, Ordering::Relaxed);
+            self.insert_memory(key, embedding.clone());
+            return Ok(profile.finish(embedding));
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let embedding = client.embed(&query).await?;
+        self.insert_redis(key.clone(), &embedding);
+        self.insert_memory(key, embedding.clone());
+        Ok(profile.finish(embedding))
+    }
+
+    pub(crate) fn stats(&self) -> EmbeddingCacheStats {
+        EmbeddingCacheStats {
+            memory_hits: self.memory_hits.load(Ordering::Relaxed),
+            redis_hits: self.redis_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn get_memory(&self, key: &str) -> Option<Vec<f32>> {
+        let mut memory = self.memory.as_ref()?.lock().ok()?;
+        let (inserted, embedding) = memory.get(key)?;
+        if !self.ttl.is_zero() && inserted.elapsed() > self.ttl {
+            memory.pop(key);
+            return None;
+        }
+        Some(embedding.clone())
+    }
+
+    fn insert_memory(&self, key: String, embedding: Vec<f32>) {
+        if let Some(Ok(mut memory)) = self.memory.as_ref().map(Mutex::lock) {
+            memory.put(key, (Instant::now(), embedding));
+        }
+    }
+
+    async fn get_redis(&self, key: &str) -> Option<Vec<f32>> {
+        let mut redis = self.redis.clone()?;
+        match redis.get::<_, Option<Vec<u8>>>(key).await {
+            Ok(bytes) => bytes.and_then(|bytes| decode(&bytes)),
+            Err(e) => {
+                log::warn!("Embedding cache: {e}");
+                None
+            }
+        }
+    }
+
+    fn insert_redis(&self, key: String, embedding: &[f32]) {
+        let Some(mut redis) = self.redis.clone() else {
+            return;
+        };
+        let bytes = encode(embedding);
+        let ttl = self.ttl.as_secs();
+        tokio::spawn(async move {
+            let result: Result<(), RedisError> = if ttl == 0 {
+                redis.set(key, bytes).await
+            } else {
+                redis.set_ex(key, bytes, ttl).await
+            };
+            if let Err(e) = result {
+                log::warn!("Embedding cache: {e}");
+            }
+        });
+    }
+}
+
+fn normalize(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn cache_key(model_name: &str, query: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(model_name)
+        .chain_update([0])
+        .chain_update(query)
+        .finalize();
+    format!("{KEY_PREFIX}:{digest:x}")
+}
+
+fn encode(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+fn decode(bytes: &[u8]) -> Option<Vec<f32>> {
+    let chunks = bytes.chunks_exact(4);
+    if bytes.is_empty() || !chunks.remainder().is_empty() {
+        return None;
+    }
+    Some(
+        chunks
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cache_key, decode, encode, normalize};
+
+    #[test]
+    fn key_ignores_whitespace_but_not_model() {
+        let a = cache_key("gte-small", &normalize("  Why is the sky\n blue? "));
+        let b = cache_key("gte-small", &normalize("Why is the sky blue?"));
+        let c = cache_key("gte-base", &normalize("Why is the sky blue?"));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn embedding_round_trip() {
+        let embedding = vec![0.25f32, -1.5, 3.0];
+        assert_eq!(decode(&encode(&embedding)), Some(embedding));
+        assert_eq!(decode(&[0, 0, 0]), None);
+    }
+}
@@ -3,7 +3,15 @@ use std::fmt::{Display, Formatter, Result};
 #[derive(Debug)]
 pub(crate) enum EmbeddingServiceError {
     AsyncOpenAiError(async_openai::error::OpenAIError),
+    Anyhow(anyhow::Error),
+    TonicError(tonic::transport::Error),
+    TonicStatus(tonic::Status),
     EmbeddingSizeMismatch(usize, usize),
+    MissingOutput(String),
+    ModelNotReady(String),
+    ProfileMismatch { recorded: String, active: String },
+    UnsupportedIndexDatabase(url::Url),
+    Sqlx(sqlx::Error),
 }
 
 impl From<async_openai::error::OpenAIError> for EmbeddingServiceError {
@@ -12,17 +20,63 @@ impl From<async_openai::error::OpenAIError> for EmbeddingServiceError {
     }
 }
 
+impl From<anyhow::Error> for EmbeddingServiceError {
+    fn from(value: anyhow::Error) -> Self {
+        Self::Anyhow(value)
+    }
+}
+
+impl From<tonic::transport::Error> for EmbeddingServiceError {
+    fn from(value: tonic::transport::Error) -> Self {
+        Self::TonicError(value)
+    }
+}
+
+impl From<tonic::Status> for EmbeddingServiceError {
+    fn from(value: tonic::Status) -> Self {
+        Self::TonicStatus(value)
+    }
+}
+
+impl From<sqlx::Error> for EmbeddingServiceError {
+    fn from(value: sqlx::Error) -> Self {
+        Self::Sqlx(value)
+    }
+}
+
 impl std::error::Error for EmbeddingServiceError {}
 
 impl Display for EmbeddingServiceError {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
             EmbeddingServiceError::AsyncOpenAiError(err) => write!(f, "LLMService: {}", err),
+            EmbeddingServiceError::Anyhow(err) => write!(f, "EmbeddingService: {:?}", err),
+            EmbeddingServiceError::TonicError(err) => write!(f, "EmbeddingService: {:?}", err),
+            EmbeddingServiceError::TonicStatus(err) => write!(f, "EmbeddingService: {:?}", err),
             EmbeddingServiceError::EmbeddingSizeMismatch(expected, actual) => write!(
                 f,
                 "EmbeddingService: Embedding size mismatch. Expected: {}, Actual: {}",
                 expected, actual
             ),
+            EmbeddingServiceError::MissingOutput(name) => write!(
+                f,
+                "EmbeddingService: Response is missing output tensor {}",
+                name
+            ),
+            EmbeddingServiceError::ModelNotReady(name) => {
+                write!(f, "EmbeddingService: Model {} is not ready", name)
+            }
+            EmbeddingServiceError::ProfileMismatch { recorded, active } => write!(
+                f,
+                "EmbeddingService: The index was embedded with profile {}, but the active profile is {}",
+                recorded, active
+            ),
+            EmbeddingServiceError::UnsupportedIndexDatabase(url) => write!(
+                f,
+                "EmbeddingService: No embedding profile can be read from index database {}",
+                url
+            ),
+            EmbeddingServiceError::Sqlx(err) => write!(f, "EmbeddingService: {}", err),
         }
     }
 }
@@ -1,12 +1,21 @@
-mod embedding;
+#[cfg(feature = "server")]
+mod cache;
 mod error;
+mod openai;
+mod profile;
+mod quantization;
+mod triton;
 
-use async_openai::{error::OpenAIError, types::ListModelResponse};
-pub(crate) use embedding::EmbeddingClient;
+#[cfg(feature = "server")]
+pub(crate) use cache::{EmbeddingCache, EmbeddingCacheStats};
 pub(crate) use error::EmbeddingServiceError;
+pub(crate) use openai::OpenAiEmbeddingClient;
+pub(crate) use profile::{EmbeddingProfile, EMBED_MAX_STR_LEN_ACCORDING_TO_INFINITY};
+pub(crate) use quantization::{EmbeddingQuantization, Quantizer};
+pub(crate) use triton::TritonEmbeddingClient;
 
 pub(crate) trait EmbeddingClientService {
-    async fn up(&self) -> Result<ListModelResponse, OpenAIError>;
+    async fn up(&self) -> Result<(), EmbeddingServiceError>;
 
     async fn embed_batch(
         &self,
@@ -14,3 +23,34 @@ pub(crate) trait EmbeddingClientService {
     ) -> Result<Vec<Vec<f32>>, EmbeddingServiceError>;
     async fn embed(&self, query: &str) -> Result<Vec<f32>, EmbeddingServiceError>;
 }
+
+pub(crate) enum EmbeddingClientImpl {
+    OpenAi(OpenAiEmbeddingClient),
+    Triton(TritonEmbeddingClient),
+}
+
+impl EmbeddingClientService for EmbeddingClientImpl {
+    async fn up(&self) -> Result<(), EmbeddingServiceError> {
+        match self {
+            EmbeddingClientImpl::OpenAi(o) => o.up().await,
+            EmbeddingClientImpl::Triton(t) => t.up().await,
+        }
+    }
+
+    async fn embed_batch(
+        &self,
+        queries: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingServiceError> {
+        match self {
+            EmbeddingClientImpl::OpenAi(o) => o.embed_batch(queries).await,
+            EmbeddingClientImpl::Triton(t) => t.embed_batch(queries).await,
+        }
+    }
+
+    async fn embed(&self, query: &str) -> Result<Vec<f32>, EmbeddingServiceError> {
+        match self {
+            EmbeddingClientImpl::OpenAi(o) => o.embed(query).await,
+            EmbeddingClientImpl::Triton(t) => t.embed(query).await,
+        }
+    }
+}
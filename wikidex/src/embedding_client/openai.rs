@@ -1,32 +1,28 @@
-use async_openai::{
-    config::OpenAIConfig,
-    error::OpenAIError,
-    types::{CreateEmbeddingRequestArgs, ListModelResponse},
-    Client,
-};
+use async_openai::{config::OpenAIConfig, types::CreateEmbeddingRequestArgs, Client};
 
 use super::{error::EmbeddingServiceError, EmbeddingClientService};
 
-pub(crate) struct EmbeddingClient {
+pub(crate) struct OpenAiEmbeddingClient {
     embedding_client: Client<OpenAIConfig>,
     embedding_model_name: String,
 }
 
-impl EmbeddingClient {
+impl OpenAiEmbeddingClient {
     pub(crate) fn new(
         embedding_client: Client<OpenAIConfig>,
         embedding_model_name: String,
     ) -> Self {
-        EmbeddingClient {
+        OpenAiEmbeddingClient {
             embedding_client,
             embedding_model_name,
         }
     }
 }
 
-impl EmbeddingClientService for EmbeddingClient {
-    async fn up(&self) -> Result<ListModelResponse, OpenAIError> {
-        self.embedding_client.models().list().await
+impl EmbeddingClientService for OpenAiEmbeddingClient {
+    async fn up(&self) -> Result<(), EmbeddingServiceError> {
+        let _ = self.embedding_client.models().list().await?;
+        Ok(())
     }
 
     async fn embed_batch(
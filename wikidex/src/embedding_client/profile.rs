@@ -0,0 +1,288 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+#[cfg(all(feature = "server", any(feature = "postgres", feature = "pgvector")))]
+use sqlx::PgPool;
+#[cfg(all(
+    feature = "server",
+    any(feature = "sqlite", feature = "postgres", feature = "pgvector")
+))]
+use sqlx::Row;
+#[cfg(all(feature = "server", feature = "sqlite"))]
+use sqlx::SqlitePool;
+#[cfg(feature = "server")]
+use url::Url;
+
+#[cfg(feature = "server")]
+use super::EmbeddingServiceError;
+
+/// The infinity embedding server rejects longer inputs.
+pub(crate) const EMBED_MAX_STR_LEN_ACCORDING_TO_INFINITY: usize = 122880usize;
+
+/// How text is prepared for, and vectors are taken from, an embedding model.
+///
+/// Asymmetric models (e5, bge, nomic) embed queries and passages with different prefixes. The
+/// ingest and the server must apply the same profile, so the ingest records it in the index
+/// database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EmbeddingProfile {
+    pub(crate) name: String,
+    pub(crate) query_prefix: String,
+    pub(crate) document_prefix: String,
+    pub(crate) normalize: bool,
+    /// In characters, the tokenizer of the model is not available here.
+    pub(crate) max_input_chars: usize,
+}
+
+impl EmbeddingProfile {
+    fn new(
+        name: &str,
+        query_prefix: &str,
+        document_prefix: &str,
+        normalize: bool,
+        max_input_chars: usize,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            query_prefix: query_prefix.to_string(),
+            document_prefix: document_prefix.to_string(),
+            normalize,
+            max_input_chars,
+        }
+    }
+
+    pub(crate) fn with_overrides(
+        mut self,
+        query_prefix: Option<String>,
+        document_prefix: Option<String>,
+        normalize: Option<bool>,
+        max_input_chars: Option<usize>,
+    ) -> Self {
+        if let Some(query_prefix) = query_prefix {
+            self.query_prefix = query_prefix;
+        }
+        if let Some(document_prefix) = document_prefix {
+            self.document_prefix = document_prefix;
+        }
+        if let Some(normalize) = normalize {
+            self.normalize = normalize;
+        }
+        if let Some(max_input_chars) = max_input_chars {
+            self.max_input_chars = max_input_chars;
+        }
+        self
+    }
+
+    pub(crate) fn query(&self, text: &str) -> String {
+        self.prepare(&self.query_prefix, text)
+    }
+
+    pub(crate) fn document(&self, text: &str) -> String {
+        self.prepare(&self.document_prefix, text)
+    }
+
+    fn prepare(&self, prefix: &str, text: &str) -> String {
+        prefix
+            .chars()
+            .chain(text.chars())
+            .take(self.max_input_chars)
+            .collect()
+    }
+
+    pub(crate) fn finish(&self, mut embedding: Vec<f32>) -> Vec<f32> {
+        if self.normalize {
+            let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if norm > 0f32 {
+                embedding.iter_mut().for_each(|x| *x /= norm);
+            }
+        }
+        embedding
+    }
+
+    /// Compares the profile against the one recorded in the index database: the SQLite index
+    /// database of the ingest, or the Postgres database `wikidex index pgvector` copied it to.
+    /// Indices written before profiles were recorded have no `embedding_profile` table and were
+    /// embedded raw.
+    #[cfg(feature = "server")]
+    pub(crate) async fn verify(
+        &self,
+        index_database_url: &Url,
+    ) -> Result<(), EmbeddingServiceError> {
+        let recorded = match index_database_url.scheme() {
+            #[cfg(feature = "sqlite")]
+            "sqlite" => {
+                let pool = SqlitePool::connect(index_database_url.as_str()).await?;
+                let recorded = EmbeddingProfile::recorded_sqlite(&pool).await;
+                pool.close().await;
+                recorded?
+            }
+            #[cfg(any(feature = "postgres", feature = "pgvector"))]
+            "postgres" => {
+                let pool = PgPool::connect(index_database_url.as_str()).await?;
+                let recorded = EmbeddingProfile::recorded_postgres(&pool).await;
+                pool.close().await;
+                recorded?
+            }
+            _ => {
+                return Err(EmbeddingServiceError::UnsupportedIndexDatabase(
+                    index_database_url.clone(),
+                ))
+            }
+        };
+
+        let recorded = recorded.unwrap_or_else(|| {
+            log::warn!("{index_database_url} records no embedding profile, assuming raw");
+            EmbeddingProfile::from_str("raw").unwrap()
+        });
+
+        if recorded == *self {
+            Ok(())
+        } else {
+            Err(EmbeddingServiceError::ProfileMismatch {
+                recorded: recorded.to_string(),
+                active: self.to_string(),
+            })
+        }
+    }
+
+    /// The profile recorded in a SQLite index database, `None` when it has none.
+    #[cfg(all(feature = "server", feature = "sqlite"))]
+    pub(crate) async fn recorded_sqlite(
+        pool: &SqlitePool,
+    ) -> Result<Option<EmbeddingProfile>, sqlx::Error> {
+        let tables = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'embedding_profile'",
+        )
+        .fetch_one(pool)
+        .await?;
+        if tables == 0 {
+            return Ok(None);
+        }
+        let row = sqlx::query(
+            "SELECT name, query_prefix, document_prefix, normalize, max_input_chars FROM embedding_profile LIMIT 1",
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(row.map(|row| EmbeddingProfile {
+            name: row.get::<String, _>("name"),
+            query_prefix: row.get::<String, _>("query_prefix"),
+            document_prefix: row.get::<String, _>("document_prefix"),
+            normalize: row.get::<bool, _>("normalize"),
+            max_input_chars: row.get::<i64, _>("max_input_chars") as usize,
+        }))
+    }
+
+    /// The profile recorded in a pgvector database, `None` when it has none.
+    #[cfg(all(feature = "server", any(feature = "postgres", feature = "pgvector")))]
+    pub(crate) async fn recorded_postgres(
+        pool: &PgPool,
+    ) -> Result<Option<EmbeddingProfile>, sqlx::Error> {
+        let exists =
+            sqlx::query_scalar::<_, bool>("SELECT to_regclass('embedding_profile') IS NOT NULL")
+                .fetch_one(pool)
+                .await?;
+        if !exists {
+            return Ok(None);
+        }
+        let row = sqlx::query(
+            "SELECT name, query_prefix, document_prefix, normalize, max_input_chars FROM embedding_profile LIMIT 1",
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(row.map(|row| EmbeddingProfile {
+            name: row.get::<String, _>("name"),
+            query_prefix: row.get::<String, _>("query_prefix"),
+            document_prefix: row.get::<String, _>("document_prefix"),
+            normalize: row.get::<bool, _>("normalize"),
+            max_input_chars: row.get::<i64, _>("max_input_chars") as usize,
+        }))
+    }
+}
+
+impl Display for EmbeddingProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let EmbeddingProfile {
+            name,
+            query_prefix,
+            document_prefix,
+            normalize,
+            max_input_chars,
+        } = self;
+        write!(
+            f,
+            "{name} (query prefix {query_prefix:?}, document prefix {document_prefix:?}, normalize {normalize}, max {max_input_chars} chars)"
+        )
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ParseEmbeddingProfileError;
+impl Error for ParseEmbeddingProfileError {}
+impl Display for ParseEmbeddingProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unable to parse embedding profile. Must be one of [raw, e5, bge, gte, gte-instruct, nomic]"
+        )
+    }
+}
+
+impl FromStr for EmbeddingProfile {
+    type Err = ParseEmbeddingProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        let max = EMBED_MAX_STR_LEN_ACCORDING_TO_INFINITY;
+
+        match s.as_str() {
+            "raw" => Ok(Self::new("raw", "", "", false, max)),
+            "e5" => Ok(Self::new("e5", "query: ", "passage: ", true, 2048)),
+            "bge" => Ok(Self::new(
+                "bge",
+                "Represent this sentence for searching relevant passages: ",
+                "",
+                true,
+                2048,
+            )),
+            "gte" => Ok(Self::new("gte", "", "", true, 2048)),
+            "gte-instruct" => Ok(Self::new(
+                "gte-instruct",
+                "Instruct: Given a web search query, retrieve relevant passages that answer the query\nQuery: ",
+                "",
+                true,
+                max,
+            )),
+            "nomic" => Ok(Self::new(
+                "nomic",
+                "search_query: ",
+                "search_document: ",
+                true,
+                32768,
+            )),
+            _ => Err(ParseEmbeddingProfileError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::EmbeddingProfile;
+
+    #[test]
+    fn asymmetric_prefixes() {
+        let e5 = EmbeddingProfile::from_str("e5").unwrap();
+        assert_eq!(e5.query("capital of France"), "query: capital of France");
+        assert_eq!(e5.document("Paris"), "passage: Paris");
+    }
+
+    #[test]
+    fn normalized_and_truncated() {
+        let profile =
+            EmbeddingProfile::from_str("gte")
+                .unwrap()
+                .with_overrides(None, None, None, Some(4));
+        assert_eq!(profile.finish(vec![3.0, 4.0]), vec![0.6, 0.8]);
+        assert_eq!(profile.document("truncated"), "trun");
+    }
+}
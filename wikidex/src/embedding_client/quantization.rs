@@ -0,0 +1,252 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+#[cfg(feature = "sqlite")]
+use sqlx::{Row, SqliteConnection};
+
+/// How the index database stores the vectors search is scored with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EmbeddingQuantization {
+    /// Little endian `f32`s.
+    F32,
+    /// One byte per dimension, scaled between the per dimension minimum and maximum seen while
+    /// calibrating.
+    Int8,
+}
+
+impl Display for EmbeddingQuantization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmbeddingQuantization::F32 => write!(f, "f32"),
+            EmbeddingQuantization::Int8 => write!(f, "int8"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ParseEmbeddingQuantizationError(String);
+impl Error for ParseEmbeddingQuantizationError {}
+impl Display for ParseEmbeddingQuantizationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unable to parse quantization {}. Must be one of [f32, int8]",
+            self.0
+        )
+    }
+}
+
+impl FromStr for EmbeddingQuantization {
+    type Err = ParseEmbeddingQuantizationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f32" => Ok(EmbeddingQuantization::F32),
+            "int8" => Ok(EmbeddingQuantization::Int8),
+            _ => Err(ParseEmbeddingQuantizationError(s.to_string())),
+        }
+    }
+}
+
+/// Encodes embeddings for the index database, and decodes them again.
+///
+/// With `binary` the ingest also stores a 1-bit code per dimension, set where the value is
+/// positive, which local indices search by Hamming distance before rescoring the best candidates.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Quantizer {
+    pub(crate) kind: EmbeddingQuantization,
+    pub(crate) binary: bool,
+    pub(crate) dimensions: usize,
+    /// Per dimension, empty for `f32`.
+    pub(crate) minimum: Vec<f32>,
+    /// Per dimension, empty for `f32`.
+    pub(crate) scale: Vec<f32>,
+}
+
+impl Quantizer {
+    /// Raw `f32`s without codes, how indices were written before quantization was recorded.
+    pub(crate) fn raw(dimensions: usize) -> Self {
+        Self {
+            kind: EmbeddingQuantization::F32,
+            binary: false,
+            dimensions,
+            minimum: vec![],
+            scale: vec![],
+        }
+    }
+
+    /// Takes the range of every dimension from a sample of embeddings.
+    pub(crate) fn calibrate(
+        kind: EmbeddingQuantization,
+        binary: bool,
+        sample: &[Vec<f32>],
+    ) -> Self {
+        let dimensions = sample.first().map_or(0, Vec::len);
+        let (minimum, scale) = match kind {
+            EmbeddingQuantization::F32 => (vec![], vec![]),
+            EmbeddingQuantization::Int8 => {
+                let mut minimum = vec![f32::MAX; dimensions];
+                let mut maximum = vec![f32::MIN; dimensions];
+                for embedding in sample {
+                    for (d, value) in embedding.iter().enumerate().take(dimensions) {
+                        minimum[d] = minimum[d].min(*value);
+                        maximum[d] = maximum[d].max(*value);
+                    }
+                }
+                let scale = minimum
+                    .iter()
+                    .zip(&maximum)
+                    .map(|(min, max)| ((max - min) / 255f32).max(f32::EPSILON))
+                    .collect();
+                (minimum, scale)
+            }
+        };
+        Self {
+            kind,
+            binary,
+            dimensions,
+            minimum,
+            scale,
+        }
+    }
+
+    /// Bytes per stored vector.
+    pub(crate) fn vector_len(&self) -> usize {
+        match self.kind {
+            EmbeddingQuantization::F32 => self.dimensions * 4,
+            EmbeddingQuantization::Int8 => self.dimensions,
+        }
+    }
+
+    /// Bytes per binary code.
+    pub(crate) fn code_len(&self) -> usize {
+        self.dimensions.div_ceil(8)
+    }
+
+    pub(crate) fn encode(&self, embedding: &[f32]) -> Vec<u8> {
+        match self.kind {
+            EmbeddingQuantization::F32 => embedding.iter().flat_map(|f| f.to_le_bytes()).collect(),
+            EmbeddingQuantization::Int8 => embedding
+                .iter()
+                .zip(self.minimum.iter().zip(&self.scale))
+                .map(|(value, (min, scale))| {
+                    let level = ((value - min) / scale).round().clamp(0f32, 255f32);
+                    (level as i16 - 128) as i8 as u8
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn decode(&self, bytes: &[u8]) -> Vec<f32> {
+        match self.kind {
+            EmbeddingQuantization::F32 => bytes
+                .chunks_exact(4)
+                .map(|f| f32::from_le_bytes(f.try_into().unwrap()))
+                .collect(),
+            EmbeddingQuantization::Int8 => bytes
+                .iter()
+                .zip(self.minimum.iter().zip(&self.scale))
+                .map(|(byte, (min, scale))| min + (*byte as i8 as f32 + 128f32) * scale)
+                .collect(),
+        }
+    }
+
+    /// One bit per dimension, set where the value is positive.
+    pub(crate) fn code(embedding: &[f32]) -> Vec<u8> {
+        embedding
+            .chunks(8)
+            .map(|bits| {
+                bits.iter().enumerate().fold(0u8, |code, (bit, value)| {
+                    code | (((*value > 0f32) as u8) << bit)
+                })
+            })
+            .collect()
+    }
+
+    /// The quantization recorded by the ingest, `None` for indices written before it was or before
+    /// their first batch. Other failures are errors.
+    #[cfg(feature = "sqlite")]
+    pub(crate) async fn load(
+        connection: &mut SqliteConnection,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let has_table = sqlx::query(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'quantization'",
+        )
+        .fetch_optional(&mut *connection)
+        .await?
+        .is_some();
+        if !has_table {
+            return Ok(None);
+        }
+        let Some(row) = sqlx::query(
+            "SELECT kind, binary_codes, dimensions, minimum, scale FROM quantization LIMIT 1",
+        )
+        .fetch_optional(&mut *connection)
+        .await?
+        else {
+            return Ok(None);
+        };
+        let kind = row
+            .get::<String, _>("kind")
+            .parse::<EmbeddingQuantization>()
+            .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+        let floats = |bytes: Vec<u8>| {
+            bytes
+                .chunks_exact(4)
+                .map(|f| f32::from_le_bytes(f.try_into().unwrap()))
+                .collect::<Vec<_>>()
+        };
+        Ok(Some(Self {
+            kind,
+            binary: row.get::<bool, _>("binary_codes"),
+            dimensions: row.get::<i64, _>("dimensions") as usize,
+            minimum: floats(row.get("minimum")),
+            scale: floats(row.get("scale")),
+        }))
+    }
+
+    #[cfg(feature = "sqlite")]
+    pub(crate) async fn save(&self, connection: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+        let floats = |values: &[f32]| {
+            values
+                .iter()
+                .flat_map(|f| f.to_le_bytes())
+                .collect::<Vec<_>>()
+        };
+        sqlx::query("DELETE FROM quantization")
+            .execute(&mut *connection)
+            .await?;
+        sqlx::query(
+            "INSERT INTO quantization (kind, binary_codes, dimensions, minimum, scale) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(self.kind.to_string())
+        .bind(self.binary)
+        .bind(self.dimensions as i64)
+        .bind(floats(&self.minimum))
+        .bind(floats(&self.scale))
+        .execute(&mut *connection)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EmbeddingQuantization, Quantizer};
+
+    #[test]
+    fn int8_round_trip() {
+        let sample = vec![vec![-1.0, 0.0, 2.0], vec![1.0, 0.5, 4.0]];
+        let quantizer = Quantizer::calibrate(EmbeddingQuantization::Int8, false, &sample);
+        let decoded = quantizer.decode(&quantizer.encode(&[0.0, 0.25, 3.0]));
+        for (value, expected) in decoded.iter().zip([0.0, 0.25, 3.0]) {
+            assert!((value - expected).abs() < 0.01, "{value} != {expected}");
+        }
+        assert_eq!(quantizer.vector_len(), 3);
+    }
+
+    #[test]
+    fn binary_code_bits() {
+        let embedding = [0.5, -0.5, 0.1, -0.1, 0.0, 1.0, 1.0, 1.0, 0.3];
+        assert_eq!(Quantizer::code(&embedding), vec![0b1110_0101, 0b0000_0001]);
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Context;
+
+use crate::llm_client::{
+    create_embedding_request, deserialize_fp32_tensor, ModelReadyRequest, TritonClient,
+};
+
+use super::{error::EmbeddingServiceError, EmbeddingClientService};
+
+pub(crate) struct TritonEmbeddingClient {
+    client: TritonClient,
+    model_name: String,
+    input_name: String,
+    output_name: String,
+    batch_size: usize,
+}
+
+impl TritonEmbeddingClient {
+    pub(crate) fn new(
+        client: TritonClient,
+        model_name: String,
+        input_name: String,
+        output_name: String,
+        batch_size: usize,
+    ) -> Self {
+        Self {
+            client,
+            model_name,
+            input_name,
+            output_name,
+            batch_size: batch_size.max(1),
+        }
+    }
+
+    async fn embed_chunk(
+        &self,
+        queries: &[String],
+    ) -> Result<Vec<Vec<f32>>, EmbeddingServiceError> {
+        let request = create_embedding_request(
+            &self.model_name,
+            &self.input_name,
+            &self.output_name,
+            queries,
+        )?;
+
+        let response = self
+            .client
+            .clone()
+            .model_infer(tonic::Request::new(request))
+            .await?
+            .into_inner();
+
+        let output_index = response
+            .outputs
+            .iter()
+            .position(|output| output.name == self.output_name)
+            .ok_or(EmbeddingServiceError::MissingOutput(
+                self.output_name.clone(),
+            ))?;
+
+        let dimensions = response.outputs[output_index]
+            .shape
+            .last()
+            .map(|d| *d as usize)
+            .filter(|d| *d > 0)
+            .context("embedding output has no dimensions")?;
+
+        let raw_content = response
+            .raw_output_contents
+            .get(output_index)
+            .cloned()
+            .ok_or(EmbeddingServiceError::MissingOutput(
+                self.output_name.clone(),
+            ))?;
+
+        let embeddings = deserialize_fp32_tensor(raw_content)
+            .chunks_exact(dimensions)
+            .map(|embedding| embedding.to_vec())
+            .collect::<Vec<_>>();
+
+        if embeddings.len() != queries.len() {
+            Err(EmbeddingServiceError::EmbeddingSizeMismatch(
+                queries.len(),
+                embeddings.len(),
+            ))
+        } else {
+            Ok(embeddings)
+        }
+    }
+}
+
+impl EmbeddingClientService for TritonEmbeddingClient {
+    async fn up(&self) -> Result<(), EmbeddingServiceError> {
+        let request = tonic::Request::new(ModelReadyRequest {
+            name: self.model_name.clone(),
+            version: String::new(),
+        });
+        let response = self.client.clone().model_ready(request).await?.into_inner();
+
+        if response.ready {
+            Ok(())
+        } else {
+            Err(EmbeddingServiceError::ModelNotReady(
+                self.model_name.clone(),
+            ))
+        }
+    }
+
+    async fn embed_batch(
+        &self,
+        queries: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, EmbeddingServiceError> {
+        let mut embeddings = Vec::with_capacity(queries.len());
+        for chunk in queries.chunks(self.batch_size) {
+            embeddings.extend(self.embed_chunk(chunk).await?);
+        }
+        Ok(embeddings)
+    }
+
+    async fn embed(&self, query: &str) -> Result<Vec<f32>, EmbeddingServiceError> {
+        self.embed_chunk(&[query.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(EmbeddingServiceError::EmbeddingSizeMismatch(1, 0))
+    }
+}
@@ -1,7 +1,27 @@
-use super::style::CitationStyle;
+use serde_json::Value;
+
+use super::{metadata::encode_title, style::CitationStyle};
 
 pub(crate) trait Cite {
     fn format(&self, style: &CitationStyle) -> String;
     fn url(&self) -> String;
     fn title(&self) -> String;
+    /// Links to the revision cited, empty when the document has no url.
+    fn revision_url(&self) -> String;
+    /// A BibTeX entry under `key`.
+    fn bibtex(&self, key: &str) -> String;
+    /// A CSL-JSON item under `id`.
+    fn csl_json(&self, id: &str) -> Value;
+    /// An RIS record, ending with its `ER` tag.
+    fn ris(&self) -> String;
+
+    /// The url of a section of the cited document, empty when the document has no url.
+    fn section_url(&self, section: &str) -> String {
+        let url = self.url();
+        if url.is_empty() {
+            url
+        } else {
+            format!("{url}#{}", encode_title(section))
+        }
+    }
 }
@@ -0,0 +1,543 @@
+use std::collections::HashSet;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use utoipa::ToSchema;
+
+use super::{Cite, Provenance};
+
+/// A machine-readable reference format, for reference managers rather than readers.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ReferenceFormat {
+    Bibtex,
+    CslJson,
+    Ris,
+}
+
+impl ReferenceFormat {
+    pub(crate) fn content_type(&self) -> &'static str {
+        match self {
+            ReferenceFormat::Bibtex => "application/x-bibtex; charset=utf-8",
+            ReferenceFormat::CslJson => "application/vnd.citationstyles.csl+json",
+            ReferenceFormat::Ris => "application/x-research-info-systems; charset=utf-8",
+        }
+    }
+}
+
+/// The kind of a cited work, as each format names it.
+enum Work {
+    Encyclopedia,
+    WebPage,
+    Document,
+    Book,
+    Article,
+}
+
+impl Work {
+    fn bibtex(&self) -> &'static str {
+        match self {
+            Work::Encyclopedia | Work::WebPage | Work::Document => "misc",
+            Work::Book => "book",
+            Work::Article => "article",
+        }
+    }
+
+    fn csl(&self) -> &'static str {
+        match self {
+            Work::Encyclopedia => "entry-encyclopedia",
+            Work::WebPage => "webpage",
+            Work::Document => "document",
+            Work::Book => "book",
+            Work::Article => "article-journal",
+        }
+    }
+
+    fn ris(&self) -> &'static str {
+        match self {
+            Work::Encyclopedia => "ENCYC",
+            Work::WebPage => "ELEC",
+            Work::Document => "GEN",
+            Work::Book => "BOOK",
+            Work::Article => "JOUR",
+        }
+    }
+}
+
+enum Issued {
+    Date(NaiveDate),
+    Year(i32),
+}
+
+impl Issued {
+    fn year(&self) -> i32 {
+        match self {
+            Issued::Date(date) => date.year(),
+            Issued::Year(year) => *year,
+        }
+    }
+
+    fn date_parts(&self) -> Value {
+        match self {
+            Issued::Date(date) => date_parts(date),
+            Issued::Year(year) => json!({ "date-parts": [[year]] }),
+        }
+    }
+}
+
+fn date_parts(date: &NaiveDate) -> Value {
+    json!({ "date-parts": [[date.year(), date.month(), date.day()]] })
+}
+
+/// The fields of a cited work every format is written from.
+struct Reference {
+    work: Work,
+    title: String,
+    authors: Vec<String>,
+    /// The encyclopedia, web site, corpus or journal the work is part of.
+    container: Option<String>,
+    publisher: Option<String>,
+    issued: Option<Issued>,
+    accessed: NaiveDate,
+    url: Option<String>,
+    doi: Option<String>,
+    isbn: Option<String>,
+    /// Where a local file lies.
+    location: Option<String>,
+    language: Option<String>,
+}
+
+impl From<&Provenance> for Reference {
+    fn from(provenance: &Provenance) -> Self {
+        let url = Some(provenance.url()).filter(|url| !url.is_empty());
+        let reference = Reference {
+            work: Work::Document,
+            title: provenance.title(),
+            authors: vec![],
+            container: None,
+            publisher: None,
+            issued: None,
+            accessed: NaiveDate::default(),
+            url,
+            doi: None,
+            isbn: None,
+            location: None,
+            language: None,
+        };
+        match provenance {
+            Provenance::Wikipedia(_, access_date, edit_date, _, wiki) => Reference {
+                work: Work::Encyclopedia,
+                container: Some(wiki.name()),
+                language: Some(wiki.language.clone()),
+                publisher: wiki.publisher.clone(),
+                issued: Some(Issued::Date(*edit_date)),
+                accessed: *access_date,
+                ..reference
+            },
+            Provenance::Internal(corpus, _, access_date, edit_date) => Reference {
+                container: Some(corpus.clone()),
+                issued: Some(Issued::Date(*edit_date)),
+                accessed: *access_date,
+                ..reference
+            },
+            Provenance::WebPage(_, page, access_date, edit_date) => Reference {
+                work: Work::WebPage,
+                authors: page.author.iter().cloned().collect(),
+                container: page.site.clone(),
+                issued: Some(Issued::Date(page.published.unwrap_or(*edit_date))),
+                accessed: *access_date,
+                ..reference
+            },
+            Provenance::LocalFile(_, file, access_date, edit_date) => Reference {
+                issued: Some(Issued::Date(*edit_date)),
+                accessed: *access_date,
+                location: Some(match file.page {
+                    Some(page) => format!("{}, p. {page}", file.path),
+                    None => file.path.clone(),
+                }),
+                ..reference
+            },
+            Provenance::Publication(_, publication, access_date, _) => {
+                // A work with an ISBN and no DOI is taken for a book, anything else for a paper.
+                let book = publication.isbn.is_some() && publication.doi.is_none();
+                Reference {
+                    work: if book { Work::Book } else { Work::Article },
+                    authors: publication.authors.clone(),
+                    container: (!book).then(|| publication.publisher.clone()).flatten(),
+                    publisher: book.then(|| publication.publisher.clone()).flatten(),
+                    issued: publication.year.map(Issued::Year),
+                    accessed: *access_date,
+                    doi: publication.doi.clone(),
+                    isbn: publication.isbn.clone(),
+                    ..reference
+                }
+            }
+        }
+    }
+}
+
+impl Reference {
+    fn bibtex(&self, key: &str) -> String {
+        let mut fields = vec![("title", format!("{{{}}}", escape_bibtex(&self.title)))];
+        if !self.authors.is_empty() {
+            let authors = self
+                .authors
+                .iter()
+                .map(|author| escape_bibtex(author))
+                .collect::<Vec<_>>();
+            fields.push(("author", authors.join(" and ")));
+        }
+        if let Some(container) = &self.container {
+            let field = match self.work {
+                Work::Article => "journal",
+                _ => "howpublished",
+            };
+            fields.push((field, escape_bibtex(container)));
+        }
+        if let Some(publisher) = &self.publisher {
+            fields.push(("publisher", escape_bibtex(publisher)));
+        }
+        if let Some(issued) = &self.issued {
+            fields.push(("year", issued.year().to_string()));
+            if let Issued::Date(date) = issued {
+                fields.push(("date", date.format("%Y-%m-%d").to_string()));
+            }
+        }
+        if let Some(url) = &self.url {
+            fields.push(("url", url.clone()));
+            fields.push(("urldate", self.accessed.format("%Y-%m-%d").to_string()));
+        }
+        if let Some(doi) = &self.doi {
+            fields.push(("doi", doi.clone()));
+        }
+        if let Some(isbn) = &self.isbn {
+            fields.push(("isbn", isbn.clone()));
+        }
+        if let Some(location) = &self.location {
+            fields.push(("note", escape_bibtex(location)));
+        }
+        if let Some(language) = &self.language {
+            fields.push(("language", language.clone()));
+        }
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| format!("  {name} = {{{value}}}"))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        format!("@{}{{{key},\n{fields}\n}}\n", self.work.bibtex())
+    }
+
+    fn csl_json(&self, id: &str) -> Value {
+        let mut item = Map::new();
+        item.insert("id".to_string(), json!(id));
+        item.insert("type".to_string(), json!(self.work.csl()));
+        item.insert("title".to_string(), json!(self.title));
+        if !self.authors.is_empty() {
+            let authors = self
+                .authors
+                .iter()
+                .map(|author| json!({ "literal": author }))
+                .collect::<Vec<_>>();
+            item.insert("author".to_string(), Value::Array(authors));
+        }
+        let fields = [
+            ("container-title", &self.container),
+            ("publisher", &self.publisher),
+            ("URL", &self.url),
+            ("DOI", &self.doi),
+            ("ISBN", &self.isbn),
+            ("archive_location", &self.location),
+            ("language", &self.language),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                item.insert(name.to_string(), json!(value));
+            }
+        }
+        if let Some(issued) = &self.issued {
+            item.insert("issued".to_string(), issued.date_parts());
+        }
+        item.insert("accessed".to_string(), date_parts(&self.accessed));
+        Value::Object(item)
+    }
+
+    fn ris(&self) -> String {
+        let mut lines = vec![("TY", self.work.ris().to_string())];
+        lines.push(("TI", self.title.clone()));
+        lines.extend(self.authors.iter().map(|author| ("AU", author.clone())));
+        if let Some(container) = &self.container {
+            lines.push(("T2", container.clone()));
+        }
+        if let Some(publisher) = &self.publisher {
+            lines.push(("PB", publisher.clone()));
+        }
+        if let Some(issued) = &self.issued {
+            lines.push(("PY", issued.year().to_string()));
+            if let Issued::Date(date) = issued {
+                lines.push(("DA", date.format("%Y/%m/%d").to_string()));
+            }
+        }
+        if let Some(url) = &self.url {
+            lines.push(("UR", url.clone()));
+            lines.push(("Y2", self.accessed.format("%Y/%m/%d").to_string()));
+        }
+        if let Some(doi) = &self.doi {
+            lines.push(("DO", doi.clone()));
+        }
+        if let Some(isbn) = &self.isbn {
+            lines.push(("SN", isbn.clone()));
+        }
+        if let Some(location) = &self.location {
+            lines.push(("AV", location.clone()));
+        }
+        if let Some(language) = &self.language {
+            lines.push(("LA", language.clone()));
+        }
+        lines.push(("ER", String::new()));
+        // A tag's value ends with its line, so line breaks within it are folded into spaces.
+        lines
+            .into_iter()
+            .map(|(tag, value)| {
+                let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+                format!("{tag}  - {value}\n")
+            })
+            .collect()
+    }
+}
+
+pub(super) fn bibtex(provenance: &Provenance, key: &str) -> String {
+    Reference::from(provenance).bibtex(key)
+}
+
+pub(super) fn csl_json(provenance: &Provenance, id: &str) -> Value {
+    Reference::from(provenance).csl_json(id)
+}
+
+pub(super) fn ris(provenance: &Provenance) -> String {
+    Reference::from(provenance).ris()
+}
+
+/// Escapes the characters BibTeX reads as markup.
+fn escape_bibtex(text: &str) -> String {
+    text.chars()
+        .fold(String::with_capacity(text.len()), |mut escaped, c| {
+            match c {
+                '{' | '}' | '%' | '&' | '$' | '#' | '_' => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                _ => escaped.push(c),
+            }
+            escaped
+        })
+}
+
+/// A key from the ASCII letters and digits of the first author's last name, or of the first title
+/// words, and the year, suffixed `a`, `b`, ... when `used` already holds it. BibTeX reads only
+/// ASCII in keys.
+fn citation_key(provenance: &Provenance, used: &mut HashSet<String>) -> String {
+    let reference = Reference::from(provenance);
+    let words = match reference.authors.first() {
+        Some(author) => author
+            .split_whitespace()
+            .last()
+            .map(str::to_string)
+            .into_iter()
+            .collect::<Vec<_>>(),
+        None => reference
+            .title
+            .split_whitespace()
+            .take(3)
+            .map(str::to_string)
+            .collect(),
+    };
+    let mut base = words
+        .concat()
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<String>();
+    if base.is_empty() {
+        base.push_str("ref");
+    }
+    if let Some(issued) = &reference.issued {
+        base.push_str(&issued.year().to_string());
+    }
+    let mut key = base.clone();
+    let mut duplicate = 0;
+    while !used.insert(key.clone()) {
+        duplicate += 1;
+        key = format!("{base}{}", suffix(duplicate));
+    }
+    key
+}
+
+/// The letters telling the `n`th duplicate of a key apart, counting from one: `a` to `z`, then
+/// `aa`, `ab` and so on.
+fn suffix(mut n: usize) -> String {
+    let mut letters = vec![];
+    while n > 0 {
+        n -= 1;
+        letters.push(b'a' + (n % 26) as u8);
+        n /= 26;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap_or_default()
+}
+
+/// The references of `provenances` in `format`, keyed so that no two entries share a key.
+pub(crate) fn bibliography(provenances: &[Provenance], format: ReferenceFormat) -> String {
+    let mut used = HashSet::new();
+    let keyed = provenances
+        .iter()
+        .map(|provenance| (citation_key(provenance, &mut used), provenance));
+    match format {
+        ReferenceFormat::Bibtex => keyed
+            .map(|(key, provenance)| provenance.bibtex(&key))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ReferenceFormat::CslJson => {
+            let items = keyed
+                .map(|(key, provenance)| provenance.csl_json(&key))
+                .collect::<Vec<_>>();
+            serde_json::to_string_pretty(&items).unwrap_or_default()
+        }
+        ReferenceFormat::Ris => keyed.map(|(_, provenance)| provenance.ris()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    use super::{bibliography, ReferenceFormat};
+    use crate::formatter::{metadata::Publication, Cite, Provenance, Wiki};
+
+    fn wikipedia() -> Provenance {
+        Provenance::Wikipedia(
+            "Austrian German".to_string(),
+            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            1178374855,
+            Wiki::default(),
+        )
+    }
+
+    fn paper() -> Provenance {
+        Provenance::Publication(
+            "Attention is all you need".to_string(),
+            Publication {
+                authors: vec!["Ashish Vaswani".to_string(), "Noam Shazeer".to_string()],
+                publisher: Some("Advances in Neural Information Processing Systems".to_string()),
+                year: Some(2017),
+                doi: Some("10.48550/arXiv.1706.03762".to_string()),
+                isbn: None,
+            },
+            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap(),
+        )
+    }
+
+    #[test]
+    fn bibtex() {
+        let expected = "@misc{austriangerman2023,
+  title = {{Austrian German}},
+  howpublished = {Wikipedia},
+  publisher = {Wikimedia Foundation},
+  year = {2023},
+  date = {2023-10-01},
+  url = {https://en.wikipedia.org/wiki/Austrian_German},
+  urldate = {2023-10-02},
+  language = {en}
+}
+";
+        assert_eq!(
+            expected,
+            bibliography(&[wikipedia()], ReferenceFormat::Bibtex)
+        );
+        assert!(paper()
+            .bibtex("vaswani2017")
+            .starts_with("@article{vaswani2017,\n  title = {{Attention is all you need}},\n  author = {Ashish Vaswani and Noam Shazeer},\n  journal = {Advances in Neural Information Processing Systems},"));
+    }
+
+    #[test]
+    fn csl_json() {
+        assert_eq!(
+            json!({
+                "id": "vaswani2017",
+                "type": "article-journal",
+                "title": "Attention is all you need",
+                "author": [{"literal": "Ashish Vaswani"}, {"literal": "Noam Shazeer"}],
+                "container-title": "Advances in Neural Information Processing Systems",
+                "URL": "https://doi.org/10.48550/arXiv.1706.03762",
+                "DOI": "10.48550/arXiv.1706.03762",
+                "issued": {"date-parts": [[2017]]},
+                "accessed": {"date-parts": [[2023, 10, 2]]}
+            }),
+            paper().csl_json("vaswani2017")
+        );
+    }
+
+    #[test]
+    fn ris() {
+        let expected = "TY  - ENCYC
+TI  - Austrian German
+T2  - Wikipedia
+PB  - Wikimedia Foundation
+PY  - 2023
+DA  - 2023/10/01
+UR  - https://en.wikipedia.org/wiki/Austrian_German
+Y2  - 2023/10/02
+LA  - en
+ER  - \n";
+        assert_eq!(expected, wikipedia().ris());
+
+        let multiline = Provenance::Publication(
+            "Attention\nis all\r\nyou need".to_string(),
+            Publication::default(),
+            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap(),
+        );
+        assert!(multiline
+            .ris()
+            .contains("TI  - Attention is all you need\n"));
+    }
+
+    #[test]
+    fn keys_are_unique() {
+        let bibtex = bibliography(&[paper(), paper()], ReferenceFormat::Bibtex);
+        assert!(bibtex.contains("@article{vaswani2017,"));
+        assert!(bibtex.contains("@article{vaswani2017a,"));
+
+        let papers = (0..300).map(|_| paper()).collect::<Vec<_>>();
+        let keys = bibliography(&papers, ReferenceFormat::Bibtex)
+            .lines()
+            .filter_map(|line| line.strip_prefix("@article{"))
+            .map(|key| key.trim_end_matches(',').to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(keys.len(), 300);
+        assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 300);
+        assert_eq!(keys[26], "vaswani2017z");
+        assert_eq!(keys[27], "vaswani2017aa");
+        assert!(keys
+            .iter()
+            .all(|key| key.chars().all(|c| c.is_ascii_alphanumeric())));
+    }
+
+    #[test]
+    fn keys_are_ascii() {
+        let wiki = Provenance::Wikipedia(
+            "Österreichisches Deutsch".to_string(),
+            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            1178374855,
+            Wiki::default(),
+        );
+        assert!(bibliography(&[wiki], ReferenceFormat::Bibtex)
+            .starts_with("@misc{sterreichischesdeutsch2023,"));
+    }
+}
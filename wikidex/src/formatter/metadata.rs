@@ -0,0 +1,166 @@
+use chrono::NaiveDate;
+use rkyv::{Archive, Deserialize, Serialize as RkyvSerialize};
+use serde::{Deserialize as SerdeDeserialize, Serialize as SerdeSerialize};
+
+/// Where a page of a web site was published, read from the `metadata` column of its article.
+#[derive(
+    Clone, Default, RkyvSerialize, SerdeSerialize, SerdeDeserialize, Deserialize, Archive, Debug,
+)]
+#[archive(check_bytes)]
+#[serde(default)]
+pub(crate) struct WebPage {
+    pub(crate) url: String,
+    /// The name of the web site, e.g. `BBC News`.
+    pub(crate) site: Option<String>,
+    pub(crate) author: Option<String>,
+    pub(crate) published: Option<NaiveDate>,
+}
+
+/// Where a file of a local collection lies, read from the `metadata` column of its article.
+#[derive(
+    Clone, Default, RkyvSerialize, SerdeSerialize, SerdeDeserialize, Deserialize, Archive, Debug,
+)]
+#[archive(check_bytes)]
+#[serde(default)]
+pub(crate) struct LocalFile {
+    pub(crate) path: String,
+    /// The page of the file the article was taken from, counting from 1.
+    pub(crate) page: Option<i64>,
+}
+
+/// How a book or a paper was published, read from the `metadata` column of its article.
+#[derive(
+    Clone, Default, RkyvSerialize, SerdeSerialize, SerdeDeserialize, Deserialize, Archive, Debug,
+)]
+#[archive(check_bytes)]
+#[serde(default)]
+pub(crate) struct Publication {
+    /// In the order they are credited, each as it should be printed.
+    pub(crate) authors: Vec<String>,
+    /// The publisher of a book, or the journal a paper appeared in.
+    pub(crate) publisher: Option<String>,
+    pub(crate) year: Option<i32>,
+    pub(crate) doi: Option<String>,
+    pub(crate) isbn: Option<String>,
+}
+
+impl Publication {
+    pub(crate) fn doi_url(&self) -> Option<String> {
+        self.doi
+            .as_ref()
+            .map(|doi| format!("https://doi.org/{}", doi.trim_start_matches("doi:")))
+    }
+}
+
+/// The wiki an article was exported from, read from the `metadata` column of its article. Articles
+/// without one are cited from the English Wikipedia, and metadata written before the publisher was
+/// recorded names the Wikimedia Foundation.
+#[derive(
+    Clone, RkyvSerialize, SerdeSerialize, SerdeDeserialize, Deserialize, Archive, Debug, PartialEq,
+)]
+#[archive(check_bytes)]
+#[serde(default)]
+pub(crate) struct Wiki {
+    /// The `<sitename>` of the dump, `Wikipedia` for every language edition.
+    pub(crate) site_name: String,
+    /// The language code of the edition, e.g. `de`.
+    pub(crate) language: String,
+    /// The scheme and host of the wiki, e.g. `https://de.wikipedia.org`.
+    pub(crate) base_url: String,
+    /// The Wikimedia Foundation for its projects, `None` for a wiki whose publisher is unknown.
+    pub(crate) publisher: Option<String>,
+}
+
+impl Default for Wiki {
+    fn default() -> Self {
+        Self {
+            site_name: "Wikipedia".to_string(),
+            language: "en".to_string(),
+            base_url: "https://en.wikipedia.org".to_string(),
+            publisher: Some("Wikimedia Foundation".to_string()),
+        }
+    }
+}
+
+impl Wiki {
+    /// The name citations give the wiki, followed by the language of any but the English edition,
+    /// e.g. `Wikipedia (de)`.
+    pub(crate) fn name(&self) -> String {
+        if self.language == "en" {
+            self.site_name.clone()
+        } else {
+            format!("{} ({})", self.site_name, self.language)
+        }
+    }
+
+    pub(crate) fn article_url(&self, title: &str) -> String {
+        format!("{}/wiki/{}", self.base_url, encode_title(title))
+    }
+
+    pub(crate) fn revision_url(&self, title: &str, revision_id: i64) -> String {
+        format!(
+            "{}/w/index.php?title={}&oldid={revision_id}",
+            self.base_url,
+            encode_title(title)
+        )
+    }
+}
+
+/// Encodes a title, or a section heading, as MediaWiki writes it into links: spaces become `_`
+/// and every byte outside of the characters MediaWiki leaves as they are is percent-encoded.
+pub(super) fn encode_title(title: &str) -> String {
+    title
+        .replace(' ', "_")
+        .bytes()
+        .fold(String::with_capacity(title.len()), |mut encoded, byte| {
+            match byte {
+                b'A'..=b'Z'
+                | b'a'..=b'z'
+                | b'0'..=b'9'
+                | b'-'
+                | b'_'
+                | b'.'
+                | b'~'
+                | b'!'
+                | b'$'
+                | b'('
+                | b')'
+                | b'*'
+                | b','
+                | b'/'
+                | b':'
+                | b';'
+                | b'@' => encoded.push(byte as char),
+                _ => encoded.push_str(&format!("%{byte:02X}")),
+            }
+            encoded
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::Wiki;
+
+    #[test]
+    fn wiki_urls() {
+        let wiki = Wiki {
+            site_name: "Wikipedia".to_string(),
+            language: "de".to_string(),
+            base_url: "https://de.wikipedia.org".to_string(),
+            ..Wiki::default()
+        };
+        assert_eq!(wiki.name(), "Wikipedia (de)");
+        assert_eq!(
+            wiki.article_url("Österreichisches Deutsch"),
+            "https://de.wikipedia.org/wiki/%C3%96sterreichisches_Deutsch"
+        );
+        assert_eq!(
+            wiki.revision_url("AT&T", 42),
+            "https://de.wikipedia.org/w/index.php?title=AT%26T&oldid=42"
+        );
+        assert_eq!(
+            Wiki::default().article_url("C++ (programming language)"),
+            "https://en.wikipedia.org/wiki/C%2B%2B_(programming_language)"
+        );
+    }
+}
@@ -1,9 +1,13 @@
 mod citation;
 mod document;
+mod export;
+mod metadata;
 mod provenance;
 mod style;
 
 pub(crate) use citation::Cite;
 pub(crate) use document::TextFormatter;
-pub(crate) use provenance::Provenance;
+pub(crate) use export::{bibliography, ReferenceFormat};
+pub(crate) use metadata::Wiki;
+pub(crate) use provenance::{Provenance, ProvenanceKind};
 pub(crate) use style::CitationStyle;
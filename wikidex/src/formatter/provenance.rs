@@ -1,58 +1,331 @@
-use super::{CitationStyle, Cite};
+use super::{
+    export,
+    metadata::{LocalFile, Publication, WebPage, Wiki},
+    CitationStyle, Cite,
+};
+
+use std::{error::Error, fmt::Display, str::FromStr};
 
 use chrono::NaiveDate;
+use serde_json::Value;
 
 use rkyv::{Archive, Deserialize, Serialize as RkyvSerialize};
 
 type WikipediaArticleTitle = String;
+type CorpusName = String;
+type DocumentTitle = String;
 type AccessDate = NaiveDate;
 type LastModificationDate = NaiveDate;
+type RevisionId = i64;
 use serde::Serialize as SerdeSerialize;
 
 #[derive(Clone, RkyvSerialize, SerdeSerialize, Deserialize, Archive, Debug)]
+#[archive(check_bytes)]
 pub(crate) enum Provenance {
-    Wikipedia(WikipediaArticleTitle, AccessDate, LastModificationDate),
+    /// An article of a Wikipedia language edition, or of another wiki exported as a MediaWiki dump.
+    Wikipedia(
+        WikipediaArticleTitle,
+        AccessDate,
+        LastModificationDate,
+        RevisionId,
+        Wiki,
+    ),
+    /// A document of a corpus without public links, such as an internal knowledge base.
+    Internal(CorpusName, DocumentTitle, AccessDate, LastModificationDate),
+    /// A page of a web site.
+    WebPage(DocumentTitle, WebPage, AccessDate, LastModificationDate),
+    /// A file of a local collection, such as a report on a shared drive. It has no public link.
+    LocalFile(DocumentTitle, LocalFile, AccessDate, LastModificationDate),
+    /// A book or a paper.
+    Publication(DocumentTitle, Publication, AccessDate, LastModificationDate),
+}
+
+/// How the articles of a docstore are cited, recorded per article in the `provenance` column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ProvenanceKind {
+    Wikipedia,
+    Internal,
+    WebPage,
+    LocalFile,
+    Publication,
+}
+
+impl Display for ProvenanceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProvenanceKind::Wikipedia => write!(f, "wikipedia"),
+            ProvenanceKind::Internal => write!(f, "internal"),
+            ProvenanceKind::WebPage => write!(f, "web_page"),
+            ProvenanceKind::LocalFile => write!(f, "local_file"),
+            ProvenanceKind::Publication => write!(f, "publication"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ParseProvenanceKindError;
+impl Error for ParseProvenanceKindError {}
+impl Display for ParseProvenanceKindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unable to parse provenance kind. Must be one of [wikipedia, internal, web_page, local_file, publication]"
+        )
+    }
+}
+
+impl FromStr for ProvenanceKind {
+    type Err = ParseProvenanceKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "wikipedia" => Ok(ProvenanceKind::Wikipedia),
+            "internal" => Ok(ProvenanceKind::Internal),
+            "web_page" => Ok(ProvenanceKind::WebPage),
+            "local_file" => Ok(ProvenanceKind::LocalFile),
+            "publication" => Ok(ProvenanceKind::Publication),
+            _ => Err(ParseProvenanceKindError),
+        }
+    }
 }
 
 impl Cite for Provenance {
     fn format(&self, style: &CitationStyle) -> String {
         match self {
-            Provenance::Wikipedia(title, access_date, edit_date) => match style {
-                CitationStyle::Chigago => {
-                    let article_url = self.url();
-                    let access_date = access_date.format("%-d %B %Y");
-                    let edit_date = edit_date.format("%-d %B %Y");
-                    format!("\"{title}\" Wikipedia. Last modified {edit_date}, Accessed {access_date}, {article_url}.")
+            Provenance::Wikipedia(title, access_date, edit_date, _, wiki) => {
+                let publisher = wiki
+                    .publisher
+                    .as_ref()
+                    .map(|p| format!(" {p},"))
+                    .unwrap_or_default();
+                let wiki = wiki.name();
+                match style {
+                    CitationStyle::Chigago => {
+                        let article_url = self.url();
+                        let access_date = access_date.format("%-d %B %Y");
+                        let edit_date = edit_date.format("%-d %B %Y");
+                        format!("\"{title}\" {wiki}. Last modified {edit_date}, Accessed {access_date}, {article_url}.")
+                    }
+                    CitationStyle::Mla => {
+                        let article_url = self.url();
+                        let access_date = access_date.format("%-d %B %Y");
+                        let edit_date = edit_date.format("%-d %B %Y");
+                        format!("\"{title}\" {wiki},{publisher} {edit_date}, {article_url}. Accessed {access_date}.")
+                    }
+                    CitationStyle::Apa => {
+                        let article_url = self.url();
+                        let access_date = access_date.format("%B %-d, %Y");
+                        let edit_date = edit_date.format("%Y, %B %-d");
+                        format!("{title}. {edit_date}. In {wiki}. Retrieved {access_date}, from {article_url}")
+                    }
                 }
-                CitationStyle::Mla => {
-                    let article_url = self.url();
-                    let access_date = access_date.format("%-d %B %Y");
-                    let edit_date = edit_date.format("%-d %B %Y");
-                    format!("\"{title}\" Wikipedia, Wikimedia Foundation, {edit_date}, {article_url}. Accessed {access_date}.")
+            }
+            Provenance::Internal(corpus, title, access_date, edit_date) => {
+                match style {
+                    CitationStyle::Chigago => {
+                        let access_date = access_date.format("%-d %B %Y");
+                        let edit_date = edit_date.format("%-d %B %Y");
+                        format!("\"{title}\" {corpus}. Last modified {edit_date}, Accessed {access_date}.")
+                    }
+                    CitationStyle::Mla => {
+                        let access_date = access_date.format("%-d %B %Y");
+                        let edit_date = edit_date.format("%-d %B %Y");
+                        format!("\"{title}\" {corpus}, {edit_date}. Accessed {access_date}.")
+                    }
+                    CitationStyle::Apa => {
+                        let access_date = access_date.format("%B %-d, %Y");
+                        let edit_date = edit_date.format("%Y, %B %-d");
+                        format!("{title}. {edit_date}. In {corpus}. Retrieved {access_date}")
+                    }
                 }
-                CitationStyle::Apa => {
-                    let article_url = self.url();
-                    let access_date = access_date.format("%B %-d, %Y");
-                    let edit_date = edit_date.format("%Y, %B %-d");
-                    format!("{title}. {edit_date}. In Wikipedia. Retrieved {access_date}, from {article_url}")
+            }
+            Provenance::WebPage(title, page, access_date, edit_date) => {
+                let url = &page.url;
+                let author = page.author.as_deref().map(without_period);
+                let date = page.published.unwrap_or(*edit_date);
+                match style {
+                    CitationStyle::Chigago => {
+                        let author = author.map(|a| format!("{a}. ")).unwrap_or_default();
+                        let site = page
+                            .site
+                            .as_ref()
+                            .map(|s| format!(" {s}."))
+                            .unwrap_or_default();
+                        let access_date = access_date.format("%-d %B %Y");
+                        let date = date.format("%-d %B %Y");
+                        format!("{author}\"{title}.\"{site} Last modified {date}, Accessed {access_date}, {url}.")
+                    }
+                    CitationStyle::Mla => {
+                        let author = author.map(|a| format!("{a}. ")).unwrap_or_default();
+                        let site = page
+                            .site
+                            .as_ref()
+                            .map(|s| format!(" {s},"))
+                            .unwrap_or_default();
+                        let access_date = access_date.format("%-d %B %Y");
+                        let date = date.format("%-d %B %Y");
+                        format!("{author}\"{title}.\"{site} {date}, {url}. Accessed {access_date}.")
+                    }
+                    CitationStyle::Apa => {
+                        let site = page
+                            .site
+                            .as_ref()
+                            .map(|s| format!(" {s}."))
+                            .unwrap_or_default();
+                        let access_date = access_date.format("%B %-d, %Y");
+                        let date = date.format("%Y, %B %-d");
+                        let credit = match author {
+                            Some(author) => format!("{author}. ({date}). {title}."),
+                            None => format!("{title}. ({date})."),
+                        };
+                        format!("{credit}{site} Retrieved {access_date}, from {url}")
+                    }
                 }
-            },
+            }
+            Provenance::LocalFile(title, file, access_date, edit_date) => {
+                let location = match file.page {
+                    Some(page) => format!("{}, p. {page}", file.path),
+                    None => file.path.clone(),
+                };
+                match style {
+                    CitationStyle::Chigago => {
+                        let access_date = access_date.format("%-d %B %Y");
+                        let edit_date = edit_date.format("%-d %B %Y");
+                        format!("\"{title}\" {location}. Last modified {edit_date}, Accessed {access_date}.")
+                    }
+                    CitationStyle::Mla => {
+                        let access_date = access_date.format("%-d %B %Y");
+                        let edit_date = edit_date.format("%-d %B %Y");
+                        format!("\"{title}\" {location}, {edit_date}. Accessed {access_date}.")
+                    }
+                    CitationStyle::Apa => {
+                        let access_date = access_date.format("%B %-d, %Y");
+                        let edit_date = edit_date.format("%Y, %B %-d");
+                        format!("{title}. {edit_date}. {location}. Retrieved {access_date}")
+                    }
+                }
+            }
+            Provenance::Publication(title, publication, _, _) => {
+                let authors = &publication.authors;
+                let publisher = publication.publisher.clone();
+                let year = publication.year.map(|year| year.to_string());
+                let doi = publication.doi_url();
+                match style {
+                    CitationStyle::Chigago => {
+                        let authors = join_authors(authors, ", ", " and ", ", and ")
+                            .map(|a| format!("{}. ", without_period(&a)))
+                            .unwrap_or_default();
+                        let published = [publisher, year].into_iter().flatten().collect::<Vec<_>>();
+                        let published = if published.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" {}.", published.join(", "))
+                        };
+                        let doi = doi.map(|doi| format!(" {doi}.")).unwrap_or_default();
+                        format!("{authors}\"{title}.\"{published}{doi}")
+                    }
+                    CitationStyle::Mla => {
+                        let authors = mla_authors(authors)
+                            .map(|a| format!("{}. ", without_period(&a)))
+                            .unwrap_or_default();
+                        let published = [publisher, year, doi]
+                            .into_iter()
+                            .flatten()
+                            .collect::<Vec<_>>();
+                        let published = if published.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" {}.", published.join(", "))
+                        };
+                        format!("{authors}\"{title}.\"{published}")
+                    }
+                    CitationStyle::Apa => {
+                        let year = year.unwrap_or_else(|| "n.d.".to_string());
+                        let credit = match join_authors(authors, ", ", ", & ", ", & ") {
+                            Some(authors) => {
+                                format!("{}. ({year}). {title}.", without_period(&authors))
+                            }
+                            None => format!("{title}. ({year})."),
+                        };
+                        let publisher = publisher.map(|p| format!(" {p}.")).unwrap_or_default();
+                        let doi = doi.map(|doi| format!(" {doi}")).unwrap_or_default();
+                        format!("{credit}{publisher}{doi}")
+                    }
+                }
+            }
         }
     }
 
     fn url(&self) -> String {
         match self {
-            Provenance::Wikipedia(title, _, _) => {
-                format!("https://en.wikipedia.org/wiki/{}", title.replace(' ', "_"))
+            Provenance::Wikipedia(title, _, _, _, wiki) => wiki.article_url(title),
+            Provenance::Internal(..) | Provenance::LocalFile(..) => String::new(),
+            Provenance::WebPage(_, page, _, _) => page.url.clone(),
+            Provenance::Publication(_, publication, _, _) => {
+                publication.doi_url().unwrap_or_default()
+            }
+        }
+    }
+
+    fn revision_url(&self) -> String {
+        match self {
+            Provenance::Wikipedia(title, _, _, revision_id, wiki) => {
+                wiki.revision_url(title, *revision_id)
             }
+            Provenance::Internal(..)
+            | Provenance::WebPage(..)
+            | Provenance::LocalFile(..)
+            | Provenance::Publication(..) => String::new(),
         }
     }
 
     fn title(&self) -> String {
         match self {
-            Provenance::Wikipedia(title, _, _) => title.to_string(),
+            Provenance::Wikipedia(title, ..) => title.to_string(),
+            Provenance::Internal(_, title, _, _) => title.to_string(),
+            Provenance::WebPage(title, ..)
+            | Provenance::LocalFile(title, ..)
+            | Provenance::Publication(title, ..) => title.to_string(),
         }
     }
+
+    fn bibtex(&self, key: &str) -> String {
+        export::bibtex(self, key)
+    }
+
+    fn csl_json(&self, id: &str) -> Value {
+        export::csl_json(self, id)
+    }
+
+    fn ris(&self) -> String {
+        export::ris(self)
+    }
+}
+
+/// Drops the period a name may end with, which the citation adds back.
+fn without_period(name: &str) -> &str {
+    name.trim_end_matches('.')
+}
+
+/// Authors as MLA credits them: one, two joined by `and`, or the first followed by `et al.`
+fn mla_authors(authors: &[String]) -> Option<String> {
+    match authors {
+        [] => None,
+        [author] => Some(author.clone()),
+        [first, second] => Some(format!("{first}, and {second}")),
+        [first, ..] => Some(format!("{first}, et al.")),
+    }
+}
+
+/// Every author, `pair` between two of them, otherwise `separator` between all but the last two
+/// and `last` before the last.
+fn join_authors(authors: &[String], separator: &str, pair: &str, last: &str) -> Option<String> {
+    match authors {
+        [] => None,
+        [author] => Some(author.clone()),
+        [first, second] => Some(format!("{first}{pair}{second}")),
+        [init @ .., final_author] => Some(format!("{}{last}{final_author}", init.join(separator))),
+    }
 }
 
 #[cfg(test)]
@@ -67,6 +340,8 @@ mod test {
             "Austrian German".to_string(),
             NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
             NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            1178374855,
+            Wiki::default(),
         );
 
         assert_eq!(expected, provenance.format(&CitationStyle::Mla))
@@ -79,6 +354,8 @@ mod test {
             "Austrian German".to_string(),
             NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
             NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            1178374855,
+            Wiki::default(),
         );
 
         assert_eq!(expected, provenance.format(&CitationStyle::Apa))
@@ -91,8 +368,156 @@ mod test {
             "Austrian German".to_string(),
             NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
             NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            1178374855,
+            Wiki::default(),
         );
 
         assert_eq!(expected, provenance.format(&CitationStyle::Chigago))
     }
+    #[test]
+    fn wiki_edition_mla() {
+        let expected = r#""Österreichisches Deutsch" Wikipedia (de), Wikimedia Foundation, 1 October 2023, https://de.wikipedia.org/wiki/%C3%96sterreichisches_Deutsch. Accessed 1 October 2023."#;
+
+        let provenance = Provenance::Wikipedia(
+            "Österreichisches Deutsch".to_string(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            240003412,
+            Wiki {
+                site_name: "Wikipedia".to_string(),
+                language: "de".to_string(),
+                base_url: "https://de.wikipedia.org".to_string(),
+                ..Wiki::default()
+            },
+        );
+
+        assert_eq!(expected, provenance.format(&CitationStyle::Mla))
+    }
+    #[test]
+    fn other_wiki_mla() {
+        let expected = r#""Spock" Memory Alpha, 1 October 2023, https://memory-alpha.fandom.com/wiki/Spock. Accessed 1 October 2023."#;
+
+        let provenance = Provenance::Wikipedia(
+            "Spock".to_string(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            1,
+            Wiki {
+                site_name: "Memory Alpha".to_string(),
+                language: "en".to_string(),
+                base_url: "https://memory-alpha.fandom.com".to_string(),
+                publisher: None,
+            },
+        );
+
+        assert_eq!(expected, provenance.format(&CitationStyle::Mla))
+    }
+    #[test]
+    fn internal_mla() {
+        let expected = r#""Onboarding" Handbook, 1 October 2023. Accessed 1 October 2023."#;
+
+        let provenance = Provenance::Internal(
+            "Handbook".to_string(),
+            "Onboarding".to_string(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+        );
+
+        assert_eq!(expected, provenance.format(&CitationStyle::Mla));
+        assert_eq!(String::new(), provenance.url());
+        assert_eq!(String::new(), provenance.revision_url());
+    }
+    #[test]
+    fn web_page() {
+        let provenance = Provenance::WebPage(
+            "Mars mission".to_string(),
+            WebPage {
+                url: "https://www.bbc.com/news/science".to_string(),
+                site: Some("BBC News".to_string()),
+                author: Some("Jonathan Amos".to_string()),
+                published: NaiveDate::from_ymd_opt(2023, 10, 1),
+            },
+            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap(),
+        );
+
+        assert_eq!(
+            r#"Jonathan Amos. "Mars mission." BBC News, 1 October 2023, https://www.bbc.com/news/science. Accessed 2 October 2023."#,
+            provenance.format(&CitationStyle::Mla)
+        );
+        assert_eq!(
+            r#"Jonathan Amos. (2023, October 1). Mars mission. BBC News. Retrieved October 2, 2023, from https://www.bbc.com/news/science"#,
+            provenance.format(&CitationStyle::Apa)
+        );
+        assert_eq!(
+            r#"Jonathan Amos. "Mars mission." BBC News. Last modified 1 October 2023, Accessed 2 October 2023, https://www.bbc.com/news/science."#,
+            provenance.format(&CitationStyle::Chigago)
+        );
+        assert_eq!("https://www.bbc.com/news/science", provenance.url());
+    }
+    #[test]
+    fn local_file_mla() {
+        let provenance = Provenance::LocalFile(
+            "Quarterly report".to_string(),
+            LocalFile {
+                path: "/srv/reports/q3.pdf".to_string(),
+                page: Some(12),
+            },
+            NaiveDate::from_ymd_opt(2023, 10, 2).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+        );
+
+        assert_eq!(
+            r#""Quarterly report" /srv/reports/q3.pdf, p. 12, 1 October 2023. Accessed 2 October 2023."#,
+            provenance.format(&CitationStyle::Mla)
+        );
+        assert_eq!(String::new(), provenance.url());
+    }
+    #[test]
+    fn publication() {
+        let provenance = Provenance::Publication(
+            "Attention is all you need".to_string(),
+            Publication {
+                authors: vec![
+                    "Ashish Vaswani".to_string(),
+                    "Noam Shazeer".to_string(),
+                    "Niki Parmar".to_string(),
+                ],
+                publisher: Some("Advances in Neural Information Processing Systems".to_string()),
+                year: Some(2017),
+                doi: Some("10.48550/arXiv.1706.03762".to_string()),
+                isbn: None,
+            },
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+        );
+
+        assert_eq!(
+            r#"Ashish Vaswani, et al. "Attention is all you need." Advances in Neural Information Processing Systems, 2017, https://doi.org/10.48550/arXiv.1706.03762."#,
+            provenance.format(&CitationStyle::Mla)
+        );
+        assert_eq!(
+            r#"Ashish Vaswani, Noam Shazeer, & Niki Parmar. (2017). Attention is all you need. Advances in Neural Information Processing Systems. https://doi.org/10.48550/arXiv.1706.03762"#,
+            provenance.format(&CitationStyle::Apa)
+        );
+        assert_eq!(
+            r#"Ashish Vaswani, Noam Shazeer, and Niki Parmar. "Attention is all you need." Advances in Neural Information Processing Systems, 2017. https://doi.org/10.48550/arXiv.1706.03762."#,
+            provenance.format(&CitationStyle::Chigago)
+        );
+    }
+    #[test]
+    fn wiki_revision_url() {
+        let provenance = Provenance::Wikipedia(
+            "Austrian German".to_string(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
+            1178374855,
+            Wiki::default(),
+        );
+
+        assert_eq!(
+            "https://en.wikipedia.org/w/index.php?title=Austrian_German&oldid=1178374855",
+            provenance.revision_url()
+        );
+    }
 }
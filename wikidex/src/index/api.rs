@@ -5,7 +5,7 @@ use face_api::{
 
 use url::Url;
 
-use super::{IndexSearchError, SearchService};
+use super::{service::overfetch, IndexSearchError, SearchFilter, SearchService};
 
 pub(crate) struct FaceIndex {
     configuration: Configuration,
@@ -25,14 +25,35 @@ impl FaceIndex {
 
         Self { configuration }
     }
+
+    /// Face answers with the neighbors only, in order, so they carry no score.
+    async fn query(
+        &self,
+        query: Vec<f32>,
+        neighbors: usize,
+    ) -> Result<Vec<(i64, Option<f32>)>, IndexSearchError> {
+        let request = FaceQuery::new(neighbors as i32, query);
+        let response = face::query(&self.configuration, request).await?;
+        Ok(response
+            .neighbors
+            .into_iter()
+            .map(|id| (id, None))
+            .collect())
+    }
 }
 
 impl SearchService for FaceIndex {
     type E = IndexSearchError;
 
-    async fn search(&self, query: Vec<f32>, neighbors: usize) -> Result<Vec<i64>, Self::E> {
-        let request = FaceQuery::new(neighbors as i32, query);
-        let response = face::query(&self.configuration, request).await?;
-        Ok(response.neighbors)
+    async fn search<F: SearchFilter>(
+        &self,
+        query: Vec<f32>,
+        neighbors: usize,
+        filter: Option<&F>,
+    ) -> Result<Vec<(i64, Option<f32>)>, Self::E> {
+        overfetch(neighbors, usize::MAX, filter, |candidates| {
+            self.query(query.clone(), candidates)
+        })
+        .await
     }
 }
@@ -1,12 +1,51 @@
 use face_api::apis::{crate_api::QueryError, Error};
+
+use crate::docstore::DocstoreRetrieveError;
 use std::{
     error::Error as StdError,
     fmt::{Debug, Display, Formatter, Result},
 };
 
+#[derive(Debug)]
+pub enum IndexLoadError {
+    Io(std::io::Error),
+    Database(sqlx::error::Error),
+    InvalidIndex(String),
+}
+
+#[derive(Debug)]
+pub enum IndexBuildError {
+    Database(sqlx::error::Error),
+    InvalidEmbeddings(String),
+}
+
 #[derive(Debug)]
 pub enum IndexSearchError {
     QueryError(Error<QueryError>),
+    DimensionMismatch(usize, usize),
+    Join(tokio::task::JoinError),
+    Filter(DocstoreRetrieveError),
+    Database(sqlx::error::Error),
+    Io(std::io::Error),
+    ReadOnly,
+}
+
+impl From<std::io::Error> for IndexLoadError {
+    fn from(value: std::io::Error) -> Self {
+        IndexLoadError::Io(value)
+    }
+}
+
+impl From<sqlx::error::Error> for IndexLoadError {
+    fn from(value: sqlx::error::Error) -> Self {
+        IndexLoadError::Database(value)
+    }
+}
+
+impl From<sqlx::error::Error> for IndexBuildError {
+    fn from(value: sqlx::error::Error) -> Self {
+        IndexBuildError::Database(value)
+    }
 }
 
 impl From<Error<QueryError>> for IndexSearchError {
@@ -15,14 +54,87 @@ impl From<Error<QueryError>> for IndexSearchError {
     }
 }
 
+impl From<tokio::task::JoinError> for IndexSearchError {
+    fn from(value: tokio::task::JoinError) -> Self {
+        IndexSearchError::Join(value)
+    }
+}
+
+impl From<DocstoreRetrieveError> for IndexSearchError {
+    fn from(value: DocstoreRetrieveError) -> Self {
+        IndexSearchError::Filter(value)
+    }
+}
+
+impl From<sqlx::error::Error> for IndexSearchError {
+    fn from(value: sqlx::error::Error) -> Self {
+        IndexSearchError::Database(value)
+    }
+}
+
+impl From<std::io::Error> for IndexSearchError {
+    fn from(value: std::io::Error) -> Self {
+        IndexSearchError::Io(value)
+    }
+}
+
+impl StdError for IndexLoadError {}
+impl StdError for IndexBuildError {}
 impl StdError for IndexSearchError {}
 
+impl Display for IndexLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            IndexLoadError::Io(e) => write!(f, "IndexLoadError: Io: {e}"),
+            IndexLoadError::Database(e) => write!(f, "IndexLoadError: Database: {e}"),
+            IndexLoadError::InvalidIndex(message) => {
+                write!(f, "IndexLoadError: Invalid index: {message}")
+            }
+        }
+    }
+}
+
+impl Display for IndexBuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            IndexBuildError::Database(e) => write!(f, "IndexBuildError: Database: {e}"),
+            IndexBuildError::InvalidEmbeddings(message) => {
+                write!(f, "IndexBuildError: Invalid embeddings: {message}")
+            }
+        }
+    }
+}
+
 impl Display for IndexSearchError {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
             IndexSearchError::QueryError(err) => {
                 write!(f, "SearchService: {:?}", err)
             }
+            IndexSearchError::DimensionMismatch(index, query) => {
+                write!(
+                    f,
+                    "SearchService: index has {index} dimensions, query has {query}"
+                )
+            }
+            IndexSearchError::Join(err) => {
+                write!(f, "SearchService: {err}")
+            }
+            IndexSearchError::Filter(err) => {
+                write!(f, "SearchService: Filter: {err}")
+            }
+            IndexSearchError::Database(err) => {
+                write!(f, "SearchService: Database: {err}")
+            }
+            IndexSearchError::Io(err) => {
+                write!(f, "SearchService: Io: {err}")
+            }
+            IndexSearchError::ReadOnly => {
+                write!(
+                    f,
+                    "SearchService: Index does not support incremental updates"
+                )
+            }
         }
     }
 }
@@ -0,0 +1,746 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread,
+    time::SystemTime,
+};
+
+use futures::TryStreamExt;
+use memmap2::Mmap;
+use simsimd::SpatialSimilarity;
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::embedding_client::{EmbeddingQuantization, Quantizer};
+
+use super::{
+    service::overfetch, IncrementalSearchService, IndexLoadError, IndexSearchError, SearchFilter,
+    SearchService,
+};
+
+const MAGIC: &[u8; 8] = b"WDXFLAT2";
+const HEADER_LEN: usize = 40;
+/// Candidates the Hamming prefilter keeps per neighbor asked for, which are rescored exactly.
+const RESCORE_FACTOR: usize = 8;
+const MIN_RESCORE: usize = 64;
+/// Rows per statement when writing changes, within SQLite's limit on bound parameters.
+const WRITE_BATCH: usize = 512;
+
+/// Nearest neighbour search over the `embeddings` table of `wikipedia_index.sqlite`.
+///
+/// The vectors are copied once into a flat sidecar file next to the database, rebuilt whenever
+/// the database or its write ahead log is newer, and memory-mapped. The sidecar is a header
+/// (magic, count, dimensions, quantization, whether it has binary codes), the int8 calibration,
+/// the vectors as the ingest stored them, the binary codes, then `count` little endian `i64` ids.
+///
+/// Without binary codes every vector is scored. With them the codes are searched by Hamming
+/// distance first and only the best candidates are rescored against their vectors, so mostly the
+/// codes are paged in.
+///
+/// Ids in the `tombstones` table are left out of the sidecar. Vectors added and deleted while the
+/// index is loaded are written to the database and kept in memory until the next rebuild. When
+/// another process, such as an ingest, writes to the database, the next search reads its
+/// tombstones and the embeddings numbered after those it has seen.
+pub(crate) struct LocalIndex {
+    database: PathBuf,
+    vectors: Arc<FlatVectors>,
+    changes: Arc<RwLock<Changes>>,
+    synced: Mutex<Synced>,
+}
+
+/// How far the database has been read into memory.
+struct Synced {
+    /// The modification time of the database when it was last read.
+    modified: SystemTime,
+    /// The largest embedding id read.
+    through: i64,
+}
+
+/// Vectors added since the sidecar was built, and ids whose sidecar rows are hidden.
+#[derive(Default)]
+struct Changes {
+    added: HashMap<i64, Vec<f32>>,
+    tombstones: HashSet<i64>,
+}
+
+struct FlatVectors {
+    mmap: Mmap,
+    ids: Vec<i64>,
+    quantizer: Quantizer,
+    vectors: Range<usize>,
+    codes: Option<Range<usize>>,
+}
+
+/// A query prepared for the quantization of the sidecar.
+struct PreparedQuery {
+    /// The query, or for int8 the query in units of each dimension's quantization step.
+    values: Vec<f32>,
+    /// For int8, the squared quantization step of each dimension.
+    weights: Vec<f32>,
+    code: Vec<u8>,
+}
+
+impl LocalIndex {
+    pub(crate) async fn new(url: &Url) -> Result<Self, IndexLoadError> {
+        let database = url
+            .to_file_path()
+            .map_err(|_| IndexLoadError::InvalidIndex(format!("{url} is not a file path")))?;
+        let sidecar = sidecar_path(&database);
+        let modified = database_modified(&database)?;
+
+        if is_stale(&database, &sidecar)? {
+            log::info!("Building {} from {}", sidecar.display(), database.display());
+            build_sidecar(&database, &sidecar).await?;
+        }
+
+        let vectors = tokio::task::spawn_blocking(move || FlatVectors::open(&sidecar))
+            .await
+            .map_err(|e| IndexLoadError::InvalidIndex(e.to_string()))??;
+        log::info!(
+            "Loaded {} {} vectors of {} dimensions{}",
+            vectors.ids.len(),
+            vectors.quantizer.kind,
+            vectors.quantizer.dimensions,
+            if vectors.codes.is_some() {
+                " with binary codes"
+            } else {
+                ""
+            }
+        );
+        let through = vectors.ids.last().copied().unwrap_or(-1);
+        Ok(Self {
+            database,
+            vectors: Arc::new(vectors),
+            changes: Arc::default(),
+            synced: Mutex::new(Synced { modified, through }),
+        })
+    }
+
+    /// Reads the changes another process wrote to the database since it was last read: its
+    /// tombstones, and the embeddings numbered after the largest id read. Embeddings it replaced
+    /// under an id already read are not seen until the sidecar is rebuilt.
+    async fn refresh(&self) -> Result<(), IndexSearchError> {
+        let mut synced = self.synced.lock().await;
+        let modified = database_modified(&self.database)?;
+        if modified <= synced.modified {
+            return Ok(());
+        }
+
+        let pool =
+            SqlitePool::connect(&format!("sqlite://{}?mode=ro", self.database.display())).await?;
+        let tombstones = sqlx::query_scalar::<_, i64>("SELECT id FROM tombstones")
+            .fetch_all(&pool)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let added = sqlx::query("SELECT id, gte_small FROM embeddings WHERE id > ?1")
+            .bind(synced.through)
+            .fetch_all(&pool)
+            .await?;
+        pool.close().await;
+
+        let quantizer = &self.vectors.quantizer;
+        let mut changes = self.changes.write().unwrap_or_else(|e| e.into_inner());
+        for id in &tombstones {
+            changes.added.remove(id);
+        }
+        changes.tombstones.extend(tombstones);
+        for row in added {
+            let id = row.get::<i64, _>("id");
+            let embedding = row.get::<Vec<u8>, _>("gte_small");
+            if embedding.len() == quantizer.vector_len() && !changes.tombstones.contains(&id) {
+                changes.added.insert(id, quantizer.decode(&embedding));
+            }
+            synced.through = synced.through.max(id);
+        }
+        synced.modified = modified;
+        Ok(())
+    }
+
+    async fn connect(&self) -> Result<SqlitePool, sqlx::Error> {
+        let pool = SqlitePool::connect(&format!("sqlite://{}", self.database.display())).await?;
+        sqlx::query("CREATE TABLE IF NOT EXISTS tombstones ( id INTEGER PRIMARY KEY NOT NULL )")
+            .execute(&pool)
+            .await?;
+        Ok(pool)
+    }
+}
+
+impl LocalIndex {
+    /// Scores are `1 / (1 + d)` for the euclidean distance `d`.
+    async fn query(
+        &self,
+        query: Vec<f32>,
+        neighbors: usize,
+    ) -> Result<Vec<(i64, Option<f32>)>, IndexSearchError> {
+        let vectors = self.vectors.clone();
+        let changes = self.changes.clone();
+        if query.len() != vectors.quantizer.dimensions {
+            return Err(IndexSearchError::DimensionMismatch(
+                vectors.quantizer.dimensions,
+                query.len(),
+            ));
+        }
+
+        let nearest = tokio::task::spawn_blocking(move || {
+            let changes = changes.read().unwrap_or_else(|e| e.into_inner());
+            let prepared = vectors.prepare(&query);
+            let mut nearest = vectors
+                .nearest(&prepared, neighbors, |row| {
+                    changes.tombstones.contains(&vectors.ids[row])
+                })
+                .into_iter()
+                .map(|neighbor| (vectors.ids[neighbor.row], neighbor.distance))
+                .chain(changes.added.iter().map(|(id, vector)| {
+                    let distance = f32::sqeuclidean(vector, &query).unwrap_or(f64::MAX) as f32;
+                    (*id, distance)
+                }))
+                .collect::<Vec<_>>();
+            nearest.sort_by(|(a_id, a), (b_id, b)| a.total_cmp(b).then(a_id.cmp(b_id)));
+            nearest
+                .into_iter()
+                .take(neighbors)
+                // Squared distances, scored on the euclidean distance as pgvector's `<->` is.
+                .map(|(id, distance)| (id, Some(1f32 / (1f32 + distance.sqrt()))))
+                .collect::<Vec<_>>()
+        })
+        .await?;
+        Ok(nearest)
+    }
+}
+
+impl SearchService for LocalIndex {
+    type E = IndexSearchError;
+
+    async fn search<F: SearchFilter>(
+        &self,
+        query: Vec<f32>,
+        neighbors: usize,
+        filter: Option<&F>,
+    ) -> Result<Vec<(i64, Option<f32>)>, Self::E> {
+        self.refresh().await?;
+        overfetch(neighbors, usize::MAX, filter, |candidates| {
+            self.query(query.clone(), candidates)
+        })
+        .await
+    }
+}
+
+impl IncrementalSearchService for LocalIndex {
+    async fn add(&self, vectors: Vec<(i64, Vec<f32>)>) -> Result<(), Self::E> {
+        let quantizer = &self.vectors.quantizer;
+        if let Some((_, vector)) = vectors
+            .iter()
+            .find(|(_, vector)| vector.len() != quantizer.dimensions)
+        {
+            return Err(IndexSearchError::DimensionMismatch(
+                quantizer.dimensions,
+                vector.len(),
+            ));
+        }
+
+        let pool = self.connect().await?;
+        let mut transaction = pool.begin().await?;
+        for batch in vectors.chunks(WRITE_BATCH) {
+            QueryBuilder::<Sqlite>::new("INSERT OR REPLACE INTO embeddings (id, gte_small) ")
+                .push_values(batch, |mut row, (id, vector)| {
+                    row.push_bind(*id).push_bind(quantizer.encode(vector));
+                })
+                .build()
+                .execute(&mut *transaction)
+                .await?;
+            if quantizer.binary {
+                QueryBuilder::<Sqlite>::new("INSERT OR REPLACE INTO embedding_codes (id, code) ")
+                    .push_values(batch, |mut row, (id, vector)| {
+                        row.push_bind(*id).push_bind(Quantizer::code(vector));
+                    })
+                    .build()
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+            delete_where_id_in("tombstones", batch.iter().map(|(id, _)| *id))
+                .build()
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        pool.close().await;
+
+        let mut changes = self.changes.write().unwrap_or_else(|e| e.into_inner());
+        for (id, vector) in vectors {
+            // The sidecar row of a replaced vector is hidden, its new vector is searched instead.
+            changes.tombstones.insert(id);
+            changes.added.insert(id, vector);
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, ids: &[i64]) -> Result<(), Self::E> {
+        let pool = self.connect().await?;
+        let mut transaction = pool.begin().await?;
+        for batch in ids.chunks(WRITE_BATCH) {
+            delete_where_id_in("embeddings", batch.iter().copied())
+                .build()
+                .execute(&mut *transaction)
+                .await?;
+            if self.vectors.quantizer.binary {
+                delete_where_id_in("embedding_codes", batch.iter().copied())
+                    .build()
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+            QueryBuilder::<Sqlite>::new("INSERT OR IGNORE INTO tombstones (id) ")
+                .push_values(batch, |mut row, id| {
+                    row.push_bind(*id);
+                })
+                .build()
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        pool.close().await;
+
+        let mut changes = self.changes.write().unwrap_or_else(|e| e.into_inner());
+        for id in ids {
+            changes.added.remove(id);
+            changes.tombstones.insert(*id);
+        }
+        Ok(())
+    }
+}
+
+impl FlatVectors {
+    fn open(path: &Path) -> Result<Self, IndexLoadError> {
+        let file = File::open(path)?;
+        // SAFETY: the sidecar is only written through a temporary file which is renamed into place.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let invalid =
+            |problem: &str| IndexLoadError::InvalidIndex(format!("{} {problem}", path.display()));
+
+        if mmap.len() < HEADER_LEN || &mmap[..8] != MAGIC {
+            return Err(invalid("is not a flat vector file"));
+        }
+        let header = |field: usize| {
+            let start = 8 + field * 8;
+            u64::from_le_bytes(mmap[start..start + 8].try_into().unwrap()) as usize
+        };
+        let (count, dimensions, kind, binary) = (header(0), header(1), header(2), header(3) != 0);
+
+        let mut quantizer = Quantizer::raw(dimensions);
+        quantizer.binary = binary;
+        let mut offset = HEADER_LEN;
+        if kind == 1 {
+            let floats = |range: Range<usize>| {
+                mmap.get(range)
+                    .unwrap_or_default()
+                    .chunks_exact(4)
+                    .map(|f| f32::from_le_bytes(f.try_into().unwrap()))
+                    .collect::<Vec<_>>()
+            };
+            quantizer.kind = EmbeddingQuantization::Int8;
+            quantizer.minimum = floats(offset..offset + dimensions * 4);
+            quantizer.scale = floats(offset + dimensions * 4..offset + dimensions * 8);
+            offset += dimensions * 8;
+        }
+
+        let vectors = offset..offset + count * quantizer.vector_len();
+        let codes = binary.then(|| vectors.end..vectors.end + count * quantizer.code_len());
+        let ids_offset = codes.as_ref().map_or(vectors.end, |codes| codes.end);
+        if mmap.len() != ids_offset + count * 8 {
+            return Err(invalid("is truncated"));
+        }
+
+        let ids = mmap[ids_offset..]
+            .chunks_exact(8)
+            .map(|id| i64::from_le_bytes(id.try_into().unwrap()))
+            .collect();
+
+        let vectors = Self {
+            mmap,
+            ids,
+            quantizer,
+            vectors,
+            codes,
+        };
+        if vectors.quantizer.kind == EmbeddingQuantization::F32 {
+            // SAFETY: only inspects the alignment, `matrix` relies on it being checked here.
+            let (prefix, _, suffix) =
+                unsafe { vectors.mmap[vectors.vectors.clone()].align_to::<f32>() };
+            if !prefix.is_empty() || !suffix.is_empty() {
+                return Err(invalid("is misaligned"));
+            }
+        }
+        Ok(vectors)
+    }
+
+    fn matrix(&self) -> &[f32] {
+        // SAFETY: alignment and length are checked in `open`, and every bit pattern is a valid
+        // f32. The sidecar is written little endian, as are all supported targets.
+        let (_, matrix, _) = unsafe { self.mmap[self.vectors.clone()].align_to::<f32>() };
+        matrix
+    }
+
+    fn vector(&self, row: usize) -> &[u8] {
+        let len = self.quantizer.vector_len();
+        &self.mmap[self.vectors.start + row * len..self.vectors.start + (row + 1) * len]
+    }
+
+    fn code(&self, row: usize) -> &[u8] {
+        let len = self.quantizer.code_len();
+        let start = self.codes.as_ref().map_or(0, |codes| codes.start) + row * len;
+        &self.mmap[start..start + len]
+    }
+
+    fn prepare(&self, query: &[f32]) -> PreparedQuery {
+        let code = Quantizer::code(query);
+        match self.quantizer.kind {
+            EmbeddingQuantization::F32 => PreparedQuery {
+                values: query.to_vec(),
+                weights: vec![],
+                code,
+            },
+            EmbeddingQuantization::Int8 => {
+                let steps = self.quantizer.minimum.iter().zip(&self.quantizer.scale);
+                PreparedQuery {
+                    values: query
+                        .iter()
+                        .zip(steps.clone())
+                        .map(|(value, (min, scale))| (value - min) / scale - 128f32)
+                        .collect(),
+                    weights: steps.map(|(_, scale)| scale * scale).collect(),
+                    code,
+                }
+            }
+        }
+    }
+
+    /// Squared euclidean distance between a row and the query, for int8 rows between the
+    /// dequantized row and the query.
+    fn distance(&self, row: usize, query: &PreparedQuery) -> f32 {
+        let dimensions = self.quantizer.dimensions;
+        match self.quantizer.kind {
+            EmbeddingQuantization::F32 => {
+                let vector = &self.matrix()[row * dimensions..(row + 1) * dimensions];
+                f32::sqeuclidean(vector, &query.values).unwrap_or(f64::MAX) as f32
+            }
+            EmbeddingQuantization::Int8 => self
+                .vector(row)
+                .iter()
+                .zip(query.values.iter().zip(&query.weights))
+                .map(|(level, (value, weight))| {
+                    let difference = *level as i8 as f32 - value;
+                    weight * difference * difference
+                })
+                .sum(),
+        }
+    }
+
+    /// The `k` rows nearest to the query, nearest first, leaving out the rows `skip` accepts.
+    fn nearest<S: Fn(usize) -> bool + Sync>(
+        &self,
+        query: &PreparedQuery,
+        k: usize,
+        skip: S,
+    ) -> Vec<Neighbor> {
+        let rows = self.ids.len();
+        if self.codes.is_none() {
+            return nearest(rows, k, skip, |row| self.distance(row, query));
+        }
+
+        let candidates = (k * RESCORE_FACTOR).max(MIN_RESCORE);
+        let mut rescored = nearest(rows, candidates, skip, |row| {
+            hamming(self.code(row), &query.code) as f32
+        })
+        .into_iter()
+        .map(|candidate| Neighbor {
+            distance: self.distance(candidate.row, query),
+            row: candidate.row,
+        })
+        .collect::<Vec<_>>();
+        rescored.sort();
+        rescored.truncate(k);
+        rescored
+    }
+}
+
+fn hamming(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum()
+}
+
+#[derive(PartialEq)]
+struct Neighbor {
+    distance: f32,
+    row: usize,
+}
+
+impl Eq for Neighbor {}
+
+impl PartialOrd for Neighbor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Neighbor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.row.cmp(&other.row))
+    }
+}
+
+/// The `k` of `rows` rows nearest by `distance`, nearest first, leaving out the rows `skip`
+/// accepts. The rows are split across the available cores.
+fn nearest<S, D>(rows: usize, k: usize, skip: S, distance: D) -> Vec<Neighbor>
+where
+    S: Fn(usize) -> bool + Sync,
+    D: Fn(usize) -> f32 + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let rows_per_thread = rows.div_ceil(threads).max(1);
+
+    let mut heap = thread::scope(|scope| {
+        (0..rows)
+            .step_by(rows_per_thread)
+            .map(|start| {
+                let (skip, distance) = (&skip, &distance);
+                scope.spawn(move || {
+                    let mut heap = BinaryHeap::with_capacity(k + 1);
+                    for row in start..(start + rows_per_thread).min(rows) {
+                        if skip(row) {
+                            continue;
+                        }
+                        heap.push(Neighbor {
+                            distance: distance(row),
+                            row,
+                        });
+                        if heap.len() > k {
+                            heap.pop();
+                        }
+                    }
+                    heap
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect::<BinaryHeap<_>>()
+    });
+
+    while heap.len() > k {
+        heap.pop();
+    }
+    heap.into_sorted_vec()
+}
+
+fn sidecar_path(database: &Path) -> PathBuf {
+    let mut sidecar = database.as_os_str().to_owned();
+    sidecar.push(".flat");
+    PathBuf::from(sidecar)
+}
+
+/// `DELETE FROM table WHERE id IN (ids)`.
+fn delete_where_id_in<'args>(
+    table: &str,
+    ids: impl Iterator<Item = i64>,
+) -> QueryBuilder<'args, Sqlite> {
+    let mut query = QueryBuilder::new(format!("DELETE FROM {table} WHERE id IN ("));
+    let mut separated = query.separated(", ");
+    for id in ids {
+        separated.push_bind(id);
+    }
+    query.push(")");
+    query
+}
+
+/// The last modification of the database. Commits in WAL mode reach the database file only when
+/// they are checkpointed, so the write ahead log counts as part of the database.
+fn database_modified(database: &Path) -> Result<SystemTime, std::io::Error> {
+    let mut modified = std::fs::metadata(database)?.modified()?;
+    let mut wal = database.as_os_str().to_owned();
+    wal.push("-wal");
+    if let Ok(metadata) = std::fs::metadata(PathBuf::from(wal)) {
+        modified = modified.max(metadata.modified()?);
+    }
+    Ok(modified)
+}
+
+fn is_stale(database: &Path, sidecar: &Path) -> Result<bool, IndexLoadError> {
+    let database_modified = database_modified(database)?;
+    match std::fs::metadata(sidecar) {
+        Ok(metadata) => Ok(metadata.modified()? < database_modified),
+        Err(_) => Ok(true),
+    }
+}
+
+async fn build_sidecar(database: &Path, sidecar: &Path) -> Result<(), IndexLoadError> {
+    let pool = SqlitePool::connect(&format!("sqlite://{}?mode=ro", database.display())).await?;
+    let recorded = Quantizer::load(&mut *pool.acquire().await?).await?;
+
+    let has_tombstones =
+        sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'tombstones'")
+            .fetch_optional(&pool)
+            .await?
+            .is_some();
+    let live = if has_tombstones {
+        "WHERE e.id NOT IN (SELECT id FROM tombstones)"
+    } else {
+        ""
+    };
+
+    let mut temporary = sidecar.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut writer = BufWriter::new(File::create(&temporary)?);
+    writer.write_all(&[0u8; HEADER_LEN])?;
+    if let Some(quantizer) = recorded
+        .as_ref()
+        .filter(|q| q.kind == EmbeddingQuantization::Int8)
+    {
+        for value in quantizer.minimum.iter().chain(&quantizer.scale) {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+
+    let mut ids = vec![];
+    let mut quantizer = recorded;
+    let mut rows = sqlx::query(&format!(
+        "SELECT e.id, e.gte_small FROM embeddings e {live} ORDER BY e.id"
+    ))
+    .fetch(&pool);
+    while let Some(row) = rows.try_next().await? {
+        let id = row.get::<i64, _>("id");
+        let embedding = row.get::<Vec<u8>, _>("gte_small");
+        let quantizer = quantizer.get_or_insert_with(|| Quantizer::raw(embedding.len() / 4));
+        if embedding.len() != quantizer.vector_len() {
+            return Err(IndexLoadError::InvalidIndex(format!(
+                "embedding {id} has {} bytes, expected {}",
+                embedding.len(),
+                quantizer.vector_len()
+            )));
+        }
+        writer.write_all(&embedding)?;
+        ids.push(id);
+    }
+    drop(rows);
+    let quantizer = quantizer.unwrap_or_else(|| Quantizer::raw(0));
+
+    if quantizer.binary {
+        let mut codes = 0;
+        let mut rows = sqlx::query(&format!(
+            "SELECT c.code FROM embeddings e JOIN embedding_codes c ON c.id = e.id {live} ORDER BY e.id"
+        ))
+        .fetch(&pool);
+        while let Some(row) = rows.try_next().await? {
+            let code = row.get::<Vec<u8>, _>("code");
+            if code.len() != quantizer.code_len() {
+                return Err(IndexLoadError::InvalidIndex(format!(
+                    "binary code {codes} has {} bytes",
+                    code.len()
+                )));
+            }
+            writer.write_all(&code)?;
+            codes += 1;
+        }
+        if codes != ids.len() {
+            return Err(IndexLoadError::InvalidIndex(format!(
+                "{} embeddings but {codes} binary codes",
+                ids.len()
+            )));
+        }
+    }
+    pool.close().await;
+
+    for id in &ids {
+        writer.write_all(&id.to_le_bytes())?;
+    }
+    let kind = match quantizer.kind {
+        EmbeddingQuantization::F32 => 0u64,
+        EmbeddingQuantization::Int8 => 1u64,
+    };
+    writer.seek(SeekFrom::Start(0))?;
+    writer.write_all(MAGIC)?;
+    for field in [
+        ids.len() as u64,
+        quantizer.dimensions as u64,
+        kind,
+        quantizer.binary as u64,
+    ] {
+        writer.write_all(&field.to_le_bytes())?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+
+    std::fs::rename(&temporary, sidecar)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::File,
+        time::{Duration, SystemTime},
+    };
+
+    use simsimd::SpatialSimilarity;
+
+    use super::{hamming, is_stale, nearest, sidecar_path};
+
+    const MATRIX: [f32; 8] = [0.0, 0.0, 1.0, 1.0, 5.0, 5.0, 0.9, 1.2];
+
+    fn distance(row: usize) -> f32 {
+        f32::sqeuclidean(&MATRIX[row * 2..row * 2 + 2], &[1.0, 1.0]).unwrap() as f32
+    }
+
+    #[test]
+    fn nearest_rows_in_order() {
+        let rows = nearest(4, 3, |_| false, distance)
+            .into_iter()
+            .map(|neighbor| neighbor.row)
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec![1, 3, 0]);
+    }
+
+    #[test]
+    fn nearest_skips_tombstoned_rows() {
+        let rows = nearest(4, 3, |row| row == 1, distance)
+            .into_iter()
+            .map(|neighbor| neighbor.row)
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec![3, 0, 2]);
+    }
+
+    #[test]
+    fn hamming_distance() {
+        assert_eq!(hamming(&[0b1010_1010, 0xff], &[0b0101_1010, 0x0f]), 8);
+    }
+
+    #[test]
+    fn uncheckpointed_writes_make_the_sidecar_stale() {
+        let directory = std::env::temp_dir().join(format!("wikidex-stale-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let database = directory.join("index.sqlite");
+        let sidecar = sidecar_path(&database);
+        let wal = directory.join("index.sqlite-wal");
+        let now = SystemTime::now();
+        for (path, age) in [(&database, 20), (&sidecar, 10)] {
+            File::create(path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+        assert!(!is_stale(&database, &sidecar).unwrap());
+
+        File::create(&wal).unwrap().set_modified(now).unwrap();
+        assert!(is_stale(&database, &sidecar).unwrap());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
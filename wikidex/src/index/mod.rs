@@ -1,7 +1,84 @@
 mod api;
 mod error;
+#[cfg(feature = "sqlite")]
+mod local;
+#[cfg(feature = "pgvector")]
+mod pgvector;
 mod service;
 
 pub(crate) use api::FaceIndex;
-pub(crate) use error::IndexSearchError;
-pub(crate) use service::SearchService;
+pub(crate) use error::{IndexBuildError, IndexLoadError, IndexSearchError};
+#[cfg(feature = "sqlite")]
+pub(crate) use local::LocalIndex;
+#[cfg(all(feature = "pgvector", feature = "sqlite"))]
+pub(crate) use pgvector::write_pgvector;
+#[cfg(feature = "pgvector")]
+pub(crate) use pgvector::{PgVectorIndex, PgVectorIndexKind, PgVectorIndexParameters};
+pub(crate) use service::{IncrementalSearchService, SearchFilter, SearchService};
+
+use url::Url;
+
+pub(crate) enum SearchServiceImpl {
+    Face(FaceIndex),
+    #[cfg(feature = "sqlite")]
+    Local(LocalIndex),
+    #[cfg(feature = "pgvector")]
+    PgVector(PgVectorIndex),
+}
+
+impl SearchServiceImpl {
+    /// A face index server, a local index for `file://` urls or a pgvector index for
+    /// `postgres://` urls.
+    pub(crate) async fn new(index_url: Url) -> Result<Self, IndexLoadError> {
+        Ok(match index_url.scheme() {
+            #[cfg(feature = "sqlite")]
+            "file" => SearchServiceImpl::Local(LocalIndex::new(&index_url).await?),
+            #[cfg(feature = "pgvector")]
+            "postgres" | "postgresql" => {
+                SearchServiceImpl::PgVector(PgVectorIndex::new(&index_url).await?)
+            }
+            _ => SearchServiceImpl::Face(FaceIndex::new(index_url)),
+        })
+    }
+}
+
+impl SearchService for SearchServiceImpl {
+    type E = IndexSearchError;
+
+    async fn search<F: SearchFilter>(
+        &self,
+        query: Vec<f32>,
+        neighbors: usize,
+        filter: Option<&F>,
+    ) -> Result<Vec<(i64, Option<f32>)>, Self::E> {
+        match self {
+            SearchServiceImpl::Face(index) => index.search(query, neighbors, filter).await,
+            #[cfg(feature = "sqlite")]
+            SearchServiceImpl::Local(index) => index.search(query, neighbors, filter).await,
+            #[cfg(feature = "pgvector")]
+            SearchServiceImpl::PgVector(index) => index.search(query, neighbors, filter).await,
+        }
+    }
+}
+
+impl IncrementalSearchService for SearchServiceImpl {
+    async fn add(&self, vectors: Vec<(i64, Vec<f32>)>) -> Result<(), Self::E> {
+        match self {
+            SearchServiceImpl::Face(_) => Err(IndexSearchError::ReadOnly),
+            #[cfg(feature = "sqlite")]
+            SearchServiceImpl::Local(index) => index.add(vectors).await,
+            #[cfg(feature = "pgvector")]
+            SearchServiceImpl::PgVector(index) => index.add(vectors).await,
+        }
+    }
+
+    async fn delete(&self, ids: &[i64]) -> Result<(), Self::E> {
+        match self {
+            SearchServiceImpl::Face(_) => Err(IndexSearchError::ReadOnly),
+            #[cfg(feature = "sqlite")]
+            SearchServiceImpl::Local(index) => index.delete(ids).await,
+            #[cfg(feature = "pgvector")]
+            SearchServiceImpl::PgVector(index) => index.delete(ids).await,
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::{fmt::Display, str::FromStr};
+
+use sqlx::{postgres::PgPoolOptions, PgPool, Row};
+use url::Url;
+
+use super::{
+    service::overfetch, IncrementalSearchService, IndexLoadError, IndexSearchError, SearchFilter,
+    SearchService,
+};
+#[cfg(feature = "sqlite")]
+use {
+    super::IndexBuildError,
+    crate::embedding_client::{EmbeddingProfile, Quantizer},
+    futures::TryStreamExt,
+};
+
+/// The most candidates an HNSW scan may consider, pgvector rejects a larger `ef_search`.
+const MAX_EF_SEARCH: usize = 1000;
+#[cfg(feature = "sqlite")]
+const WRITE_BATCH_SIZE: usize = 1024;
+const UPSERT_EMBEDDINGS: &str = "INSERT INTO embeddings (id, embedding) SELECT id, embedding::vector FROM UNNEST($1::BIGINT[], $2::TEXT[]) AS batch(id, embedding) ON CONFLICT (id) DO UPDATE SET embedding = EXCLUDED.embedding";
+
+/// Nearest neighbour search over an `embeddings (id, embedding vector)` table in Postgres with the
+/// pgvector extension, written by [`write_pgvector`].
+pub(crate) struct PgVectorIndex {
+    pool: PgPool,
+    dimensions: usize,
+}
+
+impl PgVectorIndex {
+    pub(crate) async fn new(url: &Url) -> Result<Self, IndexLoadError> {
+        let pool = PgPoolOptions::new().connect(url.as_str()).await?;
+        let dimensions = sqlx::query(
+            "SELECT atttypmod FROM pg_attribute WHERE attrelid = 'embeddings'::regclass AND attname = 'embedding'",
+        )
+        .fetch_optional(&pool)
+        .await?
+        .map(|row| row.get::<i32, _>("atttypmod"))
+        .filter(|dimensions| *dimensions > 0)
+        .ok_or_else(|| {
+            IndexLoadError::InvalidIndex(format!("{url} has no embeddings of fixed dimension"))
+        })?;
+        log::info!("Searching vectors of {dimensions} dimensions in Postgres");
+
+        Ok(Self {
+            pool,
+            dimensions: dimensions as usize,
+        })
+    }
+}
+
+impl PgVectorIndex {
+    /// Scores are `1 / (1 + d)` for the euclidean distance `d`.
+    async fn query(
+        &self,
+        query: &[f32],
+        neighbors: usize,
+    ) -> Result<Vec<(i64, Option<f32>)>, IndexSearchError> {
+        if query.len() != self.dimensions {
+            return Err(IndexSearchError::DimensionMismatch(
+                self.dimensions,
+                query.len(),
+            ));
+        }
+
+        let mut transaction = self.pool.begin().await?;
+        // An HNSW scan returns at most `ef_search` rows, ignored by other index kinds.
+        sqlx::query(&format!(
+            "SET LOCAL hnsw.ef_search = {}",
+            neighbors.clamp(40, MAX_EF_SEARCH)
+        ))
+        .execute(&mut *transaction)
+        .await?;
+        let rows = sqlx::query(
+            "SELECT id, (embedding <-> $1::vector)::real AS distance FROM embeddings ORDER BY embedding <-> $1::vector LIMIT $2",
+        )
+        .bind(vector_literal(query))
+        .bind(neighbors as i64)
+        .fetch_all(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let distance = row.get::<f32, _>("distance");
+                (row.get::<i64, _>("id"), Some(1f32 / (1f32 + distance)))
+            })
+            .collect())
+    }
+}
+
+impl SearchService for PgVectorIndex {
+    type E = IndexSearchError;
+
+    async fn search<F: SearchFilter>(
+        &self,
+        query: Vec<f32>,
+        neighbors: usize,
+        filter: Option<&F>,
+    ) -> Result<Vec<(i64, Option<f32>)>, Self::E> {
+        overfetch(neighbors, MAX_EF_SEARCH, filter, |candidates| {
+            self.query(&query, candidates)
+        })
+        .await
+    }
+}
+
+/// Deleted rows are removed from the table, pgvector's indices skip them from then on.
+impl IncrementalSearchService for PgVectorIndex {
+    async fn add(&self, vectors: Vec<(i64, Vec<f32>)>) -> Result<(), Self::E> {
+        if let Some((_, vector)) = vectors
+            .iter()
+            .find(|(_, vector)| vector.len() != self.dimensions)
+        {
+            return Err(IndexSearchError::DimensionMismatch(
+                self.dimensions,
+                vector.len(),
+            ));
+        }
+        let (ids, vectors): (Vec<_>, Vec<_>) = vectors
+            .iter()
+            .map(|(id, vector)| (*id, vector_literal(vector)))
+            .unzip();
+        sqlx::query(UPSERT_EMBEDDINGS)
+            .bind(ids)
+            .bind(vectors)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, ids: &[i64]) -> Result<(), Self::E> {
+        sqlx::query("DELETE FROM embeddings WHERE id = ANY($1)")
+            .bind(ids)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// The approximate index pgvector builds over the `embedding` column.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PgVectorIndexKind {
+    Hnsw,
+    IvfFlat,
+}
+
+impl Display for PgVectorIndexKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgVectorIndexKind::Hnsw => write!(f, "hnsw"),
+            PgVectorIndexKind::IvfFlat => write!(f, "ivfflat"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ParsePgVectorIndexKindError(String);
+impl std::error::Error for ParsePgVectorIndexKindError {}
+impl Display for ParsePgVectorIndexKindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unable to parse index kind {}. Must be one of [hnsw, ivfflat]",
+            self.0
+        )
+    }
+}
+
+impl FromStr for PgVectorIndexKind {
+    type Err = ParsePgVectorIndexKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hnsw" => Ok(PgVectorIndexKind::Hnsw),
+            "ivfflat" => Ok(PgVectorIndexKind::IvfFlat),
+            _ => Err(ParsePgVectorIndexKindError(s.to_string())),
+        }
+    }
+}
+
+/// Build parameters of the pgvector index, see the pgvector documentation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PgVectorIndexParameters {
+    pub(crate) kind: PgVectorIndexKind,
+    pub(crate) m: usize,
+    pub(crate) ef_construction: usize,
+    pub(crate) lists: usize,
+}
+
+impl PgVectorIndexParameters {
+    fn create_index(&self) -> String {
+        match self.kind {
+            PgVectorIndexKind::Hnsw => format!(
+                "CREATE INDEX IF NOT EXISTS embeddings_embedding ON embeddings USING hnsw (embedding vector_l2_ops) WITH (m = {}, ef_construction = {})",
+                self.m, self.ef_construction
+            ),
+            PgVectorIndexKind::IvfFlat => format!(
+                "CREATE INDEX IF NOT EXISTS embeddings_embedding ON embeddings USING ivfflat (embedding vector_l2_ops) WITH (lists = {})",
+                self.lists
+            ),
+        }
+    }
+}
+
+/// Copies the `embeddings` table of the ingest's `wikipedia_index.sqlite` into Postgres, then
+/// builds the approximate index once every vector is loaded. Quantized embeddings are decoded back
+/// to `f32`s, pgvector stores full vectors. Rows already present are replaced,
+/// so an interrupted copy can be rerun. The embedding profile is copied along, so the server can
+/// check it against the pgvector database.
+#[cfg(feature = "sqlite")]
+pub(crate) async fn write_pgvector(
+    index_database_url: &Url,
+    index_url: &Url,
+    parameters: PgVectorIndexParameters,
+) -> Result<(), IndexBuildError> {
+    let source = sqlx::SqlitePool::connect(&format!("{index_database_url}?mode=ro")).await?;
+    let destination = PgPoolOptions::new().connect(index_url.as_str()).await?;
+
+    let quantizer = match Quantizer::load(&mut *source.acquire().await?).await? {
+        Some(quantizer) => quantizer,
+        None => sqlx::query("SELECT gte_small FROM embeddings LIMIT 1")
+            .fetch_optional(&source)
+            .await?
+            .map(|row| Quantizer::raw(row.get::<Vec<u8>, _>("gte_small").len() / 4))
+            .ok_or_else(|| {
+                IndexBuildError::InvalidEmbeddings(format!(
+                    "{index_database_url} has no embeddings"
+                ))
+            })?,
+    };
+    let dimensions = quantizer.dimensions;
+
+    sqlx::query("CREATE EXTENSION IF NOT EXISTS vector")
+        .execute(&destination)
+        .await?;
+    if let Some(profile) = EmbeddingProfile::recorded_sqlite(&source).await? {
+        write_profile(&destination, &profile).await?;
+    }
+    sqlx::query(&format!(
+        "CREATE TABLE IF NOT EXISTS embeddings (id BIGINT PRIMARY KEY, embedding vector({dimensions}) NOT NULL)"
+    ))
+    .execute(&destination)
+    .await?;
+
+    let mut written = 0usize;
+    let mut ids = Vec::with_capacity(WRITE_BATCH_SIZE);
+    let mut vectors = Vec::with_capacity(WRITE_BATCH_SIZE);
+    let mut rows = sqlx::query("SELECT id, gte_small FROM embeddings ORDER BY id").fetch(&source);
+    while let Some(row) = rows.try_next().await? {
+        let id = row.get::<i64, _>("id");
+        let embedding = quantizer.decode(&row.get::<Vec<u8>, _>("gte_small"));
+        if embedding.len() != dimensions {
+            return Err(IndexBuildError::InvalidEmbeddings(format!(
+                "embedding {id} has {} dimensions, expected {dimensions}",
+                embedding.len()
+            )));
+        }
+        ids.push(id);
+        vectors.push(vector_literal(&embedding));
+
+        if ids.len() == WRITE_BATCH_SIZE {
+            written += write_batch(&destination, &mut ids, &mut vectors).await?;
+            log::info!("Copied {written} embeddings");
+        }
+    }
+    written += write_batch(&destination, &mut ids, &mut vectors).await?;
+    log::info!("Copied {written} embeddings");
+
+    log::info!("Building {} index", parameters.kind);
+    sqlx::query(&parameters.create_index())
+        .execute(&destination)
+        .await?;
+    sqlx::query("ANALYZE embeddings")
+        .execute(&destination)
+        .await?;
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+async fn write_profile(pool: &PgPool, profile: &EmbeddingProfile) -> Result<(), IndexBuildError> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS embedding_profile (name TEXT NOT NULL, query_prefix TEXT NOT NULL, document_prefix TEXT NOT NULL, normalize BOOLEAN NOT NULL, max_input_chars BIGINT NOT NULL)",
+    )
+    .execute(pool)
+    .await?;
+    let mut transaction = pool.begin().await?;
+    sqlx::query("DELETE FROM embedding_profile")
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query(
+        "INSERT INTO embedding_profile (name, query_prefix, document_prefix, normalize, max_input_chars) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(&profile.name)
+    .bind(&profile.query_prefix)
+    .bind(&profile.document_prefix)
+    .bind(profile.normalize)
+    .bind(profile.max_input_chars as i64)
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+async fn write_batch(
+    pool: &PgPool,
+    ids: &mut Vec<i64>,
+    vectors: &mut Vec<String>,
+) -> Result<usize, IndexBuildError> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    sqlx::query(UPSERT_EMBEDDINGS)
+        .bind(&*ids)
+        .bind(&*vectors)
+        .execute(pool)
+        .await?;
+    let written = ids.len();
+    ids.clear();
+    vectors.clear();
+    Ok(written)
+}
+
+/// The text form of a pgvector `vector`.
+fn vector_literal(vector: &[f32]) -> String {
+    let elements = vector.iter().map(f32::to_string).collect::<Vec<_>>();
+    format!("[{}]", elements.join(","))
+}
+
+#[cfg(test)]
+mod test {
+    use super::vector_literal;
+
+    #[test]
+    fn vector_literal_format() {
+        assert_eq!(vector_literal(&[0.5, -1.0, 3.25]), "[0.5,-1,3.25]");
+    }
+}
@@ -1,6 +1,81 @@
-use std::error::Error;
+use std::{collections::HashSet, error::Error, future::Future};
+
+use super::IndexSearchError;
+
+/// Restricts a search to the documents matching some criteria the index does not store.
+pub(crate) trait SearchFilter {
+    /// The subset of `candidates` allowed by the filter.
+    async fn allowed(&self, candidates: &[i64]) -> Result<HashSet<i64>, IndexSearchError>;
+}
 
 pub(crate) trait SearchService {
     type E: Error;
-    async fn search(&self, query: Vec<f32>, neighbors: usize) -> Result<Vec<i64>, Self::E>;
+    /// The ids of the nearest documents, nearest first, with their similarity score, higher is
+    /// more similar. Indices which report only the order of the neighbors give no score.
+    async fn search<F: SearchFilter>(
+        &self,
+        query: Vec<f32>,
+        neighbors: usize,
+        filter: Option<&F>,
+    ) -> Result<Vec<(i64, Option<f32>)>, Self::E>;
+}
+
+/// An index which takes new vectors and forgets deleted ones without being rebuilt.
+pub(crate) trait IncrementalSearchService: SearchService {
+    /// Adds the vectors of new ids, replacing those of ids already indexed.
+    async fn add(&self, vectors: Vec<(i64, Vec<f32>)>) -> Result<(), Self::E>;
+    /// Tombstones the ids, which `search` no longer returns.
+    async fn delete(&self, ids: &[i64]) -> Result<(), Self::E>;
+}
+
+/// Each round asks for this many times the candidates of the last.
+const OVERFETCH_FACTOR: usize = 4;
+
+/// For indices which can not filter natively: fetches more candidates than needed and drops
+/// those the filter rejects, widening the search until enough remain or the index is exhausted,
+/// so a restrictive filter costs more rounds but still finds `neighbors` results. An index which
+/// returns at most `max_candidates` rows per search stops widening there, with fewer results.
+pub(super) async fn overfetch<F, S, Fut>(
+    neighbors: usize,
+    max_candidates: usize,
+    filter: Option<&F>,
+    search: S,
+) -> Result<Vec<(i64, Option<f32>)>, IndexSearchError>
+where
+    F: SearchFilter,
+    S: Fn(usize) -> Fut,
+    Fut: Future<Output = Result<Vec<(i64, Option<f32>)>, IndexSearchError>>,
+{
+    let Some(filter) = filter else {
+        return search(neighbors).await;
+    };
+
+    let mut candidates = neighbors
+        .saturating_mul(OVERFETCH_FACTOR)
+        .min(max_candidates);
+    loop {
+        let found = search(candidates).await?;
+        let ids = found.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        let allowed = filter.allowed(&ids).await?;
+        let kept = found
+            .iter()
+            .filter(|(id, _)| allowed.contains(id))
+            .take(neighbors)
+            .copied()
+            .collect::<Vec<_>>();
+
+        if kept.len() >= neighbors || found.len() < candidates {
+            return Ok(kept);
+        }
+        if candidates >= max_candidates {
+            log::warn!(
+                "Only {} of {max_candidates} candidates pass the filter, returning fewer than {neighbors} results.",
+                kept.len()
+            );
+            return Ok(kept);
+        }
+        candidates = candidates
+            .saturating_mul(OVERFETCH_FACTOR)
+            .min(max_candidates);
+    }
 }
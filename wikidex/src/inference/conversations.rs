@@ -0,0 +1,99 @@
+use std::{
+    collections::hash_map::RandomState, hash::BuildHasher, num::NonZeroUsize, sync::Mutex,
+    time::SystemTime,
+};
+
+use lru::LruCache;
+
+use crate::server::SourceKey;
+
+/// The sources answered in each conversation, for `/bibliography` to cite by the conversation's
+/// id.
+///
+/// Only the sources are stored, in process, for the `capacity` conversations answered most
+/// recently: a conversation is forgotten once as many others were answered since, and on restart.
+pub(crate) struct StoredConversations {
+    sources: Mutex<LruCache<String, Vec<SourceKey>>>,
+}
+
+impl StoredConversations {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            sources: Mutex::new(
+                NonZeroUsize::new(capacity).map_or_else(LruCache::unbounded, LruCache::new),
+            ),
+        }
+    }
+
+    /// Adds the sources of an answer to the conversation `id`, or to a new conversation when `id`
+    /// is absent or forgotten, and returns the conversation's id.
+    pub(crate) fn record(&self, id: Option<&str>, sources: Vec<SourceKey>) -> String {
+        let mut conversations = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+        let id = match id {
+            Some(id) if conversations.contains(id) => id.to_string(),
+            _ => new_id(),
+        };
+        let cited = conversations.get_or_insert_mut(id.clone(), Vec::new);
+        for source in sources {
+            if !cited.contains(&source) {
+                cited.push(source);
+            }
+        }
+        id
+    }
+
+    /// The sources of the conversation `id` in the order first answered, `None` when it is unknown
+    /// or forgotten.
+    pub(crate) fn sources(&self, id: &str) -> Option<Vec<SourceKey>> {
+        self.sources
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned()
+    }
+}
+
+/// A fresh id from randomly keyed hashes, so one conversation's id does not give away another's.
+fn new_id() -> String {
+    let state = RandomState::new();
+    format!(
+        "{:016x}{:016x}",
+        state.hash_one(SystemTime::now()),
+        RandomState::new().hash_one(0u8)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::StoredConversations;
+    use crate::server::SourceKey;
+
+    fn key(corpus: &str, index: i64) -> SourceKey {
+        SourceKey {
+            corpus: corpus.to_string(),
+            index,
+        }
+    }
+
+    #[test]
+    fn records_sources_per_conversation() {
+        let conversations = StoredConversations::new(1);
+        let id = conversations.record(None, vec![key("wikipedia", 2), key("wikipedia", 1)]);
+        assert_eq!(
+            id,
+            conversations.record(Some(&id), vec![key("wikipedia", 1), key("handbook", 1)])
+        );
+        assert_eq!(
+            conversations.sources(&id),
+            Some(vec![
+                key("wikipedia", 2),
+                key("wikipedia", 1),
+                key("handbook", 1)
+            ])
+        );
+
+        let other = conversations.record(Some("unknown"), vec![key("wikipedia", 3)]);
+        assert_ne!(other, id);
+        assert_eq!(conversations.sources(&id), None);
+    }
+}
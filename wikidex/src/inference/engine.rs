@@ -1,54 +1,154 @@
 use bytes::Bytes;
 
-use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedSender},
+    RwLock,
+};
+
+use futures::future::try_join_all;
 
 use crate::{
-    docstore::{Document, DocumentStore, DocumentStoreImpl},
-    embedding_client::{EmbeddingClient, EmbeddingClientService},
-    formatter::{CitationStyle, Cite},
-    index::{FaceIndex, SearchService},
+    corpus::{merge, Corpus, CorpusRegistry},
+    docstore::{
+        DocstoreFilter, Document, DocumentCacheStats, DocumentFilter, DocumentStore,
+        SharedDocumentCache,
+    },
+    embedding_client::{EmbeddingCache, EmbeddingCacheStats, EmbeddingClientImpl},
+    formatter::{CitationStyle, Cite, Provenance},
+    index::SearchService,
     llm_client::{
         LanguageServiceArguments, LanguageServiceDocument, LlmClientImpl, LlmClientService,
-        LlmMessage, LlmRole, PartialLlmMessage,
+        LlmMessage, LlmRole, PartialLlmMessage, PromptTemplates, TemplateKind,
+    },
+    server::{
+        Article, Conversation, Corpora, Message, PartialMessage, Passage, Source, SourceKey,
+        Templates, Usage,
     },
-    server::{Conversation, Message, PartialMessage, Source},
 };
 
-use super::QueryEngineError;
+use super::{conversations::StoredConversations, QueryEngineError};
 
 pub struct Engine {
-    index: FaceIndex,
-    embed_client: EmbeddingClient,
-    docstore: DocumentStoreImpl,
+    corpora: CorpusRegistry,
+    conversations: StoredConversations,
+    embed_client: EmbeddingClientImpl,
+    embedding_cache: EmbeddingCache,
+    document_cache: Arc<SharedDocumentCache>,
     llm_client: LlmClientImpl,
+    templates: Arc<RwLock<PromptTemplates>>,
 }
 
 impl Engine {
     pub(crate) async fn new(
-        index: FaceIndex,
-        embed_client: EmbeddingClient,
+        corpora: CorpusRegistry,
+        embed_client: EmbeddingClientImpl,
+        embedding_cache: EmbeddingCache,
+        document_cache: Arc<SharedDocumentCache>,
         llm_client: LlmClientImpl,
-        docstore: DocumentStoreImpl,
+        templates: Arc<RwLock<PromptTemplates>>,
     ) -> Self {
         Self {
-            index,
+            corpora,
+            conversations: StoredConversations::new(STORED_CONVERSATIONS),
             embed_client,
-            docstore,
+            embedding_cache,
+            document_cache,
             llm_client,
+            templates,
+        }
+    }
+}
+
+/// A retrieved document, the corpus it belongs to and the score its index gave it, if any.
+pub(crate) struct Retrieved {
+    corpus: String,
+    document: Document,
+    score: Option<f32>,
+}
+
+impl Retrieved {
+    fn key(&self) -> SourceKey {
+        SourceKey {
+            corpus: self.corpus.clone(),
+            index: self.document.index,
         }
     }
 }
 
 const NUM_DOCUMENTS_TO_RETRIEVE: usize = 4;
 
+/// Conversations whose sources are kept for `/bibliography`, the most recently answered.
+const STORED_CONVERSATIONS: usize = 10_000;
+
+/// The most sources one bibliography cites.
+const MAX_BIBLIOGRAPHY_SOURCES: usize = 1_000;
+
 const CITATION_STYLE: CitationStyle = CitationStyle::Mla;
 
 impl Engine {
+    pub(crate) fn embedding_cache_stats(&self) -> EmbeddingCacheStats {
+        self.embedding_cache.stats()
+    }
+
+    pub(crate) fn document_cache_stats(&self) -> DocumentCacheStats {
+        self.document_cache.stats()
+    }
+
+    pub(crate) async fn templates(&self) -> Templates {
+        Templates::from(self.templates.read().await.names())
+    }
+
+    /// Rejects a conversation naming a template which is not loaded.
+    pub(crate) async fn check_templates(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<(), QueryEngineError> {
+        let templates = self.templates.read().await;
+        templates.resolve(
+            TemplateKind::System,
+            conversation.system_template.as_deref(),
+        )?;
+        templates.resolve(TemplateKind::Chat, conversation.chat_template.as_deref())?;
+        Ok(())
+    }
+
+    pub(crate) fn corpora(&self) -> Corpora {
+        Corpora {
+            corpora: self.corpora.names(),
+            default: self.corpora.default_name().unwrap_or_default().to_string(),
+        }
+    }
+
+    /// Rejects a conversation naming a corpus which is not served.
+    pub(crate) fn check_corpora(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<(), QueryEngineError> {
+        self.corpora
+            .select(conversation.corpora.as_deref())
+            .map(|_| ())
+            .map_err(QueryEngineError::UnknownCorpus)
+    }
+
     pub(crate) async fn conversation(
         &self,
-        Conversation { messages }: Conversation,
+        conversation: Conversation,
         stop_phrases: Vec<String>,
-    ) -> Result<Message, QueryEngineError> {
+    ) -> Result<(Message, HashMap<SourceKey, Source>, Usage, String), QueryEngineError> {
+        self.check_templates(&conversation).await?;
+        let Conversation {
+            id,
+            messages,
+            system_template,
+            chat_template,
+            filter,
+            corpora,
+        } = conversation;
         let user_query = match messages.iter().last() {
             Some(Message::User(user_query)) => {
                 Ok::<std::string::String, QueryEngineError>(user_query.clone())
@@ -72,36 +172,43 @@ impl Engine {
             })
             .collect::<Vec<_>>();
 
-        let documents = self.get_documents(&user_query).await?;
+        let filter = DocumentFilter::from(filter.unwrap_or_default());
+        let documents = self
+            .get_documents(&user_query, &filter, corpora.as_deref())
+            .await?;
 
         log::info!("User message: \"{user_query}\"",);
         log::info!(
             "Obtained documents:\n{}.",
             documents
                 .iter()
-                .map(|d| format!("{}:{}", d.index, d.text.lines().next().unwrap()))
+                .map(|r| {
+                    let score = r
+                        .score
+                        .map_or_else(|| String::from("-"), |score| format!("{score:.3}"));
+                    format!(
+                        "{}:{}:{score}:{}",
+                        r.corpus,
+                        r.document.index,
+                        r.document.text.lines().next().unwrap_or_default()
+                    )
+                })
                 .collect::<Vec<_>>()
                 .join("\n")
         );
 
         let document_arguments = documents
             .iter()
-            .map(|d| LanguageServiceDocument {
-                index: d.index,
-                text: d.text.clone(),
+            .map(|r| LanguageServiceDocument {
+                key: r.key().to_string(),
+                corpus: r.corpus.clone(),
+                index: r.document.index,
+                text: r.document.text.clone(),
             })
             .collect::<Vec<_>>();
 
-        let _sources = documents
-            .into_iter()
-            .enumerate()
-            .map(|(_ordinal, document)| Source {
-                index: document.index,
-                citation: document.provenance.format(&CITATION_STYLE),
-                url: document.provenance.url(),
-                origin_text: document.text,
-            })
-            .collect::<Vec<_>>();
+        let sources = documents.iter().map(Retrieved::key).collect::<Vec<_>>();
+        let source_map = source_map(documents);
 
         let llm_service_arguments = LanguageServiceArguments {
             messages,
@@ -109,8 +216,10 @@ impl Engine {
             user_query,
             max_tokens: 2048,
             stop_phrases,
+            system_template,
+            chat_template,
         };
-        let LlmMessage { role, content } = self
+        let (LlmMessage { role, content }, usage) = self
             .llm_client
             .get_llm_answer(llm_service_arguments)
             .await?;
@@ -118,7 +227,13 @@ impl Engine {
         match role {
             LlmRole::Assistant => {
                 let content = content.trim().to_string();
-                Ok(Message::Assistant(content))
+                let id = self.conversations.record(id.as_deref(), sources);
+                Ok((
+                    Message::Assistant(content),
+                    source_map,
+                    Usage::from(usage),
+                    id,
+                ))
             }
             _ => Err(QueryEngineError::InvalidAgentResponse)?,
         }
@@ -126,10 +241,17 @@ impl Engine {
 
     pub(crate) async fn streaming_conversation(
         &self,
-        Conversation { messages }: Conversation,
+        Conversation {
+            id,
+            messages,
+            system_template,
+            chat_template,
+            filter,
+            corpora,
+        }: Conversation,
         tx: UnboundedSender<Bytes>,
         stop_phrases: Vec<String>,
-    ) -> Result<(), QueryEngineError> {
+    ) -> Result<Usage, QueryEngineError> {
         let user_query = match messages.iter().last() {
             Some(Message::User(user_query)) => {
                 Ok::<std::string::String, QueryEngineError>(user_query.clone())
@@ -152,22 +274,37 @@ impl Engine {
             })
             .collect::<Vec<_>>();
 
-        let documents = self.get_documents(&user_query).await?;
+        let filter = DocumentFilter::from(filter.unwrap_or_default());
+        let documents = self
+            .get_documents(&user_query, &filter, corpora.as_deref())
+            .await?;
         log::info!("User message: \"{user_query}\"",);
         log::info!(
             "Obtained documents:\n{}.",
             documents
                 .iter()
-                .map(|d| format!("{}:{}", d.index, d.text.lines().next().unwrap()))
+                .map(|r| {
+                    let score = r
+                        .score
+                        .map_or_else(|| String::from("-"), |score| format!("{score:.3}"));
+                    format!(
+                        "{}:{}:{score}:{}",
+                        r.corpus,
+                        r.document.index,
+                        r.document.text.lines().next().unwrap_or_default()
+                    )
+                })
                 .collect::<Vec<_>>()
                 .join("\n")
         );
 
         let document_arguments = documents
             .iter()
-            .map(|d| LanguageServiceDocument {
-                index: d.index,
-                text: d.text.clone(),
+            .map(|r| LanguageServiceDocument {
+                key: r.key().to_string(),
+                corpus: r.corpus.clone(),
+                index: r.document.index,
+                text: r.document.text.clone(),
             })
             .collect::<Vec<_>>();
         let llm_service_arguments = LanguageServiceArguments {
@@ -176,53 +313,292 @@ impl Engine {
             user_query,
             max_tokens: 2048,
             stop_phrases,
+            system_template,
+            chat_template,
         };
 
-        let _sources = documents
-            .into_iter()
-            .enumerate()
-            .map(|(_ordinal, document)| Source {
-                index: document.index,
-                citation: document.provenance.format(&CITATION_STYLE),
-                url: document.provenance.url(),
-                origin_text: document.text,
-            })
-            .collect::<Vec<_>>();
+        let id = self.conversations.record(
+            id.as_deref(),
+            documents.iter().map(Retrieved::key).collect(),
+        );
+        let source_map = source_map(documents);
+
+        let _ = tx.send(PartialMessage::source(source_map, id).message());
 
         let (partial_message_sender, mut partial_message_receiver) = unbounded_channel();
 
-        tokio::spawn(async move {
+        let content_sender = tx.clone();
+        let forward_content = tokio::spawn(async move {
             while let Some(PartialLlmMessage {
                 content: Some(content),
                 ..
             }) = partial_message_receiver.recv().await
             {
-                let _ = tx.send(PartialMessage::content(content).message());
+                let _ = content_sender.send(PartialMessage::content(content).message());
             }
-
-            let _ = tx.send(PartialMessage::done().message());
         });
 
-        self.llm_client
+        let usage = self
+            .llm_client
             .stream_llm_answer(llm_service_arguments, partial_message_sender)
-            .await?;
+            .await
+            .map(Usage::from);
 
-        Ok(())
+        let _ = forward_content.await;
+        match &usage {
+            Ok(usage) => {
+                let _ = tx.send(usage.message());
+            }
+            Err(_) => {
+                let _ = tx.send(Usage::failed().message());
+            }
+        }
+        let _ = tx.send(PartialMessage::done().message());
+
+        Ok(usage?)
     }
 
+    /// The best documents of the selected corpora. Of two results with the same corpus and id only
+    /// the better ranked is kept.
     pub(crate) async fn get_documents(
         &self,
         user_query: &str,
-    ) -> Result<Vec<Document>, QueryEngineError> {
-        let embedding: Vec<f32> = self.embed_client.embed(user_query).await?;
+        filter: &DocumentFilter,
+        corpora: Option<&[String]>,
+    ) -> Result<Vec<Retrieved>, QueryEngineError> {
+        let corpora = self
+            .corpora
+            .select(corpora)
+            .map_err(QueryEngineError::UnknownCorpus)?;
+
+        let results = try_join_all(
+            corpora
+                .into_iter()
+                .map(|corpus| self.search_corpus(corpus, user_query, filter)),
+        )
+        .await?;
+
+        let mut seen = HashSet::new();
+        let documents = merge(results, NUM_DOCUMENTS_TO_RETRIEVE)
+            .into_iter()
+            .filter_map(|((corpus, document), score)| {
+                if seen.insert((corpus.clone(), document.index)) {
+                    Some(Retrieved {
+                        corpus,
+                        document,
+                        score,
+                    })
+                } else {
+                    log::warn!("{corpus} repeats document {}, dropped", document.index);
+                    None
+                }
+            })
+            .collect();
+
+        Ok(documents)
+    }
+
+    /// The passage `index` of a corpus, `None` when its docstore has no such document.
+    pub(crate) async fn passage(
+        &self,
+        corpus: Option<&String>,
+        index: i64,
+    ) -> Result<Option<Passage>, QueryEngineError> {
+        let corpus = self.corpus(corpus)?;
+        let document = corpus.docstore.retreive(&[index]).await?.into_iter().next();
+        Ok(document.map(|document| passage(&corpus.name, document)))
+    }
+
+    /// The passages of an article of a corpus in section order.
+    pub(crate) async fn article_passages(
+        &self,
+        corpus: Option<&String>,
+        article: i64,
+    ) -> Result<Vec<Passage>, QueryEngineError> {
+        let corpus = self.corpus(corpus)?;
+        Ok(corpus
+            .docstore
+            .article_documents(article)
+            .await?
+            .into_iter()
+            .map(|document| passage(&corpus.name, document))
+            .collect())
+    }
+
+    /// Articles of a corpus found by their title, see [`DocumentStore::find_articles`].
+    pub(crate) async fn find_articles(
+        &self,
+        corpus: Option<&String>,
+        title: &str,
+        fuzzy: bool,
+        limit: usize,
+    ) -> Result<Vec<Article>, QueryEngineError> {
+        let corpus = self.corpus(corpus)?;
+        Ok(corpus
+            .docstore
+            .find_articles(title, fuzzy, limit)
+            .await?
+            .into_iter()
+            .map(|article| Article {
+                id: article.id,
+                title: article.provenance.title(),
+                citation: article.provenance.format(&CITATION_STYLE),
+                url: article.provenance.url(),
+                revision_url: article.provenance.revision_url(),
+                corpus: corpus.name.clone(),
+            })
+            .collect())
+    }
+
+    /// The provenance of each article cited, once per article in the order first cited: the
+    /// documents `sources` of a corpus, the default corpus when `None`, then the sources of the
+    /// stored `conversation`. Ids missing from their corpus are skipped.
+    ///
+    /// The documents of each corpus are retrieved at once, at most `MAX_BIBLIOGRAPHY_SOURCES` in
+    /// all.
+    pub(crate) async fn bibliography(
+        &self,
+        corpus: Option<&String>,
+        sources: &[i64],
+        conversation: Option<&str>,
+    ) -> Result<Vec<Provenance>, QueryEngineError> {
+        let mut cited = vec![];
+        if !sources.is_empty() {
+            let corpus = &self.corpus(corpus)?.name;
+            cited.extend(sources.iter().map(|index| SourceKey {
+                corpus: corpus.clone(),
+                index: *index,
+            }));
+        }
+        if let Some(id) = conversation {
+            let stored = self
+                .conversations
+                .sources(id)
+                .ok_or_else(|| QueryEngineError::UnknownConversation(id.to_string()))?;
+            cited.extend(stored);
+        }
+        if cited.len() > MAX_BIBLIOGRAPHY_SOURCES {
+            return Err(QueryEngineError::TooManySources(cited.len()));
+        }
+
+        let mut by_corpus = HashMap::<&String, Vec<i64>>::new();
+        for key in &cited {
+            by_corpus.entry(&key.corpus).or_default().push(key.index);
+        }
+        let retrieved = try_join_all(by_corpus.into_iter().map(|(name, indices)| async move {
+            let documents = self.corpus(Some(name))?.docstore.retreive(&indices).await?;
+            Ok::<_, QueryEngineError>(
+                documents
+                    .into_iter()
+                    .map(move |document| ((name, document.index), document)),
+            )
+        }))
+        .await?
+        .into_iter()
+        .flatten()
+        .collect::<HashMap<_, _>>();
+
+        let mut articles = HashSet::new();
+        let mut provenances = vec![];
+        for key in &cited {
+            if let Some(document) = retrieved.get(&(&key.corpus, key.index)) {
+                if articles.insert((&key.corpus, document.article)) {
+                    provenances.push(document.provenance.clone());
+                }
+            }
+        }
+        Ok(provenances)
+    }
+
+    /// The named corpus, the default corpus without a name.
+    fn corpus(&self, name: Option<&String>) -> Result<&Corpus, QueryEngineError> {
+        self.corpora
+            .select(name.map(std::slice::from_ref))
+            .map_err(QueryEngineError::UnknownCorpus)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| QueryEngineError::UnknownCorpus(name.cloned().unwrap_or_default()))
+    }
+
+    async fn search_corpus(
+        &self,
+        corpus: &Corpus,
+        user_query: &str,
+        filter: &DocumentFilter,
+    ) -> Result<Vec<((String, Document), Option<f32>)>, QueryEngineError> {
+        let embedding: Vec<f32> = self
+            .embedding_cache
+            .embed(&self.embed_client, &corpus.embedding_profile, user_query)
+            .await?;
 
-        let document_indices = self
+        let filter = (!filter.is_empty()).then(|| DocstoreFilter::new(&corpus.docstore, filter));
+        let neighbors = corpus
             .index
-            .search(embedding, NUM_DOCUMENTS_TO_RETRIEVE)
+            .search(embedding, NUM_DOCUMENTS_TO_RETRIEVE, filter.as_ref())
             .await?;
 
-        let documents = self.docstore.retreive(&document_indices).await?;
+        let document_indices = neighbors.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        let ranked = neighbors
+            .into_iter()
+            .enumerate()
+            .map(|(rank, (id, score))| (id, (rank, score)))
+            .collect::<HashMap<_, _>>();
+        let mut documents = corpus
+            .docstore
+            .retreive(&document_indices)
+            .await?
+            .into_iter()
+            .filter_map(|document| {
+                let (rank, score) = ranked.get(&document.index).copied()?;
+                Some((rank, ((corpus.name.clone(), document), score)))
+            })
+            .collect::<Vec<_>>();
+        documents.sort_by_key(|(rank, _)| *rank);
+
+        Ok(documents
+            .into_iter()
+            .map(|(_, document)| document)
+            .collect())
+    }
+}
+
+fn source_map(documents: Vec<Retrieved>) -> HashMap<SourceKey, Source> {
+    documents
+        .into_iter()
+        .map(|retrieved| {
+            let key = retrieved.key();
+            let Retrieved {
+                corpus,
+                document,
+                score,
+            } = retrieved;
+            let source = Source {
+                index: document.index,
+                article: document.article,
+                citation: document.provenance.format(&CITATION_STYLE),
+                url: document.url(),
+                revision_url: document.provenance.revision_url(),
+                heading: document.heading,
+                section_ordinal: document.ordinal,
+                origin_text: document.text,
+                score,
+                corpus,
+            };
+            (key, source)
+        })
+        .collect()
+}
 
-        Ok(documents)
+fn passage(corpus: &str, document: Document) -> Passage {
+    Passage {
+        index: document.index,
+        article: document.article,
+        citation: document.provenance.format(&CITATION_STYLE),
+        url: document.url(),
+        revision_url: document.provenance.revision_url(),
+        heading: document.heading,
+        section_ordinal: document.ordinal,
+        text: document.text,
+        corpus: corpus.to_string(),
     }
 }
@@ -1,8 +1,10 @@
 use std::fmt::{Display, Formatter, Result};
 
 use crate::{
-    docstore::DocstoreRetrieveError, embedding_client::EmbeddingServiceError,
-    index::IndexSearchError, llm_client::LlmClientError,
+    docstore::DocstoreRetrieveError,
+    embedding_client::EmbeddingServiceError,
+    index::IndexSearchError,
+    llm_client::{LlmClientError, PromptTemplateError},
 };
 
 #[derive(Debug)]
@@ -14,7 +16,12 @@ pub(crate) enum QueryEngineError {
     InvalidAgentResponse,
     LastMessageIsNotUser,
     LlmError(LlmClientError),
+    Template(PromptTemplateError),
     Tera(tera::Error),
+    /// More sources than a bibliography cites at once.
+    TooManySources(usize),
+    UnknownConversation(String),
+    UnknownCorpus(String),
 }
 
 impl From<tera::Error> for QueryEngineError {
@@ -44,6 +51,12 @@ impl From<LlmClientError> for QueryEngineError {
     }
 }
 
+impl From<PromptTemplateError> for QueryEngineError {
+    fn from(value: PromptTemplateError) -> Self {
+        Self::Template(value)
+    }
+}
+
 impl std::error::Error for QueryEngineError {}
 
 impl Display for QueryEngineError {
@@ -58,6 +71,7 @@ impl Display for QueryEngineError {
             }
             QueryEngineError::IndexError(err) => write!(f, "{}", err),
             QueryEngineError::LlmError(err) => write!(f, "{}", err),
+            QueryEngineError::Template(err) => write!(f, "{}", err),
             QueryEngineError::Tera(err) => write!(f, "{}", err),
             QueryEngineError::EmptyConversation => {
                 write!(f, "QueryEngine: Empty conversation error")
@@ -68,6 +82,18 @@ impl Display for QueryEngineError {
             QueryEngineError::LastMessageIsNotUser => {
                 write!(f, "QueryEngine: Last message is not from a user error")
             }
+            QueryEngineError::TooManySources(count) => {
+                write!(
+                    f,
+                    "QueryEngine: {count} sources are too many to cite at once"
+                )
+            }
+            QueryEngineError::UnknownConversation(id) => {
+                write!(f, "QueryEngine: Unknown conversation {id}")
+            }
+            QueryEngineError::UnknownCorpus(name) => {
+                write!(f, "QueryEngine: Unknown corpus {name}")
+            }
         }
     }
 }
@@ -1,3 +1,4 @@
+mod conversations;
 mod engine;
 mod error;
 pub(crate) use engine::Engine;
@@ -1,48 +1,86 @@
 use std::fmt::Display;
 
 use chrono::NaiveDateTime;
+
+use crate::text_codec::TextCodec;
+
 #[derive(Default, Clone)]
 pub(crate) struct Document {
     pub(crate) document: String,
     pub(crate) article_title: String,
     pub(crate) article_id: i64,
+    /// The MediaWiki page id, stable across dumps.
+    pub(crate) page_id: i64,
+    /// The MediaWiki revision the text was taken from.
+    pub(crate) revision_id: i64,
     pub(crate) access_date: NaiveDateTime,
+    /// The timestamp of the revision.
     pub(crate) modification_date: NaiveDateTime,
+    pub(crate) categories: Vec<String>,
+    /// The JSON metadata of the wiki the article was exported from, as `SiteInfo::metadata`
+    /// writes it.
+    pub(crate) wiki: String,
 }
 
 #[derive(Default, Clone)]
 pub(crate) struct DocumentHeading {
     pub(crate) document: String,
+    /// The article title followed by the path of section headings, separated by `:`.
     pub(crate) heading: String,
+    /// The last heading of the path, `None` for an article's lead.
+    pub(crate) section: Option<String>,
+    /// The position of the section in its article, the lead section is 0.
+    pub(crate) ordinal: i64,
     pub(crate) document_id: i64,
     pub(crate) article_id: i64,
+    pub(crate) page_id: i64,
+    pub(crate) revision_id: i64,
     pub(crate) article_title: String,
     pub(crate) access_date: NaiveDateTime,
     pub(crate) modification_date: NaiveDateTime,
+    pub(crate) categories: Vec<String>,
+    pub(crate) wiki: String,
+    /// The number of documents the article was split into.
+    pub(crate) document_count: i64,
 }
 
 #[derive(Default, Clone)]
 pub(crate) struct DocumentTextHeadingEmbedding {
     pub(crate) text: String,
     pub(crate) heading: String,
+    pub(crate) section: Option<String>,
+    pub(crate) ordinal: i64,
     pub(crate) document_id: i64,
     pub(crate) article_id: i64,
+    pub(crate) page_id: i64,
+    pub(crate) revision_id: i64,
     pub(crate) article_title: String,
     pub(crate) access_date: NaiveDateTime,
     pub(crate) modification_date: NaiveDateTime,
+    pub(crate) categories: Vec<String>,
+    pub(crate) wiki: String,
+    pub(crate) document_count: i64,
     pub(crate) embedding: Vec<f32>,
 }
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub(crate) struct DocumentCompressed {
     pub(crate) document: Vec<u8>,
+    pub(crate) codec: TextCodec,
     pub(crate) heading: String,
+    pub(crate) section: Option<String>,
+    pub(crate) ordinal: i64,
     pub(crate) document_id: i64,
     pub(crate) article_id: i64,
+    pub(crate) page_id: i64,
+    pub(crate) revision_id: i64,
     pub(crate) embedding: Vec<f32>,
     pub(crate) article_title: String,
     pub(crate) access_date: NaiveDateTime,
     pub(crate) modification_date: NaiveDateTime,
+    pub(crate) categories: Vec<String>,
+    pub(crate) wiki: String,
+    pub(crate) document_count: i64,
 }
 
 impl Display for DocumentHeading {
@@ -4,7 +4,12 @@ use std::{
     io,
 };
 
-use crate::embedding_client::EmbeddingServiceError;
+#[cfg(feature = "server")]
+use crate::index::IndexSearchError;
+use crate::{
+    embedding_client::{EmbeddingQuantization, EmbeddingServiceError},
+    schema::SchemaVersionError,
+};
 
 use super::wikipedia::WikiMarkupProcessingError;
 
@@ -18,6 +23,8 @@ pub enum PipelineError {
     WikipediaMarkupParseError(WikipediaMarkupParseError),
     WikipediaHeadingSplitterError(WikipediaHeadingSplitterError),
     Sql(Sql),
+    #[cfg(feature = "server")]
+    Index(IndexSearchError),
 }
 impl StdError for PipelineError {}
 impl Display for PipelineError {
@@ -33,10 +40,19 @@ impl Display for PipelineError {
             PipelineError::BatchingError(e) => write!(f, "{e}"),
             PipelineError::WikipediaMarkupParseError(e) => write!(f, "{e}"),
             PipelineError::WikipediaHeadingSplitterError(e) => write!(f, "{e}"),
+            #[cfg(feature = "server")]
+            PipelineError::Index(e) => write!(f, "{e}"),
         }
     }
 }
 
+#[cfg(feature = "server")]
+impl From<IndexSearchError> for PipelineError {
+    fn from(value: IndexSearchError) -> Self {
+        Self::Index(value)
+    }
+}
+
 #[derive(Debug)]
 pub enum WikipediaDumpReaderError {
     XmlDateReadError,
@@ -131,6 +147,13 @@ impl From<CompressionError> for PipelineError {
 #[derive(Debug)]
 pub enum Sql {
     Sql(sqlx::Error),
+    Schema(SchemaVersionError),
+    /// A kept index stores embeddings other than the run asked for, as the quantization and
+    /// whether binary codes are stored.
+    QuantizationMismatch {
+        recorded: (EmbeddingQuantization, bool),
+        requested: (EmbeddingQuantization, bool),
+    },
 }
 impl StdError for Sql {}
 impl Display for Sql {
@@ -139,6 +162,21 @@ impl Display for Sql {
             Sql::Sql(e) => {
                 write!(f, "Sql {e}")
             }
+            Sql::Schema(e) => write!(f, "{e}"),
+            Sql::QuantizationMismatch {
+                recorded,
+                requested,
+            } => {
+                let storage = |(kind, binary): &(EmbeddingQuantization, bool)| {
+                    format!("{kind}{}", if *binary { " with binary codes" } else { "" })
+                };
+                write!(
+                    f,
+                    "The index stores {} embeddings, not {}. Pass the quantization it was written with, or ingest without --incremental or --resume.",
+                    storage(recorded),
+                    storage(requested)
+                )
+            }
         }
     }
 }
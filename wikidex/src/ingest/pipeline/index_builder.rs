@@ -0,0 +1,192 @@
+use std::{fmt::Display, fs::File, io::BufWriter, path::PathBuf, str::FromStr};
+
+use faiss::{index_factory, IdMap, Idx, Index, MetricType};
+use futures::TryStreamExt;
+use sqlx::{Row, SqlitePool};
+use url::Url;
+
+use crate::embedding_client::Quantizer;
+
+/// The distance the index ranks by.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum IndexMetric {
+    L2,
+    InnerProduct,
+}
+
+impl From<IndexMetric> for MetricType {
+    fn from(value: IndexMetric) -> Self {
+        match value {
+            IndexMetric::L2 => MetricType::L2,
+            IndexMetric::InnerProduct => MetricType::InnerProduct,
+        }
+    }
+}
+
+impl Display for IndexMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexMetric::L2 => write!(f, "l2"),
+            IndexMetric::InnerProduct => write!(f, "ip"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ParseIndexMetricError(String);
+impl std::error::Error for ParseIndexMetricError {}
+impl Display for ParseIndexMetricError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unable to parse metric {}. Must be one of [l2, ip]",
+            self.0
+        )
+    }
+}
+
+impl FromStr for IndexMetric {
+    type Err = ParseIndexMetricError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "l2" => Ok(IndexMetric::L2),
+            "ip" => Ok(IndexMetric::InnerProduct),
+            _ => Err(ParseIndexMetricError(s.to_string())),
+        }
+    }
+}
+
+/// Builds a faiss index over the `embeddings` table of the ingest's `wikipedia_index.sqlite`.
+///
+/// The index is trained on an evenly spaced sample of the embeddings, then the embeddings are
+/// streamed into it `chunk_size` at a time under their ids, which resumed and incremental ingests
+/// leave with gaps. Tombstoned ids are left out. Quantized embeddings are decoded back to `f32`s
+/// first. Next to the index a `.json` sidecar records how it was built.
+pub(crate) struct IndexBuilder {
+    pub(crate) index_database_url: Url,
+    pub(crate) output: PathBuf,
+    pub(crate) factory: String,
+    pub(crate) metric: IndexMetric,
+    pub(crate) train_sample: usize,
+    pub(crate) chunk_size: usize,
+}
+
+impl IndexBuilder {
+    pub(crate) async fn build(&self) -> anyhow::Result<()> {
+        let pool = SqlitePool::connect(&format!("{}?mode=ro", self.index_database_url)).await?;
+
+        let has_tombstones =
+            sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'tombstones'")
+                .fetch_optional(&pool)
+                .await?
+                .is_some();
+        let live = if has_tombstones {
+            "WHERE id NOT IN (SELECT id FROM tombstones)"
+        } else {
+            ""
+        };
+
+        let count = sqlx::query(&format!("SELECT COUNT(*) AS count FROM embeddings {live}"))
+            .fetch_one(&pool)
+            .await?
+            .get::<i64, _>("count") as usize;
+        let quantizer = match Quantizer::load(&mut *pool.acquire().await?).await? {
+            Some(quantizer) => quantizer,
+            None => sqlx::query("SELECT gte_small FROM embeddings LIMIT 1")
+                .fetch_optional(&pool)
+                .await?
+                .map(|row| Quantizer::raw(row.get::<Vec<u8>, _>("gte_small").len() / 4))
+                .ok_or_else(|| anyhow::anyhow!("{} has no embeddings", self.index_database_url))?,
+        };
+        let dimensions = quantizer.dimensions;
+
+        let mut index = index_factory(dimensions as u32, &self.factory, self.metric.into())?;
+
+        if !index.is_trained() {
+            let sample = self.sample(&pool, count, &quantizer).await?;
+            log::info!(
+                "Training {} on {} of {count} vectors.",
+                self.factory,
+                sample.len() / dimensions
+            );
+            tokio::task::block_in_place(|| index.train(&sample))?;
+        }
+
+        let mut index = IdMap::new(index)?;
+        let mut added = 0usize;
+        let mut chunk = Vec::with_capacity(self.chunk_size * dimensions);
+        let mut ids = Vec::with_capacity(self.chunk_size);
+        let mut rows = sqlx::query(&format!(
+            "SELECT id, gte_small FROM embeddings {live} ORDER BY id"
+        ))
+        .fetch(&pool);
+        while let Some(row) = rows.try_next().await? {
+            let id = row.get::<i64, _>("id");
+            let embedding = row.get::<Vec<u8>, _>("gte_small");
+            if embedding.len() != quantizer.vector_len() {
+                anyhow::bail!("embedding {id} has {} bytes", embedding.len());
+            }
+            chunk.extend(quantizer.decode(&embedding));
+            ids.push(Idx::new(id as u64));
+
+            if ids.len() == self.chunk_size {
+                tokio::task::block_in_place(|| index.add_with_ids(&chunk, &ids))?;
+                added += ids.len();
+                chunk.clear();
+                ids.clear();
+                log::info!("Added {added} of {count} vectors.");
+            }
+        }
+        if !ids.is_empty() {
+            tokio::task::block_in_place(|| index.add_with_ids(&chunk, &ids))?;
+            added += ids.len();
+        }
+        drop(rows);
+        pool.close().await;
+        log::info!("Added {added} of {count} vectors.");
+
+        log::info!("Writing {}.", self.output.display());
+        let output = self
+            .output
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("{} is not valid unicode", self.output.display()))?;
+        faiss::write_index(&index, output)?;
+        self.write_metadata(dimensions, added)?;
+        Ok(())
+    }
+
+    /// Every `count / train_sample`th embedding, at most `train_sample` of them.
+    async fn sample(
+        &self,
+        pool: &SqlitePool,
+        count: usize,
+        quantizer: &Quantizer,
+    ) -> anyhow::Result<Vec<f32>> {
+        let step = (count / self.train_sample.max(1)).max(1);
+        let mut sample = Vec::with_capacity(self.train_sample.min(count) * quantizer.dimensions);
+        let mut rows = sqlx::query("SELECT gte_small FROM embeddings WHERE id % ?1 = 0 LIMIT ?2")
+            .bind(step as i64)
+            .bind(self.train_sample as i64)
+            .fetch(pool);
+        while let Some(row) = rows.try_next().await? {
+            sample.extend(quantizer.decode(&row.get::<Vec<u8>, _>("gte_small")));
+        }
+        Ok(sample)
+    }
+
+    fn write_metadata(&self, dimensions: usize, count: usize) -> anyhow::Result<()> {
+        let mut path = self.output.as_os_str().to_owned();
+        path.push(".json");
+        let metadata = serde_json::json!({
+            "factory": self.factory,
+            "metric": self.metric.to_string(),
+            "dimensions": dimensions,
+            "count": count,
+            "train_sample": self.train_sample,
+            "source": self.index_database_url.as_str(),
+        });
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &metadata)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+/// A step of the Wikipedia ingest, as `--step-limit` names it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Step {
+    Read,
+    Parse,
+    Split,
+    BatchEmbed,
+    Embed,
+    BatchWrite,
+    Compress,
+    Write,
+}
+
+impl Step {
+    const ALL: [Step; 8] = [
+        Step::Read,
+        Step::Parse,
+        Step::Split,
+        Step::BatchEmbed,
+        Step::Embed,
+        Step::BatchWrite,
+        Step::Compress,
+        Step::Write,
+    ];
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Read => write!(f, "read"),
+            Step::Parse => write!(f, "parse"),
+            Step::Split => write!(f, "split"),
+            Step::BatchEmbed => write!(f, "batch-embed"),
+            Step::Embed => write!(f, "embed"),
+            Step::BatchWrite => write!(f, "batch-write"),
+            Step::Compress => write!(f, "compress"),
+            Step::Write => write!(f, "write"),
+        }
+    }
+}
+
+impl FromStr for Step {
+    type Err = ParseStepLimitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Step::ALL
+            .into_iter()
+            .find(|step| step.to_string() == s)
+            .ok_or_else(|| ParseStepLimitError::UnknownStep(s.to_string()))
+    }
+}
+
+/// How much a step may hold: the items waiting in its output channel, and for steps that
+/// transform items concurrently, how many are in flight. A step whose output is full waits,
+/// which stops it reading its input, so a slow step holds back every step before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StepLimits {
+    pub(crate) capacity: usize,
+    /// Ignored by steps that transform one item at a time.
+    pub(crate) concurrency: usize,
+}
+
+impl StepLimits {
+    const fn new(capacity: usize, concurrency: usize) -> Self {
+        Self {
+            capacity,
+            concurrency,
+        }
+    }
+}
+
+/// The limits of every step of the Wikipedia ingest. Batches count as one item, so the steps
+/// after a batcher hold few of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PipelineLimits {
+    pub(crate) read: StepLimits,
+    pub(crate) parse: StepLimits,
+    pub(crate) split: StepLimits,
+    pub(crate) batch_embed: StepLimits,
+    pub(crate) embed: StepLimits,
+    pub(crate) batch_write: StepLimits,
+    pub(crate) compress: StepLimits,
+    pub(crate) write: StepLimits,
+}
+
+impl Default for PipelineLimits {
+    fn default() -> Self {
+        Self {
+            read: StepLimits::new(1024, 1),
+            parse: StepLimits::new(1024, 64),
+            split: StepLimits::new(4096, 16),
+            batch_embed: StepLimits::new(4, 1),
+            embed: StepLimits::new(20480, 1),
+            batch_write: StepLimits::new(2, 1),
+            compress: StepLimits::new(2, 2),
+            write: StepLimits::new(16, 1),
+        }
+    }
+}
+
+impl PipelineLimits {
+    fn step(&self, step: Step) -> StepLimits {
+        match step {
+            Step::Read => self.read,
+            Step::Parse => self.parse,
+            Step::Split => self.split,
+            Step::BatchEmbed => self.batch_embed,
+            Step::Embed => self.embed,
+            Step::BatchWrite => self.batch_write,
+            Step::Compress => self.compress,
+            Step::Write => self.write,
+        }
+    }
+
+    fn step_mut(&mut self, step: Step) -> &mut StepLimits {
+        match step {
+            Step::Read => &mut self.read,
+            Step::Parse => &mut self.parse,
+            Step::Split => &mut self.split,
+            Step::BatchEmbed => &mut self.batch_embed,
+            Step::Embed => &mut self.embed,
+            Step::BatchWrite => &mut self.batch_write,
+            Step::Compress => &mut self.compress,
+            Step::Write => &mut self.write,
+        }
+    }
+
+    /// Applies the limits given on the command line, later ones overriding earlier ones.
+    pub(crate) fn with_overrides(mut self, overrides: Vec<StepLimit>) -> Self {
+        for StepLimit {
+            step,
+            capacity,
+            concurrency,
+        } in overrides
+        {
+            let limits = self.step_mut(step);
+            if let Some(capacity) = capacity {
+                limits.capacity = capacity;
+            }
+            if let Some(concurrency) = concurrency {
+                limits.concurrency = concurrency;
+            }
+        }
+        self
+    }
+}
+
+impl Display for PipelineLimits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let limits = Step::ALL
+            .into_iter()
+            .map(|step| {
+                let StepLimits {
+                    capacity,
+                    concurrency,
+                } = self.step(step);
+                format!("{step} {capacity}/{concurrency}")
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{limits}")
+    }
+}
+
+/// The limits of one step given on the command line as comma separated `key=value` pairs, e.g.
+/// `step=parse,capacity=1024,concurrency=32`. `step` is required, the limits left out keep their
+/// defaults. Both must be at least one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StepLimit {
+    pub(crate) step: Step,
+    pub(crate) capacity: Option<usize>,
+    pub(crate) concurrency: Option<usize>,
+}
+
+#[derive(Debug)]
+pub(crate) enum ParseStepLimitError {
+    MissingStep,
+    UnknownStep(String),
+    UnknownKey(String),
+    InvalidValue(String, String),
+}
+impl Error for ParseStepLimitError {}
+impl Display for ParseStepLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseStepLimitError::MissingStep => {
+                write!(f, "Unable to parse step limit: `step` is required")
+            }
+            ParseStepLimitError::UnknownStep(step) => write!(
+                f,
+                "Unable to parse step limit: unknown step `{step}`. Must be one of [read, parse, split, batch-embed, embed, batch-write, compress, write]"
+            ),
+            ParseStepLimitError::UnknownKey(key) => write!(
+                f,
+                "Unable to parse step limit: unknown key `{key}`. Must be one of [step, capacity, concurrency]"
+            ),
+            ParseStepLimitError::InvalidValue(key, value) => write!(
+                f,
+                "Unable to parse step limit: `{key}` must be a positive integer, not `{value}`"
+            ),
+        }
+    }
+}
+
+impl FromStr for StepLimit {
+    type Err = ParseStepLimitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut step = None;
+        let mut capacity = None;
+        let mut concurrency = None;
+
+        for pair in s.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| ParseStepLimitError::UnknownKey(pair.to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            let positive = || {
+                value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|value| *value > 0)
+                    .ok_or_else(|| {
+                        ParseStepLimitError::InvalidValue(key.to_string(), value.to_string())
+                    })
+            };
+            match key {
+                "step" => step = Some(Step::from_str(value)?),
+                "capacity" => capacity = Some(positive()?),
+                "concurrency" => concurrency = Some(positive()?),
+                _ => return Err(ParseStepLimitError::UnknownKey(key.to_string())),
+            }
+        }
+
+        Ok(Self {
+            step: step.ok_or(ParseStepLimitError::MissingStep)?,
+            capacity,
+            concurrency,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::{PipelineLimits, Step, StepLimit};
+
+    #[test]
+    fn parse_step_limit() {
+        let limit = StepLimit::from_str("step=parse, concurrency=8").unwrap();
+        assert_eq!(limit.step, Step::Parse);
+        assert_eq!(limit.capacity, None);
+        assert_eq!(limit.concurrency, Some(8));
+        assert!(StepLimit::from_str("capacity=8").is_err());
+        assert!(StepLimit::from_str("step=tokenize").is_err());
+        assert!(StepLimit::from_str("step=read,capacity=0").is_err());
+
+        let defaults = PipelineLimits::default();
+        let limits = defaults.with_overrides(vec![
+            limit,
+            StepLimit::from_str("step=batch-write,capacity=1").unwrap(),
+        ]);
+        assert_eq!(limits.parse.capacity, defaults.parse.capacity);
+        assert_eq!(limits.parse.concurrency, 8);
+        assert_eq!(limits.batch_write.capacity, 1);
+    }
+}
@@ -1,12 +1,17 @@
 mod document;
 mod error;
-// mod index_converter;
+#[cfg(all(feature = "faiss", feature = "sqlite"))]
+mod index_builder;
+mod limits;
 #[cfg(feature = "sqlite")]
 mod processor;
 mod recursive_character_text_splitter;
 pub(super) mod steps;
 mod wikipedia;
 
+#[cfg(all(feature = "faiss", feature = "sqlite"))]
+pub(crate) use index_builder::{IndexBuilder, IndexMetric};
+pub(crate) use limits::{PipelineLimits, StepLimit};
 #[cfg(feature = "sqlite")]
 pub(crate) use processor::PipelineProcessor;
-pub(super) use wikipedia::{HEADING_END, HEADING_START};
+pub(super) use wikipedia::{HEADING_END, HEADING_SEPARATOR, HEADING_START};
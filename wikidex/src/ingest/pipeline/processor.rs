@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
@@ -7,20 +8,20 @@ use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use sqlx::migrate::MigrateDatabase;
 use sqlx::sqlite::{Sqlite, SqliteConnectOptions, SqlitePoolOptions};
 
-use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::channel;
 
-use crate::embedding_client::EmbeddingClient;
-use crate::ingest::pipeline::steps::WikipediaDumpReader;
-use crate::ingest::pipeline::{error::PipelineError, steps::PipelineStep};
+use crate::embedding_client::{EmbeddingClientImpl, EmbeddingProfile, EmbeddingQuantization};
+#[cfg(feature = "server")]
+use crate::index::SearchServiceImpl;
+use crate::ingest::pipeline::steps::{get_date_from_xml_name, WikipediaDumpReader};
+use crate::ingest::pipeline::{error::PipelineError, steps::PipelineStep, PipelineLimits};
+use crate::text_codec::TextCodec;
 
-use super::document::{DocumentCompressed, DocumentHeading};
+use super::document::{DocumentHeading, DocumentTextHeadingEmbedding};
 use super::error::Sql;
 use super::steps::{Batcher, Embedding, SqliteWriter};
 
-use super::{
-    steps::{Compressor, WikipediaHeadingSplitter, WikipediaMarkdownParser},
-    wikipedia::WikiMarkupProcessor,
-};
+use super::steps::{Compressor, WikipediaHeadingSplitter, WikipediaMarkdownParser};
 
 pub(crate) struct PipelineProcessor;
 
@@ -30,8 +31,17 @@ impl PipelineProcessor {
         multi_progress: &MultiProgress,
         wiki_xml_path: PathBuf,
         database_output_directory: PathBuf,
-        embedding_client: EmbeddingClient,
+        embedding_client: EmbeddingClientImpl,
+        embedding_profile: EmbeddingProfile,
+        incremental: bool,
+        resume: bool,
+        quantization: EmbeddingQuantization,
+        binary: bool,
+        compression: TextCodec,
+        limits: PipelineLimits,
+        #[cfg(feature = "server")] index: Option<SearchServiceImpl>,
     ) -> Result<(), PipelineError> {
+        let dump_date = get_date_from_xml_name(&wiki_xml_path)?;
         let docstore_path = {
             let mut p = database_output_directory.clone();
             p.push("wikipedia_docstore.sqlite");
@@ -85,31 +95,61 @@ impl PipelineProcessor {
             .await
             .map_err(Sql::Sql)?;
 
-        let step_read_input = WikipediaDumpReader::new(0);
-        let step_parse_markup = WikipediaMarkdownParser::new(WikiMarkupProcessor);
-        let step_split_on_heading = WikipediaHeadingSplitter::default();
+        let step_save = SqliteWriter::new(
+            docstore_pool.clone(),
+            index_pool,
+            &embedding_profile,
+            incremental,
+            resume,
+            quantization,
+            binary,
+        )
+        .await?;
+        #[cfg(feature = "server")]
+        let step_save = match index {
+            Some(index) => step_save.with_index(index),
+            None => step_save,
+        };
+        let written = if resume {
+            step_save.resume().await?
+        } else {
+            HashSet::new()
+        };
+        if incremental || resume {
+            log::info!(
+                "Continuing from article {} and document {}, {} articles written.",
+                step_save.first_article_id(),
+                step_save.first_document_id(),
+                written.len()
+            );
+        }
+
+        let step_read_input = WikipediaDumpReader::new(0).with_written(written);
+        let step_parse_markup = WikipediaMarkdownParser::new(step_save.first_article_id());
+        let step_split_on_heading = WikipediaHeadingSplitter::new(step_save.first_document_id());
         let step_batch_embedder = Batcher::<2048, DocumentHeading>::new();
-        let step_embed = Embedding::new(embedding_client);
-        let step_compress = Compressor;
-        let step_batch_writer = Batcher::<10240, DocumentCompressed>::new();
-        let step_save = SqliteWriter::new(docstore_pool, index_pool).await?;
+        let step_embed = Embedding::new(embedding_client, embedding_profile);
+        let step_batch_writer = Batcher::<10240, DocumentTextHeadingEmbedding>::new();
+        let step_compress =
+            Compressor::new(docstore_pool, compression, incremental || resume).await?;
 
         let progres_read_input = new_progress_bar(multi_progress, 0);
         let progres_parse_markup = new_progress_bar(multi_progress, 0);
         let progres_split_on_heading = new_progress_bar(multi_progress, 0);
         let progres_batch_embedder = new_progress_bar(multi_progress, 0);
         let progres_embed = new_progress_bar(multi_progress, 0);
-        let progres_compress = new_progress_bar(multi_progress, 0);
         let progres_batch_writer = new_progress_bar(multi_progress, 0);
+        let progres_compress = new_progress_bar(multi_progress, 0);
         let progres_save = new_progress_bar(multi_progress, 0);
         let progres_docstore = new_progress_bar(multi_progress, 0);
 
         progres_docstore.set_message("Docstore");
 
-        let (t, rx_pathbuf) = unbounded_channel::<PathBuf>();
+        let (t, rx_pathbuf) = channel::<PathBuf>(1);
         let mut rx_page = step_read_input
             .link(
                 rx_pathbuf,
+                limits.read,
                 progres_read_input.clone(),
                 vec![progres_parse_markup.clone()],
             )
@@ -117,6 +157,7 @@ impl PipelineProcessor {
         let mut rx_document = step_parse_markup
             .link(
                 rx_page.pop().unwrap(),
+                limits.parse,
                 progres_parse_markup.clone(),
                 vec![progres_split_on_heading.clone()],
             )
@@ -124,6 +165,7 @@ impl PipelineProcessor {
         let mut rx_doc_heading = step_split_on_heading
             .link(
                 rx_document.pop().unwrap(),
+                limits.split,
                 progres_split_on_heading.clone(),
                 vec![progres_batch_embedder.clone()],
             )
@@ -131,6 +173,7 @@ impl PipelineProcessor {
         let mut rx_batch_embedder = step_batch_embedder
             .link(
                 rx_doc_heading.pop().unwrap(),
+                limits.batch_embed,
                 progres_batch_embedder.clone(),
                 vec![progres_embed.clone()],
             )
@@ -138,38 +181,48 @@ impl PipelineProcessor {
         let mut rx_doc_head_embed = step_embed
             .link(
                 rx_batch_embedder.pop().unwrap(),
+                limits.embed,
                 progres_embed,
-                vec![progres_compress.clone()],
+                vec![progres_batch_writer.clone()],
             )
             .await?;
-        let mut rx_doc_compress = step_compress
+        let mut rx_batch_writer = step_batch_writer
             .link(
                 rx_doc_head_embed.pop().unwrap(),
-                progres_compress.clone(),
-                vec![progres_batch_writer.clone()],
+                limits.batch_write,
+                progres_batch_writer.clone(),
+                vec![progres_compress.clone()],
             )
             .await?;
-        let mut rx_batch_writer = step_batch_writer
+        let mut rx_doc_compress = step_compress
             .link(
-                rx_doc_compress.pop().unwrap(),
-                progres_batch_writer.clone(),
+                rx_batch_writer.pop().unwrap(),
+                limits.compress,
+                progres_compress.clone(),
                 vec![progres_save.clone()],
             )
             .await?;
         let mut rx_written = step_save
             .link(
-                rx_batch_writer.pop().unwrap(),
+                rx_doc_compress.pop().unwrap(),
+                limits.write,
                 progres_save.clone(),
                 vec![progres_docstore.clone()],
             )
             .await?;
 
-        let _ = t.send(wiki_xml_path);
+        let _ = t.send(wiki_xml_path).await;
+        drop(t);
 
+        // Every step closes its output once its input closes and its last item is sent, so this
+        // ends once the last batch is written.
         let mut rx_writter = rx_written.pop().unwrap();
-        loop {
-            let _x = rx_writter.recv().await;
-        }
+        while rx_writter.recv().await.is_some() {}
+
+        // A dump that could not be opened or read also ends the pipeline, it is not complete.
+        step_read_input.finished().await?;
+        step_save.complete(dump_date).await?;
+        Ok(())
     }
 }
 fn new_progress_bar(multibar: &MultiProgress, limit: u64) -> Arc<ProgressBar> {
@@ -1,13 +1,19 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
-use crate::ingest::pipeline::error::{BatchingError, LinkError, PipelineError};
+use crate::ingest::pipeline::{
+    error::{BatchingError, LinkError, PipelineError},
+    limits::StepLimits,
+};
 use indicatif::ProgressBar;
 use tokio::{
     sync::{
-        mpsc::{unbounded_channel, UnboundedReceiver},
-        RwLock,
+        mpsc::{channel, Receiver},
+        watch, RwLock,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use super::PipelineStep;
@@ -58,11 +64,12 @@ impl<const N: usize, X: Sync + Send + 'static> PipelineStep<false> for Batcher<N
     }
     async fn link(
         &self,
-        mut receiver: UnboundedReceiver<Self::IN>,
+        mut receiver: Receiver<Self::IN>,
+        limits: StepLimits,
         progress: Arc<ProgressBar>,
         next_progress: Vec<Arc<ProgressBar>>,
-    ) -> Result<Vec<UnboundedReceiver<Self::OUT>>, PipelineError> {
-        let (sender, new_receiver) = unbounded_channel::<Self::OUT>();
+    ) -> Result<Vec<Receiver<Self::OUT>>, PipelineError> {
+        let (sender, new_receiver) = channel::<Self::OUT>(limits.capacity.max(1));
         let batch = self.args();
         let next_progress = next_progress
             .first()
@@ -71,7 +78,10 @@ impl<const N: usize, X: Sync + Send + 'static> PipelineStep<false> for Batcher<N
 
         progress.set_message(Self::name().to_string());
         tokio::spawn(async move {
-            let mut last_flush = tokio::time::Instant::now();
+            // Milliseconds since `started` of the last input or timed flush, shared with the
+            // timer so that it waits out a full timeout after either.
+            let started = Instant::now();
+            let last_flush = Arc::new(AtomicU64::new(0));
             let flush_timeout = Duration::from_secs(10);
 
             let pipeline_batch = batch.clone();
@@ -79,38 +89,42 @@ impl<const N: usize, X: Sync + Send + 'static> PipelineStep<false> for Batcher<N
             let pipeline_progress = progress.clone();
             let pipeline_next_progress = next_progress.clone();
 
-            // Timeout task to handle flushing the batch after inactivity
-            tokio::spawn(async move {
+            // Timeout task to handle flushing the batch after inactivity. It is only stopped
+            // between flushes, a batch it took out is always sent.
+            let (stop, mut stopped) = watch::channel(false);
+            let timer_last_flush = last_flush.clone();
+            let timer = tokio::spawn(async move {
                 let batch = batch.clone();
                 let sender = sender.clone();
                 let progress = progress.clone();
                 let next_progress = next_progress.clone();
                 loop {
-                    tokio::time::sleep(Duration::from_secs(2)).await;
-
-                    if tokio::time::Instant::now() - last_flush >= flush_timeout {
-                        let mut batch = batch.write().await;
-
-                        if batch.as_ref().is_some() {
-                            match batch.replace(vec![]) {
-                                Some(replace) => {
-                                    if !replace.is_empty() {
-                                        progress.inc(1);
-                                        next_progress.inc_length(1);
-
-                                        let _ = sender.send(replace);
-                                    }
-                                }
-                                None => continue,
-                            };
-                        } else {
-                            continue;
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_secs(2)) => {}
+                        _ = stopped.changed() => break,
+                    }
+
+                    let idle = started.elapsed()
+                        - Duration::from_millis(timer_last_flush.load(Ordering::Relaxed));
+                    if idle >= flush_timeout {
+                        // Released before sending, a full output must not hold up batching.
+                        let replace = match batch.write().await.as_mut() {
+                            Some(batch) => std::mem::take(batch),
+                            None => continue,
+                        };
+                        timer_last_flush
+                            .store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+                        if !replace.is_empty() {
+                            progress.inc(1);
+                            next_progress.inc_length(1);
+
+                            let _ = sender.send(replace).await;
                         }
                     }
                 }
             });
             while let Some(input) = receiver.recv().await {
-                last_flush = tokio::time::Instant::now();
+                last_flush.store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
 
                 let transform = Self::transform(input, &pipeline_batch)
                     .await
@@ -123,7 +137,7 @@ impl<const N: usize, X: Sync + Send + 'static> PipelineStep<false> for Batcher<N
                         for t in transform {
                             pipeline_next_progress.inc_length(1);
 
-                            let _ = pipeline_sender.send(t);
+                            let _ = pipeline_sender.send(t).await;
                         }
                     }
                     Err(e) => {
@@ -132,8 +146,65 @@ impl<const N: usize, X: Sync + Send + 'static> PipelineStep<false> for Batcher<N
                 }
             }
 
+            // The input closed, flush what is left so the steps after this one close in turn.
+            let _ = stop.send(true);
+            let _ = timer.await;
+            if let Some(remaining) = pipeline_batch.write().await.take() {
+                if !remaining.is_empty() {
+                    pipeline_progress.inc(1);
+                    pipeline_next_progress.inc_length(1);
+
+                    let _ = pipeline_sender.send(remaining).await;
+                }
+            }
+
             Ok::<(), PipelineError>(())
         });
         Ok(vec![new_receiver])
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use indicatif::ProgressBar;
+    use tokio::{
+        sync::mpsc::channel,
+        time::{timeout, Duration},
+    };
+
+    use super::{Batcher, PipelineStep};
+    use crate::ingest::pipeline::limits::StepLimits;
+
+    #[tokio::test]
+    async fn stalled_consumer_blocks_the_input() {
+        let limits = StepLimits {
+            capacity: 1,
+            concurrency: 1,
+        };
+        let (input, receiver) = channel::<i32>(1);
+        let mut output = Batcher::<1, i32>::new()
+            .link(
+                receiver,
+                limits,
+                Arc::new(ProgressBar::hidden()),
+                vec![Arc::new(ProgressBar::hidden())],
+            )
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        let wait = Duration::from_millis(200);
+
+        // The first batch fills the output, the batcher waits to send the second while the third
+        // fills the input.
+        for item in 0..3 {
+            assert!(timeout(wait, input.send(item)).await.is_ok());
+        }
+        assert!(timeout(wait, input.send(3)).await.is_err());
+
+        assert_eq!(output.recv().await, Some(vec![0]));
+        assert!(timeout(wait, input.send(3)).await.is_ok());
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use sqlx::SqlitePool;
+use tokio::sync::OnceCell;
+
+use crate::{
+    ingest::pipeline::{
+        document::{DocumentCompressed, DocumentTextHeadingEmbedding},
+        error::{CompressionError, PipelineError, Sql},
+    },
+    text_codec::{TextCodec, TextCompressor},
+};
+
+use super::PipelineStep;
+
+/// Compresses batches of documents for the docstore.
+///
+/// With zstd the first batch trains the dictionary, which is recorded in the docstore. An
+/// incremental run keeps the recorded dictionary.
+pub(crate) struct Compressor {
+    docstore_pool: Arc<SqlitePool>,
+    codec: TextCodec,
+    compressor: Arc<OnceCell<Arc<TextCompressor>>>,
+}
+
+impl Compressor {
+    /// Expects the docstore schema to be created already.
+    pub(crate) async fn new(
+        docstore_pool: SqlitePool,
+        codec: TextCodec,
+        incremental: bool,
+    ) -> Result<Self, Sql> {
+        let recorded = if incremental {
+            let mut connection = docstore_pool.acquire().await.map_err(Sql::Sql)?;
+            TextCompressor::load(&mut connection)
+                .await
+                .map_err(Sql::Sql)?
+        } else {
+            None
+        };
+        let compressor = match (codec, recorded) {
+            (TextCodec::Gzip, _) => Some(TextCompressor::new(codec, None)),
+            (TextCodec::Zstd, Some(dictionary)) => {
+                Some(TextCompressor::new(codec, Some(dictionary)))
+            }
+            (TextCodec::Zstd, None) => None,
+        };
+        Ok(Self {
+            docstore_pool: Arc::new(docstore_pool),
+            codec,
+            compressor: Arc::new(OnceCell::new_with(compressor.map(Arc::new))),
+        })
+    }
+}
+
+impl PipelineStep<true> for Compressor {
+    type IN = Vec<DocumentTextHeadingEmbedding>;
+    type OUT = Vec<DocumentCompressed>;
+    type ARG = (
+        Arc<SqlitePool>,
+        TextCodec,
+        Arc<OnceCell<Arc<TextCompressor>>>,
+    );
+
+    async fn transform(
+        documents: Self::IN,
+        (docstore_pool, codec, compressor): &Self::ARG,
+    ) -> Result<Vec<Self::OUT>, PipelineError> {
+        let codec = *codec;
+        let compressor = compressor
+            .get_or_try_init(|| async {
+                let sample = documents
+                    .iter()
+                    .map(|document| document.text.clone())
+                    .collect::<Vec<_>>();
+                let trained =
+                    tokio::task::spawn_blocking(move || TextCompressor::train(codec, &sample))
+                        .await
+                        .map_err(|e| CompressionError::Io(e.into()))?;
+                let mut connection = docstore_pool.acquire().await.map_err(Sql::Sql)?;
+                trained.save(&mut connection).await.map_err(Sql::Sql)?;
+                Ok::<_, PipelineError>(Arc::new(trained))
+            })
+            .await?
+            .clone();
+
+        let compressed = tokio::task::spawn_blocking(move || {
+            documents
+                .into_iter()
+                .map(|document| {
+                    let bytes = compressor
+                        .compress(&document.text)
+                        .map_err(CompressionError::Io)?;
+                    Ok(DocumentCompressed {
+                        document: bytes,
+                        codec: compressor.codec,
+                        article_title: document.article_title,
+                        access_date: document.access_date,
+                        modification_date: document.modification_date,
+                        categories: document.categories,
+                        wiki: document.wiki,
+                        document_count: document.document_count,
+                        heading: document.heading,
+                        section: document.section,
+                        ordinal: document.ordinal,
+                        embedding: document.embedding,
+                        document_id: document.document_id,
+                        article_id: document.article_id,
+                        page_id: document.page_id,
+                        revision_id: document.revision_id,
+                    })
+                })
+                .collect::<Result<Vec<_>, CompressionError>>()
+        })
+        .await
+        .map_err(|e| CompressionError::Io(e.into()))??;
+        Ok(vec![compressed])
+    }
+
+    fn args(&self) -> Self::ARG {
+        (
+            self.docstore_pool.clone(),
+            self.codec,
+            self.compressor.clone(),
+        )
+    }
+
+    fn name() -> String {
+        String::from("Compressor")
+    }
+}
@@ -1,6 +1,9 @@
 use super::PipelineStep;
 use crate::{
-    embedding_client::{EmbeddingClient, EmbeddingClientService},
+    embedding_client::{
+        EmbeddingClientImpl, EmbeddingClientService, EmbeddingProfile,
+        EMBED_MAX_STR_LEN_ACCORDING_TO_INFINITY,
+    },
     ingest::pipeline::{
         document::{DocumentHeading, DocumentTextHeadingEmbedding},
         error::{EmbeddingError::EmbeddingServiceError as EmbedError, PipelineError},
@@ -11,14 +14,15 @@ use backoff::{future::retry, Error as Backoff, ExponentialBackoff};
 
 use std::sync::Arc;
 
-const EMBED_MAX_STR_LEN_ACCORDING_TO_INFINITY: usize = 122880usize;
 pub(crate) struct Embedding {
-    client: Arc<EmbeddingClient>,
+    client: Arc<EmbeddingClientImpl>,
+    profile: Arc<EmbeddingProfile>,
 }
 impl Embedding {
-    pub(crate) fn new(embedding_client: EmbeddingClient) -> Self {
+    pub(crate) fn new(embedding_client: EmbeddingClientImpl, profile: EmbeddingProfile) -> Self {
         Self {
             client: Arc::new(embedding_client),
+            profile: Arc::new(profile),
         }
     }
 }
@@ -26,7 +30,7 @@ impl Embedding {
 impl PipelineStep<false> for Embedding {
     type IN = Vec<DocumentHeading>;
 
-    type ARG = Arc<EmbeddingClient>;
+    type ARG = (Arc<EmbeddingClientImpl>, Arc<EmbeddingProfile>);
 
     type OUT = DocumentTextHeadingEmbedding;
 
@@ -36,18 +40,22 @@ impl PipelineStep<false> for Embedding {
 
     async fn transform(
         documents: Self::IN,
-        embedder: &Self::ARG,
+        (embedder, profile): &Self::ARG,
     ) -> Result<Vec<Self::OUT>, PipelineError> {
-        let queries = documents
+        let texts = documents
             .clone()
             .into_iter()
             .map(|d| {
                 format!("{d}")
                     .chars()
                     .take(EMBED_MAX_STR_LEN_ACCORDING_TO_INFINITY)
-                    .collect()
+                    .collect::<String>()
             })
             .collect::<Vec<_>>();
+        let queries = texts
+            .iter()
+            .map(|text| profile.document(text))
+            .collect::<Vec<_>>();
 
         let embeddings = retry(ExponentialBackoff::default(), || async {
             embedder
@@ -55,11 +63,13 @@ impl PipelineStep<false> for Embedding {
                 .await
                 .map_err(|e| Backoff::transient(EmbedError(e)))
         })
-        .await?;
+        .await?
+        .into_iter()
+        .map(|embedding| profile.finish(embedding));
 
         let documents = documents
             .into_iter()
-            .zip(queries)
+            .zip(texts)
             .zip(embeddings)
             .map(
                 |((document, text), embedding)| DocumentTextHeadingEmbedding {
@@ -67,10 +77,17 @@ impl PipelineStep<false> for Embedding {
                     article_title: document.article_title,
                     access_date: document.access_date,
                     modification_date: document.modification_date,
+                    categories: document.categories,
+                    wiki: document.wiki,
+                    document_count: document.document_count,
                     embedding,
                     heading: document.heading,
+                    section: document.section,
+                    ordinal: document.ordinal,
                     document_id: document.document_id,
                     article_id: document.article_id,
+                    page_id: document.page_id,
+                    revision_id: document.revision_id,
                 },
             )
             .collect::<Vec<_>>();
@@ -78,6 +95,6 @@ impl PipelineStep<false> for Embedding {
     }
 
     fn args(&self) -> Self::ARG {
-        self.client.clone()
+        (self.client.clone(), self.profile.clone())
     }
 }
@@ -1,9 +1,12 @@
 use std::{marker::PhantomData, sync::Arc};
 
 use indicatif::ProgressBar;
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::sync::mpsc::{channel, Receiver};
 
-use crate::ingest::pipeline::error::{LinkError, PipelineError};
+use crate::ingest::pipeline::{
+    error::{LinkError, PipelineError},
+    limits::StepLimits,
+};
 
 use super::PipelineStep;
 #[derive(Default)]
@@ -35,12 +38,13 @@ impl<X: Clone + Sync + Send + 'static> PipelineStep<false> for Junction<X> {
 
     async fn link(
         &self,
-        mut receiver: UnboundedReceiver<Self::IN>,
+        mut receiver: Receiver<Self::IN>,
+        limits: StepLimits,
         progress: Arc<ProgressBar>,
         mut next_progress: Vec<Arc<ProgressBar>>,
-    ) -> Result<Vec<UnboundedReceiver<Self::OUT>>, PipelineError> {
-        let (sender1, new_receiver1) = unbounded_channel::<Self::OUT>();
-        let (sender2, new_receiver2) = unbounded_channel::<Self::OUT>();
+    ) -> Result<Vec<Receiver<Self::OUT>>, PipelineError> {
+        let (sender1, new_receiver1) = channel::<Self::OUT>(limits.capacity.max(1));
+        let (sender2, new_receiver2) = channel::<Self::OUT>(limits.capacity.max(1));
         let next_progress1 = next_progress
             .pop()
             .ok_or(LinkError::NoCurrentProgressBar(Self::name()))?
@@ -59,8 +63,8 @@ impl<X: Clone + Sync + Send + 'static> PipelineStep<false> for Junction<X> {
                 progress.inc(1);
                 next_progress1.inc_length(1);
                 next_progress2.inc_length(1);
-                let _ = sender1.send(input.clone());
-                let _ = sender2.send(input);
+                let _ = sender1.send(input.clone()).await;
+                let _ = sender2.send(input).await;
             }
         });
         Ok(vec![new_receiver1, new_receiver2])
@@ -1,6 +1,7 @@
 mod batcher;
+#[cfg(feature = "sqlite")]
+mod compressor;
 mod embeddings;
-mod gzip_compressor;
 mod junction;
 mod pattern_text_splitter;
 mod recursive_text_splitter;
@@ -12,19 +13,24 @@ mod wikipedia_page_parser;
 
 use indicatif::ProgressBar;
 use std::sync::Arc;
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::sync::{
+    mpsc::{channel, Receiver},
+    Semaphore,
+};
 
 pub(crate) use batcher::Batcher;
+#[cfg(feature = "sqlite")]
+pub(crate) use compressor::Compressor;
 pub(crate) use embeddings::Embedding;
-pub(crate) use gzip_compressor::Compressor;
 
 #[cfg(feature = "sqlite")]
 pub(crate) use sqlite_writer::SqliteWriter;
-pub(crate) use wikipedia_dump_reader::WikipediaDumpReader;
+pub(crate) use wikipedia_dump_reader::{get_date_from_xml_name, WikipediaDumpReader};
 pub(crate) use wikipedia_heading_splitter::WikipediaHeadingSplitter;
 pub(crate) use wikipedia_page_parser::WikipediaMarkdownParser;
 
 use super::error::{LinkError, PipelineError};
+use super::limits::StepLimits;
 
 pub(crate) trait PipelineStep<const ASYNC: bool> {
     type IN: Send + Sync + 'static;
@@ -32,13 +38,18 @@ pub(crate) trait PipelineStep<const ASYNC: bool> {
     type OUT: Send + Sync + 'static;
     fn name() -> String;
 
+    /// Spawns the step, reading from `receiver` and sending to a channel holding at most
+    /// `limits.capacity` items. An `ASYNC` step transforms up to `limits.concurrency` items at
+    /// once and stops reading while that many are in flight.
     async fn link(
         &self,
-        mut receiver: UnboundedReceiver<Self::IN>,
+        mut receiver: Receiver<Self::IN>,
+        limits: StepLimits,
         progress: Arc<ProgressBar>,
         next_progress: Vec<Arc<ProgressBar>>,
-    ) -> Result<Vec<UnboundedReceiver<Self::OUT>>, PipelineError> {
-        let (sender, new_receiver) = unbounded_channel::<Self::OUT>();
+    ) -> Result<Vec<Receiver<Self::OUT>>, PipelineError> {
+        let (sender, new_receiver) = channel::<Self::OUT>(limits.capacity.max(1));
+        let in_flight = Arc::new(Semaphore::new(limits.concurrency.max(1)));
         let args = Arc::new(self.args());
         let next_progress = next_progress
             .first()
@@ -53,6 +64,9 @@ pub(crate) trait PipelineStep<const ASYNC: bool> {
                 let progress = progress.clone();
                 let next_progress = next_progress.clone();
                 if ASYNC {
+                    let Ok(permit) = in_flight.clone().acquire_owned().await else {
+                        break;
+                    };
                     tokio::spawn(async move {
                         let transform = Self::transform(input, &args)
                             .await
@@ -65,13 +79,14 @@ pub(crate) trait PipelineStep<const ASYNC: bool> {
                                 for t in transform {
                                     next_progress.inc_length(1);
 
-                                    let _ = sender.send(t);
+                                    let _ = sender.send(t).await;
                                 }
                             }
                             Err(e) => {
                                 log::warn!("{} {e}", Self::name())
                             }
                         }
+                        drop(permit);
 
                         Ok::<(), PipelineError>(())
                     });
@@ -87,7 +102,7 @@ pub(crate) trait PipelineStep<const ASYNC: bool> {
                             for t in transform {
                                 next_progress.inc_length(1);
 
-                                let _ = sender.send(t);
+                                let _ = sender.send(t).await;
                             }
                         }
                         Err(e) => {
@@ -37,7 +37,11 @@ impl PipelineStep<true> for PatternSplitter {
                 article_title: input.article_title.clone(),
                 access_date: input.access_date,
                 modification_date: input.modification_date,
+                categories: input.categories.clone(),
+                wiki: input.wiki.clone(),
                 article_id: input.article_id,
+                page_id: input.page_id,
+                revision_id: input.revision_id,
             })
             .collect::<Vec<_>>())
     }
@@ -30,11 +30,18 @@ impl PipelineStep<true> for Splitter {
             .map(|document| DocumentHeading {
                 document,
                 heading: input.heading.clone(),
+                section: input.section.clone(),
+                ordinal: input.ordinal,
                 article_title: input.article_title.clone(),
                 access_date: input.access_date,
                 modification_date: input.modification_date,
+                categories: input.categories.clone(),
+                wiki: input.wiki.clone(),
+                document_count: input.document_count,
                 document_id: input.document_id,
                 article_id: input.article_id,
+                page_id: input.page_id,
+                revision_id: input.revision_id,
             })
             .collect::<Vec<_>>())
     }
@@ -1,73 +1,421 @@
-use std::{io::Write, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
 
-use sqlx::SqlitePool;
+use chrono::NaiveDateTime;
+use sqlx::{SqliteConnection, SqlitePool};
+use tokio::sync::OnceCell;
 
-use crate::ingest::pipeline::{
-    document::DocumentCompressed,
-    error::{PipelineError, Sql},
+#[cfg(feature = "server")]
+use crate::index::{IncrementalSearchService, SearchServiceImpl};
+use crate::{
+    embedding_client::{EmbeddingProfile, EmbeddingQuantization, Quantizer},
+    ingest::pipeline::{
+        document::DocumentCompressed,
+        error::{PipelineError, Sql},
+    },
+    schema::Store,
 };
 
 use super::PipelineStep;
 
+/// Writes the docstore and the index database.
+///
+/// A full run replaces both. An incremental run appends to them, numbering new articles and
+/// documents after the existing ones: an article whose page or title was written by an earlier
+/// run is replaced, its documents deleted and their ids added to the `tombstones` table of the
+/// index. The new revision of a page keeps the article id of the old one.
+///
+/// Embeddings are stored with the requested quantization, calibrated on the first batch written
+/// and recorded in the `quantization` table. An incremental run refuses a quantization other than
+/// the recorded one.
+///
+/// Every article records the number of documents it was split into, so that the articles whose
+/// documents were all committed are known. Pages finish out of order, so each article is its own
+/// checkpoint. A resumed run keeps the databases and what they record like an incremental one,
+/// see [`SqliteWriter::resume`], and replaces articles as the run it continues did: the
+/// `ingest_run` table of the index records whether that run was incremental and its first article.
 pub(crate) struct SqliteWriter {
     docstore_pool: Arc<SqlitePool>,
     index_pool: Arc<SqlitePool>,
+    /// The first article id of an incremental run, see [`SqliteWriterArgs::replace_before`].
+    replace_before: Option<i64>,
+    quantization: EmbeddingQuantization,
+    binary: bool,
+    quantizer: Arc<OnceCell<Quantizer>>,
+    first_article_id: i64,
+    first_document_id: i64,
+    articles: Arc<Mutex<HashMap<i64, i64>>>,
+    #[cfg(feature = "server")]
+    index: Option<Arc<SearchServiceImpl>>,
+}
+
+pub(crate) struct SqliteWriterArgs {
+    docstore_pool: Arc<SqlitePool>,
+    index_pool: Arc<SqlitePool>,
+    /// The first article id of an incremental run.
+    replace_before: Option<i64>,
+    /// The page id of each article this run wrote to, with the article id it is written under.
+    articles: Arc<Mutex<HashMap<i64, i64>>>,
+    quantization: EmbeddingQuantization,
+    binary: bool,
+    quantizer: Arc<OnceCell<Quantizer>>,
+    #[cfg(feature = "server")]
+    index: Option<Arc<SearchServiceImpl>>,
 }
+
 impl SqliteWriter {
     pub(crate) async fn new(
         docstore_pool: SqlitePool,
         index_pool: SqlitePool,
+        embedding_profile: &EmbeddingProfile,
+        incremental: bool,
+        resume: bool,
+        quantization: EmbeddingQuantization,
+        binary: bool,
     ) -> Result<Self, Sql> {
-        create_docstore_schemas(&docstore_pool).await?;
-        create_index_schemas(&index_pool).await?;
+        let keep = incremental || resume;
+        create_docstore_schemas(&docstore_pool, keep).await?;
+        create_index_schemas(&index_pool, embedding_profile, keep).await?;
+
+        let recorded = if keep {
+            recorded_quantizer(&index_pool).await?
+        } else {
+            None
+        };
+        if let Some(recorded) = &recorded {
+            if recorded.kind != quantization || recorded.binary != binary {
+                return Err(Sql::QuantizationMismatch {
+                    recorded: (recorded.kind, recorded.binary),
+                    requested: (quantization, binary),
+                });
+            }
+        }
+
+        let first_article_id = sqlx::query!("SELECT MAX(id) AS id FROM article")
+            .fetch_one(&docstore_pool)
+            .await
+            .map_err(Sql::Sql)?
+            .id
+            .map_or(0, |id| id + 1);
+        let first_document_id = sqlx::query!("SELECT MAX(id) AS id FROM document")
+            .fetch_one(&docstore_pool)
+            .await
+            .map_err(Sql::Sql)?
+            .id
+            .map_or(0, |id| id + 1);
+
+        let replace_before = if resume {
+            resumed_run(&index_pool, incremental, first_article_id).await?
+        } else {
+            start_run(&index_pool, incremental, first_article_id).await?;
+            incremental.then_some(first_article_id)
+        };
 
         Ok(Self {
             docstore_pool: Arc::new(docstore_pool),
             index_pool: Arc::new(index_pool),
+            replace_before,
+            quantization,
+            binary,
+            quantizer: Arc::new(OnceCell::new_with(recorded)),
+            first_article_id,
+            first_document_id,
+            articles: Arc::default(),
+            #[cfg(feature = "server")]
+            index: None,
         })
     }
+
+    /// Applies the embeddings written and the documents deleted to a live index as each batch
+    /// is committed.
+    #[cfg(feature = "server")]
+    pub(crate) fn with_index(mut self, index: SearchServiceImpl) -> Self {
+        self.index = Some(Arc::new(index));
+        self
+    }
+
+    pub(crate) fn first_article_id(&self) -> i64 {
+        self.first_article_id
+    }
+
+    pub(crate) fn first_document_id(&self) -> i64 {
+        self.first_document_id
+    }
+
+    /// Prepares to continue an interrupted run: deletes the articles whose documents were only
+    /// partly committed, tombstoning their documents. New articles and documents are still
+    /// numbered after the deleted ones, so no tombstoned id is written again, but a deleted article
+    /// which kept the id of an earlier revision is written under that id again. Returns the page
+    /// and revision ids of the articles fully written, whose pages need not be read again.
+    pub(crate) async fn resume(&self) -> Result<HashSet<(i64, i64)>, PipelineError> {
+        let mut docstore_connection = self.docstore_pool.acquire().await.map_err(Sql::Sql)?;
+        let mut index_connection = self.index_pool.acquire().await.map_err(Sql::Sql)?;
+        let incomplete = incomplete_articles(&mut docstore_connection).await?;
+        let mut tombstoned = vec![];
+        let _ = sqlx::query!("BEGIN TRANSACTION;",)
+            .execute(&mut *docstore_connection)
+            .await
+            .map_err(Sql::Sql)?;
+        let _ = sqlx::query!("BEGIN TRANSACTION;",)
+            .execute(&mut *index_connection)
+            .await
+            .map_err(Sql::Sql)?;
+        for (article, page) in &incomplete {
+            tombstoned.extend(
+                delete_article(&mut docstore_connection, &mut index_connection, *article).await?,
+            );
+            if self.replace_before.is_some_and(|before| *article < before) {
+                let mut articles = self.articles.lock().unwrap_or_else(|e| e.into_inner());
+                articles.insert(*page, *article);
+            }
+        }
+        let _ = sqlx::query!("COMMIT TRANSACTION;",)
+            .execute(&mut *docstore_connection)
+            .await
+            .map_err(Sql::Sql)?;
+        let _ = sqlx::query!("COMMIT TRANSACTION;",)
+            .execute(&mut *index_connection)
+            .await
+            .map_err(Sql::Sql)?;
+        #[cfg(feature = "server")]
+        if let Some(index) = &self.index {
+            if !tombstoned.is_empty() {
+                index.delete(&tombstoned).await?;
+            }
+        }
+        if !incomplete.is_empty() {
+            log::info!(
+                "Deleted {} partly written articles and tombstoned their {} documents.",
+                incomplete.len(),
+                tombstoned.len()
+            );
+        }
+
+        let written = sqlx::query!(
+            "SELECT page_id, revision_id FROM article WHERE document_count IS NOT NULL"
+        )
+        .fetch_all(&mut *docstore_connection)
+        .await
+        .map_err(Sql::Sql)?
+        .into_iter()
+        .map(|article| (article.page_id, article.revision_id))
+        .collect();
+        Ok(written)
+    }
+
+    /// Records a run that wrote every article it read in the `completed_on` table of both
+    /// databases, with the date of the dump and the number of articles. Returns `false`, recording
+    /// nothing, while articles are only partly written.
+    pub(crate) async fn complete(&self, dump_date: NaiveDateTime) -> Result<bool, Sql> {
+        let mut docstore_connection = self.docstore_pool.acquire().await.map_err(Sql::Sql)?;
+        let incomplete = incomplete_articles(&mut docstore_connection).await?;
+        if !incomplete.is_empty() {
+            log::warn!(
+                "{} articles were only partly written, run the ingest again with --resume.",
+                incomplete.len()
+            );
+            return Ok(false);
+        }
+        let db_date = dump_date.and_utc().timestamp_millis();
+        let article_count = sqlx::query!("SELECT COUNT(*) AS count FROM article")
+            .fetch_one(&mut *docstore_connection)
+            .await
+            .map_err(Sql::Sql)?
+            .count as i64;
+        let mut index_connection = self.index_pool.acquire().await.map_err(Sql::Sql)?;
+        for connection in [&mut docstore_connection, &mut index_connection] {
+            let _ = sqlx::query!("DELETE FROM completed_on;",)
+                .execute(&mut **connection)
+                .await
+                .map_err(Sql::Sql)?;
+            let _ = sqlx::query!(
+                "INSERT INTO completed_on (db_date, article_count) VALUES (?1, ?2)",
+                db_date,
+                article_count
+            )
+            .execute(&mut **connection)
+            .await
+            .map_err(Sql::Sql)?;
+        }
+        log::info!("Ingest complete, {article_count} articles.");
+        Ok(true)
+    }
 }
 
-async fn create_docstore_schemas(docstore_pool: &SqlitePool) -> Result<(), Sql> {
-    let mut connection = docstore_pool.acquire().await.map_err(Sql::Sql)?;
+/// The quantization a kept index was written with. An index with embeddings but no recorded
+/// quantization was written before quantization existed, with raw `f32`s.
+async fn recorded_quantizer(index_pool: &SqlitePool) -> Result<Option<Quantizer>, Sql> {
+    let mut connection = index_pool.acquire().await.map_err(Sql::Sql)?;
+    if let Some(quantizer) = Quantizer::load(&mut connection).await.map_err(Sql::Sql)? {
+        return Ok(Some(quantizer));
+    }
+    let embedding = sqlx::query!("SELECT gte_small FROM embeddings LIMIT 1")
+        .fetch_optional(&mut *connection)
+        .await
+        .map_err(Sql::Sql)?;
+    Ok(embedding.map(|row| Quantizer::raw(row.gte_small.len() / 4)))
+}
+
+/// Records a new run in the `ingest_run` table of the index, replacing the last.
+async fn start_run(
+    index_pool: &SqlitePool,
+    incremental: bool,
+    first_article_id: i64,
+) -> Result<(), Sql> {
+    let mut connection = index_pool.acquire().await.map_err(Sql::Sql)?;
     let _ = sqlx::query!("BEGIN;",)
         .execute(&mut *connection)
         .await
         .map_err(Sql::Sql)?;
-    let _ = sqlx::query!("DROP TABLE IF EXISTS completed_on;",)
+    let _ = sqlx::query!("DELETE FROM ingest_run;",)
         .execute(&mut *connection)
         .await
         .map_err(Sql::Sql)?;
-    let _ = sqlx::query!("DROP TABLE IF EXISTS document;",)
+    let _ = sqlx::query!(
+        "INSERT INTO ingest_run (incremental, first_article_id) VALUES (?1, ?2)",
+        incremental,
+        first_article_id
+    )
+    .execute(&mut *connection)
+    .await
+    .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("COMMIT;",)
         .execute(&mut *connection)
         .await
         .map_err(Sql::Sql)?;
-    let _ = sqlx::query!("DROP TABLE IF EXISTS article;",)
+    Ok(())
+}
+
+/// The `replace_before` of the run a resumed run continues. A run started before runs were
+/// recorded is taken as `incremental` says, replacing articles written before `first_article_id`.
+async fn resumed_run(
+    index_pool: &SqlitePool,
+    incremental: bool,
+    first_article_id: i64,
+) -> Result<Option<i64>, Sql> {
+    let run = sqlx::query!("SELECT incremental, first_article_id FROM ingest_run")
+        .fetch_optional(index_pool)
+        .await
+        .map_err(Sql::Sql)?;
+    match run {
+        Some(run) => {
+            if (run.incremental != 0) != incremental {
+                log::warn!(
+                    "Resuming the run as it was started, {}.",
+                    if run.incremental != 0 {
+                        "incremental"
+                    } else {
+                        "not incremental"
+                    }
+                );
+            }
+            Ok((run.incremental != 0).then_some(run.first_article_id))
+        }
+        None => Ok(incremental.then_some(first_article_id)),
+    }
+}
+
+/// The ids and page ids of the articles fewer of whose documents were committed than they were
+/// split into. Articles written before the count was recorded are taken as complete.
+async fn incomplete_articles(connection: &mut SqliteConnection) -> Result<Vec<(i64, i64)>, Sql> {
+    Ok(sqlx::query!(
+        "SELECT id, page_id FROM article WHERE document_count IS NOT NULL AND document_count != (SELECT COUNT(*) FROM document WHERE document.article = article.id)"
+    )
+    .fetch_all(&mut *connection)
+    .await
+    .map_err(Sql::Sql)?
+    .into_iter()
+    .map(|article| (article.id, article.page_id))
+    .collect())
+}
+
+/// Brings the docstore to the current schema version, replacing it unless it is `kept`.
+async fn create_docstore_schemas(docstore_pool: &SqlitePool, keep: bool) -> Result<(), Sql> {
+    if !keep {
+        let mut connection = docstore_pool.acquire().await.map_err(Sql::Sql)?;
+        let _ = sqlx::query!("BEGIN;",)
+            .execute(&mut *connection)
+            .await
+            .map_err(Sql::Sql)?;
+        drop_docstore_tables(&mut connection).await?;
+        drop_schema_tables(&mut connection).await?;
+        let _ = sqlx::query!("COMMIT;",)
+            .execute(&mut *connection)
+            .await
+            .map_err(Sql::Sql)?;
+    }
+    Store::Docstore
+        .migrate_sqlite(docstore_pool)
+        .await
+        .map_err(Sql::Schema)
+}
+
+async fn drop_docstore_tables(connection: &mut SqliteConnection) -> Result<(), Sql> {
+    let _ = sqlx::query!("DROP TABLE IF EXISTS completed_on;",)
         .execute(&mut *connection)
         .await
         .map_err(Sql::Sql)?;
-    let _ = sqlx::query!("CREATE TABLE IF NOT EXISTS article ( id INTEGER PRIMARY KEY NOT NULL, title TEXT NOT NULL, access_date INTEGER NOT NULL, modification_date INTEGER NOT NULL, unique(id) );",)
+    let _ = sqlx::query!("DROP TABLE IF EXISTS document;",)
         .execute(&mut *connection)
-        .await.map_err(Sql::Sql)?;
-    let _ = sqlx::query!("CREATE TABLE IF NOT EXISTS document ( id INTEGER PRIMARY KEY NOT NULL,  text BLOB NOT NULL,  article INTEGER NOT NULL,  FOREIGN KEY(article) REFERENCES article(id), unique(id) );",)
+        .await
+        .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("DROP TABLE IF EXISTS article_category;",)
         .execute(&mut *connection)
-        .await.map_err(Sql::Sql)?;
-    let _ = sqlx::query!("CREATE TABLE IF NOT EXISTS completed_on ( db_date INTEGER NOT NULL, article_count INTEGER NOT NULL );",)
+        .await
+        .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("DROP TABLE IF EXISTS compression_dictionary;",)
         .execute(&mut *connection)
-        .await.map_err(Sql::Sql)?;
-    let _ = sqlx::query!("COMMIT;",)
+        .await
+        .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("DROP TABLE IF EXISTS article;",)
         .execute(&mut *connection)
         .await
         .map_err(Sql::Sql)?;
     Ok(())
 }
 
-async fn create_index_schemas(docstore_pool: &SqlitePool) -> Result<(), Sql> {
-    let mut connection = docstore_pool.acquire().await.map_err(Sql::Sql)?;
-    let _ = sqlx::query!("BEGIN;",)
-        .execute(&mut *connection)
+/// Brings the index database to the current schema version, replacing it unless it is `kept`.
+async fn create_index_schemas(
+    index_pool: &SqlitePool,
+    embedding_profile: &EmbeddingProfile,
+    keep: bool,
+) -> Result<(), Sql> {
+    let mut connection = index_pool.acquire().await.map_err(Sql::Sql)?;
+    if !keep {
+        let _ = sqlx::query!("BEGIN;",)
+            .execute(&mut *connection)
+            .await
+            .map_err(Sql::Sql)?;
+        drop_index_tables(&mut connection).await?;
+        drop_schema_tables(&mut connection).await?;
+        let _ = sqlx::query!("COMMIT;",)
+            .execute(&mut *connection)
+            .await
+            .map_err(Sql::Sql)?;
+    }
+    Store::Index
+        .migrate_sqlite(index_pool)
         .await
-        .map_err(Sql::Sql)?;
+        .map_err(Sql::Schema)?;
+    let max_input_chars = embedding_profile.max_input_chars as i64;
+    // A kept index keeps the profile it was embedded with.
+    let _ = sqlx::query!(
+        "INSERT INTO embedding_profile (name, query_prefix, document_prefix, normalize, max_input_chars) SELECT ?1, ?2, ?3, ?4, ?5 WHERE NOT EXISTS (SELECT 1 FROM embedding_profile)",
+        embedding_profile.name,
+        embedding_profile.query_prefix,
+        embedding_profile.document_prefix,
+        embedding_profile.normalize,
+        max_input_chars
+    )
+    .execute(&mut *connection)
+    .await
+    .map_err(Sql::Sql)?;
+    Ok(())
+}
+
+async fn drop_index_tables(connection: &mut SqliteConnection) -> Result<(), Sql> {
     let _ = sqlx::query!("DROP TABLE IF EXISTS completed_on;",)
         .execute(&mut *connection)
         .await
@@ -76,61 +424,228 @@ async fn create_index_schemas(docstore_pool: &SqlitePool) -> Result<(), Sql> {
         .execute(&mut *connection)
         .await
         .map_err(Sql::Sql)?;
-    let _ = sqlx::query!("CREATE TABLE IF NOT EXISTS embeddings ( id INTEGER PRIMARY KEY NOT NULL, gte_small BLOB NOT NULL, unique(id));",)
+    let _ = sqlx::query!("DROP TABLE IF EXISTS embedding_profile;",)
         .execute(&mut *connection)
-        .await.map_err(Sql::Sql)?;
-    let _ = sqlx::query!("CREATE TABLE IF NOT EXISTS completed_on ( db_date INTEGER NOT NULL, article_count INTEGER NOT NULL );",)
+        .await
+        .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("DROP TABLE IF EXISTS tombstones;",)
         .execute(&mut *connection)
-        .await.map_err(Sql::Sql)?;
-    let _ = sqlx::query!("COMMIT;",)
+        .await
+        .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("DROP TABLE IF EXISTS quantization;",)
+        .execute(&mut *connection)
+        .await
+        .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("DROP TABLE IF EXISTS embedding_codes;",)
+        .execute(&mut *connection)
+        .await
+        .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("DROP TABLE IF EXISTS ingest_run;",)
         .execute(&mut *connection)
         .await
         .map_err(Sql::Sql)?;
     Ok(())
 }
+
+/// The migrations applied to a database and the version they brought it to.
+async fn drop_schema_tables(connection: &mut SqliteConnection) -> Result<(), Sql> {
+    let _ = sqlx::query!("DROP TABLE IF EXISTS schema_version;",)
+        .execute(&mut *connection)
+        .await
+        .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("DROP TABLE IF EXISTS _sqlx_migrations;",)
+        .execute(&mut *connection)
+        .await
+        .map_err(Sql::Sql)?;
+    Ok(())
+}
+
+/// Deletes the articles of page `page` or titled `title` written before `replace_before`, with
+/// their documents and embeddings. Returns the id of the deleted article of the same page, which
+/// the new revision is written under, and the ids of the deleted documents, which are tombstoned.
+async fn replace_article(
+    docstore_connection: &mut SqliteConnection,
+    index_connection: &mut SqliteConnection,
+    page: i64,
+    title: &str,
+    replace_before: i64,
+) -> Result<(Option<i64>, Vec<i64>), Sql> {
+    let articles = sqlx::query!(
+        "SELECT id, page_id FROM article WHERE (page_id = ?1 OR title = ?2) AND id < ?3",
+        page,
+        title,
+        replace_before
+    )
+    .fetch_all(&mut *docstore_connection)
+    .await
+    .map_err(Sql::Sql)?;
+
+    let mut replaced = None;
+    let mut tombstoned = vec![];
+    for article in articles {
+        if article.page_id == page {
+            replaced = Some(replaced.map_or(article.id, |id: i64| id.min(article.id)));
+        }
+        tombstoned.extend(delete_article(docstore_connection, index_connection, article.id).await?);
+    }
+    Ok((replaced, tombstoned))
+}
+
+/// Deletes an article with its categories, documents and embeddings. Returns the ids of the
+/// deleted documents, which are tombstoned.
+async fn delete_article(
+    docstore_connection: &mut SqliteConnection,
+    index_connection: &mut SqliteConnection,
+    article: i64,
+) -> Result<Vec<i64>, Sql> {
+    let mut tombstoned = vec![];
+    let documents = sqlx::query!("SELECT id FROM document WHERE article = ?1", article)
+        .fetch_all(&mut *docstore_connection)
+        .await
+        .map_err(Sql::Sql)?;
+    for document in documents {
+        let _ = sqlx::query!("DELETE FROM embeddings WHERE id = ?1", document.id)
+            .execute(&mut *index_connection)
+            .await
+            .map_err(Sql::Sql)?;
+        let _ = sqlx::query!("DELETE FROM embedding_codes WHERE id = ?1", document.id)
+            .execute(&mut *index_connection)
+            .await
+            .map_err(Sql::Sql)?;
+        let _ = sqlx::query!(
+            "INSERT OR IGNORE INTO tombstones (id) VALUES (?1)",
+            document.id
+        )
+        .execute(&mut *index_connection)
+        .await
+        .map_err(Sql::Sql)?;
+        tombstoned.push(document.id);
+    }
+    let _ = sqlx::query!("DELETE FROM document WHERE article = ?1", article)
+        .execute(&mut *docstore_connection)
+        .await
+        .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("DELETE FROM article_category WHERE article = ?1", article)
+        .execute(&mut *docstore_connection)
+        .await
+        .map_err(Sql::Sql)?;
+    let _ = sqlx::query!("DELETE FROM article WHERE id = ?1", article)
+        .execute(&mut *docstore_connection)
+        .await
+        .map_err(Sql::Sql)?;
+    Ok(tombstoned)
+}
+
 impl PipelineStep<true> for SqliteWriter {
     type IN = Vec<DocumentCompressed>;
     type OUT = ();
-    type ARG = (Arc<SqlitePool>, Arc<SqlitePool>);
+    type ARG = SqliteWriterArgs;
 
     async fn transform(
         documents: Self::IN,
-        pools: &Self::ARG,
+        args: &Self::ARG,
     ) -> Result<Vec<Self::OUT>, PipelineError> {
-        let mut docstore_connection = pools.0.acquire().await.map_err(Sql::Sql)?;
-        let mut index_connection = pools.1.acquire().await.map_err(Sql::Sql)?;
+        let quantizer = args
+            .quantizer
+            .get_or_try_init(|| async {
+                let sample = documents
+                    .iter()
+                    .map(|document| document.embedding.clone())
+                    .collect::<Vec<_>>();
+                let quantizer = Quantizer::calibrate(args.quantization, args.binary, &sample);
+                let mut connection = args.index_pool.acquire().await.map_err(Sql::Sql)?;
+                quantizer.save(&mut connection).await.map_err(Sql::Sql)?;
+                Ok::<_, Sql>(quantizer)
+            })
+            .await?;
+
+        let mut docstore_connection = args.docstore_pool.acquire().await.map_err(Sql::Sql)?;
+        let mut index_connection = args.index_pool.acquire().await.map_err(Sql::Sql)?;
+        let mut tombstoned = vec![];
+        #[cfg(feature = "server")]
+        let mut added = vec![];
         let _ = sqlx::query!("BEGIN TRANSACTION;",)
             .execute(&mut *docstore_connection)
             .await;
         let _ = sqlx::query!("BEGIN TRANSACTION;",)
             .execute(&mut *index_connection)
             .await;
-        for document in documents {
+        for mut document in documents {
+            if let Some(replace_before) = args.replace_before {
+                let written = args
+                    .articles
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .get(&document.page_id)
+                    .copied();
+                let article_id = match written {
+                    Some(article_id) => article_id,
+                    None => {
+                        let (replaced, deleted) = replace_article(
+                            &mut docstore_connection,
+                            &mut index_connection,
+                            document.page_id,
+                            &document.article_title,
+                            replace_before,
+                        )
+                        .await?;
+                        tombstoned.extend(deleted);
+                        let article_id = replaced.unwrap_or(document.article_id);
+                        args.articles
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .insert(document.page_id, article_id);
+                        article_id
+                    }
+                };
+                document.article_id = article_id;
+            }
+            #[cfg(feature = "server")]
+            if args.index.is_some() {
+                added.push((document.document_id, document.embedding.clone()));
+            }
+            let codec = document.codec.to_string();
             let access_millis = document.access_date.and_utc().timestamp_millis();
             let modification_millis = document.modification_date.and_utc().timestamp_millis();
-            let document_embedding = {
-                let mut v8: Vec<u8> = vec![];
-                for e in document.embedding {
-                    v8.write_all(&e.to_le_bytes()).unwrap();
-                }
-                v8
-            };
+            let document_embedding = quantizer.encode(&document.embedding);
+            let document_code = quantizer
+                .binary
+                .then(|| Quantizer::code(&document.embedding));
             let _article_future = sqlx::query!(
-                "INSERT OR IGNORE INTO article (id, title, access_date, modification_date) VALUES (?1, ?2, ?3, ?4)",
+                "INSERT OR IGNORE INTO article (id, title, access_date, modification_date, page_id, revision_id, metadata, document_count) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
                 document.article_id,
                 document.article_title,
                 access_millis,
-                modification_millis
+                modification_millis,
+                document.page_id,
+                document.revision_id,
+                document.wiki,
+                document.document_count
             )
             .execute(&mut *docstore_connection)
             .await
             .map_err(Sql::Sql)?;
 
+            for category in document.categories {
+                let _ = sqlx::query!(
+                    "INSERT OR IGNORE INTO article_category (article, category) VALUES (?1, ?2)",
+                    document.article_id,
+                    category
+                )
+                .execute(&mut *docstore_connection)
+                .await
+                .map_err(Sql::Sql)?;
+            }
+
             let _document_future = sqlx::query!(
-                "INSERT INTO document (id, text, article) VALUES (?1, ?2, ?3)",
+                "INSERT INTO document (id, text, article, heading, section, ordinal, codec) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
                 document.document_id,
                 document.document,
-                document.article_id
+                document.article_id,
+                document.heading,
+                document.section,
+                document.ordinal,
+                codec
             )
             .execute(&mut *docstore_connection)
             .await
@@ -144,6 +659,17 @@ impl PipelineStep<true> for SqliteWriter {
             .execute(&mut *index_connection)
             .await
             .map_err(Sql::Sql)?;
+
+            if let Some(document_code) = document_code {
+                let _ = sqlx::query!(
+                    "INSERT INTO embedding_codes (id, code) VALUES (?1, ?2)",
+                    document.document_id,
+                    document_code,
+                )
+                .execute(&mut *index_connection)
+                .await
+                .map_err(Sql::Sql)?;
+            }
         }
         let _ = sqlx::query!("COMMIT TRANSACTION;",)
             .execute(&mut *docstore_connection)
@@ -153,11 +679,32 @@ impl PipelineStep<true> for SqliteWriter {
             .execute(&mut *index_connection)
             .await
             .map_err(Sql::Sql)?;
+
+        #[cfg(feature = "server")]
+        if let Some(index) = &args.index {
+            if !tombstoned.is_empty() {
+                index.delete(&tombstoned).await?;
+            }
+            index.add(added).await?;
+        }
+        if !tombstoned.is_empty() {
+            log::debug!("Tombstoned {} replaced documents", tombstoned.len());
+        }
         Ok(vec![()])
     }
 
     fn args(&self) -> Self::ARG {
-        (self.docstore_pool.clone(), self.index_pool.clone())
+        SqliteWriterArgs {
+            docstore_pool: self.docstore_pool.clone(),
+            index_pool: self.index_pool.clone(),
+            replace_before: self.replace_before,
+            articles: self.articles.clone(),
+            quantization: self.quantization,
+            binary: self.binary,
+            quantizer: self.quantizer.clone(),
+            #[cfg(feature = "server")]
+            index: self.index.clone(),
+        }
     }
     fn name() -> String {
         String::from("Sqlite Writer")
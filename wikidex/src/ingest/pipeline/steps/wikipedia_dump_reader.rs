@@ -2,73 +2,107 @@ use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 
 use indicatif::ProgressBar;
 use parse_mediawiki_dump_reboot::{schema::Namespace, Page};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
-use std::sync::Arc;
-use std::{fs::File, io::BufReader};
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use std::sync::{Arc, Mutex};
+use std::{
+    fs::File,
+    io::{BufReader, Seek},
+};
+use tokio::{
+    sync::mpsc::{channel, Receiver, Sender},
+    task::JoinHandle,
+};
 
-use crate::ingest::pipeline::error::{LinkError, PipelineError, WikipediaDumpReaderError};
+use crate::ingest::pipeline::{
+    error::{LinkError, PipelineError, WikipediaDumpReaderError},
+    limits::StepLimits,
+    wikipedia::{SiteInfo, WikiMarkupProcessor},
+};
 
 use super::PipelineStep;
 const ARTICLE_COUNT_ESTIMATE: u64 = 6968500u64;
 pub(crate) struct WikipediaDumpReader {
     limit: usize,
+    written: Arc<HashSet<(i64, i64)>>,
+    reading: Mutex<Option<JoinHandle<Result<(), PipelineError>>>>,
 }
 
 impl WikipediaDumpReader {
     pub(crate) fn new(limit: usize) -> Self {
-        Self { limit }
+        Self {
+            limit,
+            written: Arc::new(HashSet::new()),
+            reading: Mutex::new(None),
+        }
+    }
+
+    /// Waits for the linked reader to stop, failing when a dump could not be read. The pages
+    /// channel closes either way, so an empty pipeline does not tell a read dump from a failure.
+    pub(crate) async fn finished(&self) -> Result<(), PipelineError> {
+        let reading = self
+            .reading
+            .lock()
+            .map_err(|_| WikipediaDumpReaderError::ErrorReadingDump)?
+            .take();
+        match reading {
+            Some(reading) => reading
+                .await
+                .map_err(|_| WikipediaDumpReaderError::ErrorReadingDump)?,
+            None => Ok(()),
+        }
+    }
+
+    /// Skips the pages whose page and revision ids are `written`, as a resumed run does.
+    pub(crate) fn with_written(self, written: HashSet<(i64, i64)>) -> Self {
+        Self {
+            written: Arc::new(written),
+            ..self
+        }
     }
 }
 impl PipelineStep<false> for WikipediaDumpReader {
     type IN = PathBuf;
     type ARG = ();
-    type OUT = (Page, NaiveDateTime);
+    type OUT = (Page, NaiveDateTime, Arc<WikiMarkupProcessor>);
 
     async fn link(
         &self,
-        mut receiver: UnboundedReceiver<Self::IN>,
+        mut receiver: Receiver<Self::IN>,
+        limits: StepLimits,
         progress: Arc<ProgressBar>,
         next_progress: Vec<Arc<ProgressBar>>,
-    ) -> Result<Vec<UnboundedReceiver<Self::OUT>>, PipelineError> {
-        let (sender, new_receiver) = unbounded_channel::<Self::OUT>();
+    ) -> Result<Vec<Receiver<Self::OUT>>, PipelineError> {
+        let (sender, new_receiver) = channel::<Self::OUT>(limits.capacity.max(1));
         let next_progress = next_progress
             .first()
             .ok_or(LinkError::NoCurrentProgressBar(Self::name()))?
             .clone();
 
         let limit = self.limit;
+        let written = self.written.clone();
         progress.set_message(Self::name().to_string());
         progress.set_length(ARTICLE_COUNT_ESTIMATE);
-        tokio::spawn(async move {
+        let reading = tokio::spawn(async move {
             while let Some(input) = receiver.recv().await {
-                log::info!("{}", input.display());
-                let date = get_date_from_xml_name(&input)?;
-                let file = File::open(input).map_err(|_| {
-                    PipelineError::WikipediaDumpReaderError(
-                        WikipediaDumpReaderError::ErrorReadingDump,
-                    )
-                })?;
-                let file = BufReader::with_capacity(2 * 1024 * 1024, file);
-                let parse: parse_mediawiki_dump_reboot::Parser<BufReader<File>> =
-                    parse_mediawiki_dump_reboot::parse(file);
-
-                let limit = if limit == 0 { usize::MAX } else { limit };
-
-                let pages = parse.filter_map(Result::ok).filter(page_filter).take(limit);
-
-                for page in pages {
-                    next_progress.inc_length(1);
-                    let sender = sender.clone();
-                    tokio::spawn(async move {
-                        let _ = sender.send((page, date));
-                    });
-                    progress.inc(1);
-                }
+                let sender = sender.clone();
+                let written = written.clone();
+                let progress = progress.clone();
+                let next_progress = next_progress.clone();
+                // The dump is read on a blocking thread, which waits whenever the parser is
+                // behind, so the reader never runs further ahead than the pages channel holds.
+                tokio::task::spawn_blocking(move || {
+                    read_dump(input, limit, &written, &sender, &progress, &next_progress)
+                })
+                .await
+                .map_err(|_| WikipediaDumpReaderError::ErrorReadingDump)??;
             }
             Ok::<(), PipelineError>(())
         });
+        if let Ok(mut handle) = self.reading.lock() {
+            *handle = Some(reading);
+        }
         Ok(vec![new_receiver])
     }
 
@@ -85,7 +119,70 @@ impl PipelineStep<false> for WikipediaDumpReader {
     }
 }
 
-fn get_date_from_xml_name(file_name: &Path) -> Result<NaiveDateTime, WikipediaDumpReaderError> {
+/// Sends the pages of the dump at `path` to the parser, skipping those `written` before.
+fn read_dump(
+    path: PathBuf,
+    limit: usize,
+    written: &HashSet<(i64, i64)>,
+    sender: &Sender<<WikipediaDumpReader as PipelineStep<false>>::OUT>,
+    progress: &ProgressBar,
+    next_progress: &ProgressBar,
+) -> Result<(), PipelineError> {
+    log::info!("{}", path.display());
+    let date = get_date_from_xml_name(&path)?;
+    let file = File::open(path).map_err(|_| {
+        PipelineError::WikipediaDumpReaderError(WikipediaDumpReaderError::ErrorReadingDump)
+    })?;
+    let mut file = BufReader::with_capacity(2 * 1024 * 1024, file);
+    let site_info = read_site_info(&mut file)?;
+    log::info!(
+        "{} ({}) at {}",
+        site_info.site_name,
+        site_info.language,
+        site_info.base_url
+    );
+    let markup_processor = Arc::new(WikiMarkupProcessor::new(site_info));
+    let parse: parse_mediawiki_dump_reboot::Parser<BufReader<File>> =
+        parse_mediawiki_dump_reboot::parse(file);
+
+    let limit = if limit == 0 { usize::MAX } else { limit };
+
+    let pages = parse.filter_map(Result::ok).filter(page_filter).take(limit);
+
+    for page in pages {
+        progress.inc(1);
+        if written.contains(&(page.id as i64, page.revision_id as i64)) {
+            continue;
+        }
+        next_progress.inc_length(1);
+        if sender
+            .blocking_send((page, date, markup_processor.clone()))
+            .is_err()
+        {
+            // The pipeline after the reader is gone.
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the `<siteinfo>` at the head of a dump and rewinds it for the page parser. A dump
+/// without one is taken for the English Wikipedia.
+fn read_site_info(dump: &mut BufReader<File>) -> Result<SiteInfo, WikipediaDumpReaderError> {
+    let site_info =
+        SiteInfo::read(&mut *dump).map_err(|_| WikipediaDumpReaderError::ErrorReadingDump)?;
+    dump.rewind()
+        .map_err(|_| WikipediaDumpReaderError::ErrorReadingDump)?;
+    Ok(site_info.unwrap_or_else(|| {
+        log::warn!("The dump has no <siteinfo>, reading it as the English Wikipedia");
+        SiteInfo::default()
+    }))
+}
+
+/// The date of a dump from its file name, e.g. `enwiki-20240420-pages-articles.xml`.
+pub(crate) fn get_date_from_xml_name(
+    file_name: &Path,
+) -> Result<NaiveDateTime, WikipediaDumpReaderError> {
     let date_index_from_split = 1;
     let year_range = 0..4;
     let month_range = 4..6;
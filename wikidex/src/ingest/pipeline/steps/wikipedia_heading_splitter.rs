@@ -6,12 +6,19 @@ use crate::ingest::pipeline::document::DocumentHeading;
 use super::PipelineStep;
 use crate::ingest::pipeline::document::Document;
 use crate::ingest::pipeline::error::{PipelineError, WikipediaHeadingSplitterError};
-use crate::ingest::pipeline::{HEADING_END, HEADING_START};
-#[derive(Default)]
+use crate::ingest::pipeline::{HEADING_END, HEADING_SEPARATOR, HEADING_START};
 pub(crate) struct WikipediaHeadingSplitter {
     document_id: Arc<AtomicI64>,
 }
 
+impl WikipediaHeadingSplitter {
+    pub(crate) fn new(first_document_id: i64) -> Self {
+        Self {
+            document_id: Arc::new(AtomicI64::new(first_document_id)),
+        }
+    }
+}
+
 impl PipelineStep<true> for WikipediaHeadingSplitter {
     type IN = Document;
 
@@ -36,10 +43,17 @@ impl PipelineStep<true> for WikipediaHeadingSplitter {
             return Ok(vec![DocumentHeading {
                 document: input.document.trim().to_string(),
                 heading: input.article_title.to_string(),
+                section: None,
+                ordinal: 0,
                 article_title: input.article_title.clone(),
                 access_date: input.access_date,
                 modification_date: input.modification_date,
+                categories: input.categories.clone(),
+                wiki: input.wiki.clone(),
+                document_count: 1,
                 article_id: input.article_id,
+                page_id: input.page_id,
+                revision_id: input.revision_id,
                 document_id: counter.fetch_add(1, Ordering::Relaxed),
             }]);
         }
@@ -49,24 +63,35 @@ impl PipelineStep<true> for WikipediaHeadingSplitter {
             ))?;
         }
 
-        Ok(input
+        let sections = input
             .document
             .split(HEADING_START)
-            .filter_map(|s| {
+            .enumerate()
+            .filter_map(|(ordinal, s)| {
                 let split = s.split(HEADING_END).collect::<Vec<_>>();
                 match split.len() {
                     2 => {
-                        let heading = format!("{}{}", input.article_title, split.first()?);
+                        let path = split.first()?.split(HEADING_SEPARATOR).collect::<Vec<_>>();
+                        let heading = format!("{}{}", input.article_title, path.join(":"));
+                        let section = path
+                            .last()
+                            .filter(|section| !section.is_empty())
+                            .map(|section| section.to_string());
                         let text = split.get(1)?.to_string();
                         if text.len() > 5 {
-                            Some((heading, text))
+                            Some((heading, section, ordinal, text))
                         } else {
                             None
                         }
                     }
                     1 => {
                         if s.len() > 5 {
-                            Some((input.article_title.to_string(), s.to_string()))
+                            Some((
+                                input.article_title.to_string(),
+                                None,
+                                ordinal,
+                                s.to_string(),
+                            ))
                         } else {
                             None
                         }
@@ -74,14 +99,26 @@ impl PipelineStep<true> for WikipediaHeadingSplitter {
                     _ => None,
                 }
             })
-            .map(|(heading, document)| DocumentHeading {
+            .collect::<Vec<_>>();
+        let document_count = sections.len() as i64;
+
+        Ok(sections
+            .into_iter()
+            .map(|(heading, section, ordinal, document)| DocumentHeading {
                 document: document.trim().to_string(),
                 heading,
+                section,
+                ordinal: ordinal as i64,
                 article_title: input.article_title.clone(),
                 access_date: input.access_date,
                 modification_date: input.modification_date,
+                categories: input.categories.clone(),
+                wiki: input.wiki.clone(),
+                document_count,
                 document_id: counter.fetch_add(1, Ordering::Relaxed),
                 article_id: input.article_id,
+                page_id: input.page_id,
+                revision_id: input.revision_id,
             })
             .collect::<Vec<_>>())
     }
@@ -1,4 +1,4 @@
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime};
 
 use parse_mediawiki_dump_reboot::Page;
 
@@ -17,28 +17,71 @@ use crate::ingest::{pipeline::document::Document, service::Process};
 use super::PipelineStep;
 
 pub(crate) struct WikipediaMarkdownParser {
-    markup_processor: Arc<WikiMarkupProcessor>,
     article_counter: Arc<AtomicI64>,
 }
 
 impl WikipediaMarkdownParser {
-    pub(crate) fn new(markup_processor: WikiMarkupProcessor) -> Self {
+    pub(crate) fn new(first_article_id: i64) -> Self {
         Self {
-            markup_processor: Arc::new(markup_processor),
-            article_counter: Arc::new(AtomicI64::new(0)),
+            article_counter: Arc::new(AtomicI64::new(first_article_id)),
         }
     }
 }
+/// The targets of `[[Category:Name|Sort key]]` links, which the parser drops from the text, with
+/// the category namespace under any of its lowercase `namespaces`, e.g. `[[Kategorie:Name]]`.
+fn categories(markup: &str, namespaces: &[String]) -> Vec<String> {
+    let mut categories = markup
+        .split("[[")
+        .skip(1)
+        .filter_map(|link| {
+            let (namespace, target) = link.split_once(':')?;
+            if !namespaces.contains(&namespace.trim().to_lowercase()) {
+                return None;
+            }
+            let name = target.split(['|', ']']).next()?.replace('_', " ");
+            let name = name.trim();
+            (!name.is_empty() && !name.contains('\n')).then(|| name.to_string())
+        })
+        .collect::<Vec<_>>();
+    categories.sort();
+    categories.dedup();
+    categories
+}
+
+/// The time of a revision from its `<timestamp>`, e.g. `2024-04-01T12:34:56Z`.
+fn revision_date(timestamp: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|timestamp| timestamp.naive_utc())
+}
+
 impl PipelineStep<true> for WikipediaMarkdownParser {
-    type IN = (Page, NaiveDateTime);
-    type ARG = (Arc<WikiMarkupProcessor>, Arc<AtomicI64>);
+    type IN = (Page, NaiveDateTime, Arc<WikiMarkupProcessor>);
+    type ARG = Arc<AtomicI64>;
 
     type OUT = Document;
 
     async fn transform(input: Self::IN, arg: &Self::ARG) -> Result<Vec<Self::OUT>, PipelineError> {
-        let (Page { text, title, .. }, date) = input;
+        let (
+            Page {
+                text,
+                title,
+                id,
+                revision_id,
+                timestamp,
+                ..
+            },
+            date,
+            markup_processor,
+        ) = input;
+        let modification_date = revision_date(&timestamp).unwrap_or_else(|| {
+            log::warn!("Unable to parse the revision timestamp {timestamp} of {title}");
+            date
+        });
 
-        let markup_processor = arg.0.clone();
+        let site_info = markup_processor.site_info();
+        let categories = categories(&text, &site_info.category_namespaces);
+        let wiki = site_info.metadata();
         let ttext = text.clone();
         let (tx, rx) = channel();
         tokio::spawn(async move {
@@ -59,16 +102,50 @@ impl PipelineStep<true> for WikipediaMarkdownParser {
                 document: parse,
                 article_title: title,
                 access_date: date,
-                modification_date: date,
-                article_id: arg.1.fetch_add(1, Ordering::Relaxed),
+                modification_date,
+                categories,
+                wiki,
+                article_id: arg.fetch_add(1, Ordering::Relaxed),
+                page_id: id as i64,
+                revision_id: revision_id as i64,
             }])
         }
     }
 
     fn args(&self) -> Self::ARG {
-        (self.markup_processor.clone(), self.article_counter.clone())
+        self.article_counter.clone()
     }
     fn name() -> String {
         String::from("Parser")
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::{categories, revision_date};
+
+    #[test]
+    fn category_links() {
+        let markup = "Text.\n[[Category:Living_people]]\n[[Category:1952 births|Adams, Douglas]]\n[[Category:Living people]]";
+        let namespaces = ["category".to_string()];
+        assert_eq!(
+            categories(markup, &namespaces),
+            vec!["1952 births", "Living people"]
+        );
+        let markup = "Text mit [[Link]].\n[[Kategorie:Mann]]\n[[:Kategorie:Frau]]";
+        let namespaces = ["kategorie".to_string(), "category".to_string()];
+        assert_eq!(categories(markup, &namespaces), vec!["Mann"]);
+    }
+
+    #[test]
+    fn revision_timestamp() {
+        let expected = NaiveDate::from_ymd_opt(2024, 4, 1)
+            .unwrap()
+            .and_hms_opt(12, 34, 56)
+            .unwrap();
+        assert_eq!(revision_date("2024-04-01T12:34:56Z"), Some(expected));
+        assert_eq!(revision_date("20240401"), None);
+    }
+}
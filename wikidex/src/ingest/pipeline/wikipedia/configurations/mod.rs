@@ -1,3 +1,3 @@
 mod wikipedia_org;
 
-pub(crate) use wikipedia_org::WIKIPEDIA_CONFIGURATION;
+pub(crate) use wikipedia_org::{link_trail, wikimedia_configuration};
@@ -1,91 +1,148 @@
-use parse_wiki_text::ConfigurationSource;
+use parse_wiki_text::{Configuration, ConfigurationSource};
 
-pub(crate) const WIKIPEDIA_CONFIGURATION: &ConfigurationSource = &ConfigurationSource {
-    category_namespaces: &["category"],
-    extension_tags: &[
-        "categorytree",
-        "ce",
-        "charinsert",
-        "chem",
-        "gallery",
-        "graph",
-        "hiero",
-        "imagemap",
-        "indicator",
-        "inputbox",
-        "langconvert",
-        "mapframe",
-        "maplink",
-        "math",
-        "nowiki",
-        "phonos",
-        "poem",
-        "pre",
-        "ref",
-        "references",
-        "score",
-        "section",
-        "source",
-        "syntaxhighlight",
-        "templatedata",
-        "templatestyles",
-        "timeline",
-    ],
-    file_namespaces: &["file", "image"],
-    link_trail: "abcdefghijklmnopqrstuvwxyz",
-    magic_words: &[
-        "archivedtalk",
-        "disambig",
-        "expected_unconnected_page",
-        "expectunusedcategory",
-        "forcetoc",
-        "hiddencat",
-        "index",
-        "newsectionlink",
-        "nocc",
-        "nocontentconvert",
-        "noeditsection",
-        "nogallery",
-        "noglobal",
-        "noindex",
-        "nonewsectionlink",
-        "notalk",
-        "notc",
-        "notitleconvert",
-        "notoc",
-        "staticredirect",
-        "toc",
-    ],
-    protocols: &[
-        "//",
-        "bitcoin:",
-        "ftp://",
-        "ftps://",
-        "geo:",
-        "git://",
-        "gopher://",
-        "http://",
-        "https://",
-        "irc://",
-        "ircs://",
-        "magnet:",
-        "mailto:",
-        "matrix:",
-        "mms://",
-        "news:",
-        "nntp://",
-        "redis://",
-        "sftp://",
-        "sip:",
-        "sips:",
-        "sms:",
-        "ssh://",
-        "svn://",
-        "tel:",
-        "telnet://",
-        "urn:",
-        "worldwind://",
-        "xmpp:",
-    ],
-    redirect_magic_words: &["redirect"],
-};
+const EXTENSION_TAGS: &[&str] = &[
+    "categorytree",
+    "ce",
+    "charinsert",
+    "chem",
+    "gallery",
+    "graph",
+    "hiero",
+    "imagemap",
+    "indicator",
+    "inputbox",
+    "langconvert",
+    "mapframe",
+    "maplink",
+    "math",
+    "nowiki",
+    "phonos",
+    "poem",
+    "pre",
+    "ref",
+    "references",
+    "score",
+    "section",
+    "source",
+    "syntaxhighlight",
+    "templatedata",
+    "templatestyles",
+    "timeline",
+];
+const LINK_TRAIL: &str = "abcdefghijklmnopqrstuvwxyz";
+/// The letters the `$linkTrail` of a language edition adds to the English one, for the editions
+/// whose trail differs. Chinese and Japanese are written without spaces and have no trail.
+const LANGUAGE_LINK_TRAILS: &[(&str, &str)] = &[
+    ("de", "äöüß"),
+    ("fr", "àâçéèêîôûäëïöüùÿæœ"),
+    ("it", "àéèíîìóòúù"),
+    ("nl", "äöüïëéèà"),
+    ("pl", "ęóąśłżźćńĘÓĄŚŁŻŹĆŃ"),
+    ("pt", "áâãàéêẽçíòóôõúüűũ"),
+    ("ru", "абвгдеёжзийклмнопрстуфхцчшщъыьэюя"),
+    ("sv", "åäöéÅÄÖÉ"),
+];
+const LANGUAGES_WITHOUT_LINK_TRAIL: &[&str] = &["ja", "zh"];
+const MAGIC_WORDS: &[&str] = &[
+    "archivedtalk",
+    "disambig",
+    "expected_unconnected_page",
+    "expectunusedcategory",
+    "forcetoc",
+    "hiddencat",
+    "index",
+    "newsectionlink",
+    "nocc",
+    "nocontentconvert",
+    "noeditsection",
+    "nogallery",
+    "noglobal",
+    "noindex",
+    "nonewsectionlink",
+    "notalk",
+    "notc",
+    "notitleconvert",
+    "notoc",
+    "staticredirect",
+    "toc",
+];
+const PROTOCOLS: &[&str] = &[
+    "//",
+    "bitcoin:",
+    "ftp://",
+    "ftps://",
+    "geo:",
+    "git://",
+    "gopher://",
+    "http://",
+    "https://",
+    "irc://",
+    "ircs://",
+    "magnet:",
+    "mailto:",
+    "matrix:",
+    "mms://",
+    "news:",
+    "nntp://",
+    "redis://",
+    "sftp://",
+    "sip:",
+    "sips:",
+    "sms:",
+    "ssh://",
+    "svn://",
+    "tel:",
+    "telnet://",
+    "urn:",
+    "worldwind://",
+    "xmpp:",
+];
+const REDIRECT_MAGIC_WORDS: &[&str] = &["redirect"];
+
+/// The letters which, following a link, MediaWiki takes into the link's text on the edition of
+/// `language`, e.g. the `s` of `[[Mars]]s`. Editions not listed use the English trail.
+pub(crate) fn link_trail(language: &str) -> String {
+    if LANGUAGES_WITHOUT_LINK_TRAIL.contains(&language) {
+        return String::new();
+    }
+    let extension = LANGUAGE_LINK_TRAILS
+        .iter()
+        .find(|(edition, _)| *edition == language)
+        .map_or("", |(_, letters)| letters);
+    [LINK_TRAIL, extension].concat()
+}
+
+/// The parser configuration of a Wikimedia wiki, given the names of its category and file
+/// namespaces in lowercase, e.g. `kategorie` and `datei` on the German Wikipedia, and its
+/// `link_trail`.
+///
+/// Extension tags, magic words and protocols are those of the English Wikipedia, which every
+/// language edition shares.
+pub(crate) fn wikimedia_configuration(
+    category_namespaces: &[&str],
+    file_namespaces: &[&str],
+    link_trail: &str,
+) -> Configuration {
+    Configuration::new(&ConfigurationSource {
+        category_namespaces,
+        extension_tags: EXTENSION_TAGS,
+        file_namespaces,
+        link_trail,
+        magic_words: MAGIC_WORDS,
+        protocols: PROTOCOLS,
+        redirect_magic_words: REDIRECT_MAGIC_WORDS,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::link_trail;
+
+    #[test]
+    fn link_trail_of_language() {
+        assert!(link_trail("de").starts_with("abcdefghijklmnopqrstuvwxyz"));
+        assert!(link_trail("de").contains('ß'));
+        assert_eq!(link_trail("xx"), link_trail("en"));
+        assert_eq!(link_trail("ja"), "");
+    }
+}
@@ -3,5 +3,5 @@ mod parse;
 mod processor;
 
 pub(crate) use error::WikiMarkupProcessingError;
-pub(crate) use parse::{HEADING_END, HEADING_START};
+pub(crate) use parse::{HEADING_END, HEADING_SEPARATOR, HEADING_START};
 pub(crate) use processor::WikiMarkupProcessor;
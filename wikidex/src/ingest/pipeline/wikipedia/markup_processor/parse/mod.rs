@@ -6,5 +6,5 @@ mod tables;
 mod template_params;
 
 pub(super) use nodes::process_to_article;
-pub(crate) use nodes::{HEADING_END, HEADING_START};
+pub(crate) use nodes::{HEADING_END, HEADING_SEPARATOR, HEADING_START};
 pub(super) use regexes::Regexes;
@@ -21,6 +21,9 @@ pub(crate) const STOP_PHRASES: [&str; 7] = [
 ];
 pub(crate) const HEADING_START: &str = "###HEADING_START###";
 pub(crate) const HEADING_END: &str = "###HEADING_END###";
+/// Separates the headings of a section's path between the heading markers. Headings may contain
+/// `:` and any other punctuation.
+pub(crate) const HEADING_SEPARATOR: &str = "###HEADING_SEPARATOR###";
 
 pub(crate) type ParseResult = Result<String, <WikiMarkupProcessor as Process>::E>;
 
@@ -153,18 +156,20 @@ fn adjust_headings(level: &u8, heading: &mut Vec<String>, new_heading: String) -
     }
 
     // Construct the formatted heading string from the adjusted vector
-    let heading_str = format!("{HEADING_START}{}{HEADING_END}", heading.join(":"));
+    let heading_str = format!(
+        "{HEADING_START}{}{HEADING_END}",
+        heading.join(HEADING_SEPARATOR)
+    );
     heading_str
 }
 
 #[cfg(test)]
 mod tests_node_to_string {
 
-    use parse_wiki_text::Configuration;
-
     use crate::{
         ingest::pipeline::wikipedia::{
-            configurations::WIKIPEDIA_CONFIGURATION, markup_processor::parse::Regexes,
+            configurations::{link_trail, wikimedia_configuration},
+            markup_processor::parse::Regexes,
         },
         test_data::{SUPREME_COURT_VOL_129_MARKUP, SUPREME_COURT_VOL_129_PARSE_RESULT},
     };
@@ -175,7 +180,8 @@ mod tests_node_to_string {
     fn read_document_file_to_string() {
         std::env::set_var("RUST_LOG", "info");
         env_logger::init();
-        let configuration = Configuration::new(WIKIPEDIA_CONFIGURATION);
+        let configuration =
+            wikimedia_configuration(&["category"], &["file", "image"], &link_trail("en"));
 
         let document_text = SUPREME_COURT_VOL_129_MARKUP;
 
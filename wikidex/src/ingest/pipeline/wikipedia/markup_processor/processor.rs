@@ -1,22 +1,55 @@
 use crate::ingest::service::Process;
 
 use super::{
-    super::configurations::WIKIPEDIA_CONFIGURATION,
+    super::{
+        configurations::{link_trail, wikimedia_configuration},
+        SiteInfo,
+    },
     parse::{process_to_article, Regexes},
     WikiMarkupProcessingError,
 };
 
 use parse_wiki_text::Configuration;
 
-#[derive(Clone)]
-pub(crate) struct WikiMarkupProcessor;
+/// Parses the markup of the pages of one wiki, whose namespaces its configuration knows.
+pub(crate) struct WikiMarkupProcessor {
+    site_info: SiteInfo,
+    configuration: Configuration,
+}
+
+impl WikiMarkupProcessor {
+    pub(crate) fn new(site_info: SiteInfo) -> Self {
+        let category_namespaces = site_info
+            .category_namespaces
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let file_namespaces = site_info
+            .file_namespaces
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let configuration = wikimedia_configuration(
+            &category_namespaces,
+            &file_namespaces,
+            &link_trail(&site_info.language),
+        );
+        Self {
+            site_info,
+            configuration,
+        }
+    }
+
+    pub(crate) fn site_info(&self) -> &SiteInfo {
+        &self.site_info
+    }
+}
 
 impl Process for WikiMarkupProcessor {
     type E = WikiMarkupProcessingError;
     fn process(&self, markup: &str) -> Result<String, Self::E> {
         let regexes: Regexes = Regexes::new();
-        let configuration = Configuration::new(WIKIPEDIA_CONFIGURATION);
-        let parse = configuration.parse(markup).nodes;
+        let parse = self.configuration.parse(markup).nodes;
 
         process_to_article(&parse, &regexes)
     }
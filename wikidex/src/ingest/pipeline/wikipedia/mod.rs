@@ -1,6 +1,8 @@
 mod configurations;
 mod markup_processor;
+mod site_info;
 
 pub(crate) use markup_processor::{
-    WikiMarkupProcessingError, WikiMarkupProcessor, HEADING_END, HEADING_START,
+    WikiMarkupProcessingError, WikiMarkupProcessor, HEADING_END, HEADING_SEPARATOR, HEADING_START,
 };
+pub(crate) use site_info::SiteInfo;
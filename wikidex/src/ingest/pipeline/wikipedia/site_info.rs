@@ -0,0 +1,224 @@
+use std::io::{self, BufRead};
+
+use serde_json::json;
+use url::Url;
+
+const CATEGORY_NAMESPACE: &str = "14";
+const FILE_NAMESPACE: &str = "6";
+const WIKIMEDIA_FOUNDATION: &str = "Wikimedia Foundation";
+/// The domains of the Wikimedia projects, whose wikis the Wikimedia Foundation publishes.
+const WIKIMEDIA_DOMAINS: &[&str] = &[
+    "mediawiki.org",
+    "wikibooks.org",
+    "wikidata.org",
+    "wikifunctions.org",
+    "wikimedia.org",
+    "wikinews.org",
+    "wikipedia.org",
+    "wikiquote.org",
+    "wikisource.org",
+    "wikiversity.org",
+    "wikivoyage.org",
+    "wiktionary.org",
+];
+
+/// The wiki a dump was exported from, as the `<siteinfo>` at its head describes it.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SiteInfo {
+    /// The `<sitename>`, `Wikipedia` for every language edition.
+    pub(crate) site_name: String,
+    /// The language code of the edition, e.g. `de`, from the `xml:lang` of the dump or else the
+    /// first label of the wiki's host.
+    pub(crate) language: String,
+    /// The scheme and host of the wiki, e.g. `https://de.wikipedia.org`.
+    pub(crate) base_url: String,
+    /// The Wikimedia Foundation for a wiki of its projects, `None` for any other wiki, whose
+    /// publisher the dump does not name.
+    pub(crate) publisher: Option<String>,
+    /// The names of the category namespace in lowercase, the wiki's own and the canonical one.
+    pub(crate) category_namespaces: Vec<String>,
+    /// The names of the file namespace in lowercase, the wiki's own and the canonical ones.
+    pub(crate) file_namespaces: Vec<String>,
+}
+
+impl Default for SiteInfo {
+    fn default() -> Self {
+        Self {
+            site_name: "Wikipedia".to_string(),
+            language: "en".to_string(),
+            base_url: "https://en.wikipedia.org".to_string(),
+            publisher: Some(WIKIMEDIA_FOUNDATION.to_string()),
+            category_namespaces: vec!["category".to_string()],
+            file_namespaces: vec!["file".to_string(), "image".to_string()],
+        }
+    }
+}
+
+impl SiteInfo {
+    /// Reads the `<siteinfo>` of a dump, stopping at its end or at the first page. `None` when the
+    /// dump has no `<siteinfo>` before its first page.
+    pub(crate) fn read<R: BufRead>(dump: R) -> io::Result<Option<Self>> {
+        let mut site_info = SiteInfo::default();
+        let mut found = false;
+        let mut language = None;
+        for line in dump.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.starts_with("<page>") || line.starts_with("</siteinfo>") {
+                break;
+            }
+            if line.starts_with("<mediawiki") {
+                language = attribute(line, "xml:lang");
+            } else if line.starts_with("<siteinfo>") {
+                found = true;
+            } else if let Some(site_name) = element(line, "sitename") {
+                site_info.site_name = site_name;
+            } else if let Some(base) = element(line, "base") {
+                if let Some((base_url, language)) = base_url_and_language(&base) {
+                    site_info.publisher = publisher(&base_url);
+                    site_info.base_url = base_url;
+                    site_info.language = language;
+                }
+            } else if let Some((key, name)) = namespace(line) {
+                let name = name.to_lowercase();
+                let names = match key.as_str() {
+                    CATEGORY_NAMESPACE => &mut site_info.category_namespaces,
+                    FILE_NAMESPACE => &mut site_info.file_namespaces,
+                    _ => continue,
+                };
+                if !names.contains(&name) {
+                    names.insert(0, name);
+                }
+            }
+        }
+        if let Some(language) = language {
+            site_info.language = language;
+        }
+        Ok(found.then_some(site_info))
+    }
+
+    /// The JSON the docstore reads the wiki of an article from, in its `metadata` column.
+    pub(crate) fn metadata(&self) -> String {
+        json!({
+            "site_name": self.site_name,
+            "language": self.language,
+            "base_url": self.base_url,
+            "publisher": self.publisher,
+        })
+        .to_string()
+    }
+}
+
+/// The text of `<name>...</name>` when `line` is that element.
+fn element(line: &str, name: &str) -> Option<String> {
+    let text = line
+        .strip_prefix(&format!("<{name}>"))?
+        .strip_suffix(&format!("</{name}>"))?;
+    Some(unescape(text))
+}
+
+/// The key and the name of `<namespace key="14" case="first-letter">Category</namespace>`, `None`
+/// for the unnamed main namespace.
+fn namespace(line: &str) -> Option<(String, String)> {
+    let attributes = line.strip_prefix("<namespace ")?;
+    let key = attribute(attributes, "key")?;
+    let name = attributes.split_once('>')?.1.strip_suffix("</namespace>")?;
+    Some((key, unescape(name)))
+}
+
+/// The value of the attribute `name` of the element on `line`.
+fn attribute(line: &str, name: &str) -> Option<String> {
+    let value = line
+        .split_once(&format!(" {name}=\""))?
+        .1
+        .split('"')
+        .next()?;
+    Some(unescape(value))
+}
+
+/// The scheme and host of the `<base>` url of a wiki's main page, and the language the host
+/// starts with, `en` when it names none.
+fn base_url_and_language(base: &str) -> Option<(String, String)> {
+    let url = Url::parse(base).ok()?;
+    let host = url.host_str()?;
+    let labels = host.split('.').collect::<Vec<_>>();
+    let language = match labels.as_slice() {
+        [language, _, _, ..] => language.to_string(),
+        _ => "en".to_string(),
+    };
+    Some((format!("{}://{host}", url.scheme()), language))
+}
+
+/// The publisher of the wiki at `base_url`, known for the Wikimedia projects only.
+fn publisher(base_url: &str) -> Option<String> {
+    let host = Url::parse(base_url).ok()?.host_str()?.to_string();
+    WIKIMEDIA_DOMAINS
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{domain}")))
+        .then(|| WIKIMEDIA_FOUNDATION.to_string())
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#039;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+    use super::SiteInfo;
+
+    #[test]
+    fn german_site_info() {
+        let dump = r#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.10/" xml:lang="de">
+  <siteinfo>
+    <sitename>Wikipedia</sitename>
+    <dbname>dewiki</dbname>
+    <base>https://de.wikipedia.org/wiki/Wikipedia:Hauptseite</base>
+    <generator>MediaWiki 1.42.0-wmf.5</generator>
+    <case>first-letter</case>
+    <namespaces>
+      <namespace key="0" case="first-letter" />
+      <namespace key="6" case="first-letter">Datei</namespace>
+      <namespace key="14" case="first-letter">Kategorie</namespace>
+    </namespaces>
+  </siteinfo>
+  <page>
+"#;
+        let site_info = SiteInfo::read(dump.as_bytes()).unwrap().unwrap();
+        assert_eq!(site_info.language, "de");
+        assert_eq!(site_info.base_url, "https://de.wikipedia.org");
+        assert_eq!(site_info.category_namespaces, vec!["kategorie", "category"]);
+        assert_eq!(site_info.file_namespaces, vec!["datei", "file", "image"]);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&site_info.metadata()).unwrap(),
+            serde_json::json!({
+                "site_name": "Wikipedia",
+                "language": "de",
+                "base_url": "https://de.wikipedia.org",
+                "publisher": "Wikimedia Foundation"
+            })
+        );
+    }
+
+    #[test]
+    fn other_wiki_has_no_publisher() {
+        let dump = r#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.10/" xml:lang="en">
+  <siteinfo>
+    <sitename>Memory Alpha</sitename>
+    <base>https://memory-alpha.fandom.com/wiki/Portal:Main</base>
+  </siteinfo>
+"#;
+        let site_info = SiteInfo::read(dump.as_bytes()).unwrap().unwrap();
+        assert_eq!(site_info.publisher, None);
+        assert_eq!(site_info.base_url, "https://memory-alpha.fandom.com");
+    }
+
+    #[test]
+    fn no_site_info() {
+        let dump = "<mediawiki>\n  <page>\n    <title>Mars</title>\n";
+        assert_eq!(SiteInfo::read(dump.as_bytes()).unwrap(), None);
+    }
+}
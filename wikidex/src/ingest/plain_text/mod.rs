@@ -13,7 +13,7 @@ use nebula_client::v3::{GraphClient, GraphSession as GS, GraphTransportResponseH
 use tokio::{net::TcpStream, time::Sleep};
 use url::Url;
 
-use crate::{embedding_client::EmbeddingClient, llm_client::LlmClientImpl};
+use crate::{embedding_client::EmbeddingClientImpl, llm_client::LlmClientImpl};
 
 use self::error::PlainTextProcessingError;
 
@@ -25,12 +25,12 @@ pub(crate) type GraphSession =
 pub(crate) struct PlainTextProcessor {
     pub(crate) graph: GraphSession,
     pub(crate) llm: Arc<LlmClientImpl>,
-    pub(crate) embed: Arc<EmbeddingClient>,
+    pub(crate) embed: Arc<EmbeddingClientImpl>,
 }
 impl PlainTextProcessor {
     pub(crate) fn new(
         llm: Arc<LlmClientImpl>,
-        embed: Arc<EmbeddingClient>,
+        embed: Arc<EmbeddingClientImpl>,
         graph: GraphSession,
         _multi_progress: MultiProgress,
     ) -> Self {
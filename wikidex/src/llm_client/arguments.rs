@@ -1,8 +1,12 @@
 use super::LlmMessage;
 use serde::Serialize;
 
+/// A document as templates see it. `key` is the `corpus:index` a citation names, `index` alone is
+/// only unique within `corpus`.
 #[derive(Serialize)]
 pub(crate) struct LanguageServiceDocument {
+    pub(crate) key: String,
+    pub(crate) corpus: String,
     pub(crate) index: i64,
     pub(crate) text: String,
 }
@@ -15,4 +19,6 @@ pub(crate) struct LanguageServiceArguments {
     pub(crate) user_query: String,
     pub(crate) max_tokens: u16,
     pub(crate) stop_phrases: Vec<String>,
+    pub(crate) system_template: Option<String>,
+    pub(crate) chat_template: Option<String>,
 }
@@ -4,6 +4,8 @@ use std::{error::Error, fmt::Display, str::FromStr};
 pub(crate) enum ModelEndpoint {
     Triton,
     OpenAi,
+    Ollama,
+    LlamaCpp,
 }
 
 impl Display for ModelEndpoint {
@@ -11,6 +13,8 @@ impl Display for ModelEndpoint {
         match self {
             ModelEndpoint::Triton => write!(f, "Triton"),
             ModelEndpoint::OpenAi => write!(f, "Openai"),
+            ModelEndpoint::Ollama => write!(f, "Ollama"),
+            ModelEndpoint::LlamaCpp => write!(f, "LlamaCpp"),
         }
     }
 }
@@ -22,7 +26,7 @@ impl Display for ParseModelEndpointError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Unable to parse model kind. Must be one of [Triton, OpenAi]"
+            "Unable to parse model kind. Must be one of [Triton, OpenAi, Ollama, LlamaCpp]"
         )
     }
 }
@@ -35,6 +39,8 @@ impl FromStr for ModelEndpoint {
         match s.as_str() {
             "triton" => Ok(ModelEndpoint::Triton),
             "openai" => Ok(ModelEndpoint::OpenAi),
+            "ollama" => Ok(ModelEndpoint::Ollama),
+            "llamacpp" | "llama.cpp" | "llama-cpp" => Ok(ModelEndpoint::LlamaCpp),
             _ => Err(ParseModelEndpointError),
         }
     }
@@ -8,6 +8,11 @@ pub(crate) enum LlmClientError {
     TonicStatus(tonic::Status),
     OpenAiClient(async_openai::error::OpenAIError),
     Tera(tera::Error),
+    Template(PromptTemplateError),
+    Reqwest(reqwest::Error),
+    Url(url::ParseError),
+    Json(serde_json::Error),
+    ServerError(String),
     EmptyResponse,
 }
 
@@ -46,6 +51,30 @@ impl From<tera::Error> for LlmClientError {
     }
 }
 
+impl From<reqwest::Error> for LlmClientError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Reqwest(value)
+    }
+}
+
+impl From<url::ParseError> for LlmClientError {
+    fn from(value: url::ParseError) -> Self {
+        Self::Url(value)
+    }
+}
+
+impl From<serde_json::Error> for LlmClientError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<PromptTemplateError> for LlmClientError {
+    fn from(value: PromptTemplateError) -> Self {
+        Self::Template(value)
+    }
+}
+
 impl std::error::Error for LlmClientError {}
 
 impl Display for LlmClientError {
@@ -58,6 +87,71 @@ impl Display for LlmClientError {
             LlmClientError::OpenAiClient(e) => write!(f, "LlmClientError: OpenAiClient: {e}"),
             LlmClientError::EmptyResponse => write!(f, "LlmClientError: Empty Response"),
             LlmClientError::Tera(e) => write!(f, "LlmClientError: Tera: {e:?}"),
+            LlmClientError::Template(e) => write!(f, "LlmClientError: {e}"),
+            LlmClientError::Reqwest(e) => write!(f, "LlmClientError: Reqwest: {e}"),
+            LlmClientError::Url(e) => write!(f, "LlmClientError: Url: {e}"),
+            LlmClientError::Json(e) => write!(f, "LlmClientError: Json: {e}"),
+            LlmClientError::ServerError(e) => write!(f, "LlmClientError: ServerError: {e}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum PromptTemplateError {
+    Io(std::io::Error),
+    Pattern(glob::PatternError),
+    Glob(glob::GlobError),
+    Tera(tera::Error),
+    #[cfg(feature = "server")]
+    Watch(notify::Error),
+    UnknownTemplate(String),
+}
+
+impl From<std::io::Error> for PromptTemplateError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<glob::PatternError> for PromptTemplateError {
+    fn from(value: glob::PatternError) -> Self {
+        Self::Pattern(value)
+    }
+}
+
+impl From<glob::GlobError> for PromptTemplateError {
+    fn from(value: glob::GlobError) -> Self {
+        Self::Glob(value)
+    }
+}
+
+impl From<tera::Error> for PromptTemplateError {
+    fn from(value: tera::Error) -> Self {
+        Self::Tera(value)
+    }
+}
+
+#[cfg(feature = "server")]
+impl From<notify::Error> for PromptTemplateError {
+    fn from(value: notify::Error) -> Self {
+        Self::Watch(value)
+    }
+}
+
+impl std::error::Error for PromptTemplateError {}
+
+impl Display for PromptTemplateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PromptTemplateError::Io(e) => write!(f, "PromptTemplateError: Io: {e}"),
+            PromptTemplateError::Pattern(e) => write!(f, "PromptTemplateError: Pattern: {e}"),
+            PromptTemplateError::Glob(e) => write!(f, "PromptTemplateError: Glob: {e}"),
+            PromptTemplateError::Tera(e) => write!(f, "PromptTemplateError: Tera: {e:?}"),
+            #[cfg(feature = "server")]
+            PromptTemplateError::Watch(e) => write!(f, "PromptTemplateError: Watch: {e}"),
+            PromptTemplateError::UnknownTemplate(name) => {
+                write!(f, "PromptTemplateError: Unknown template {name}")
+            }
         }
     }
 }
@@ -0,0 +1,62 @@
+use async_stream::try_stream;
+use futures::{Stream, StreamExt};
+use url::Url;
+
+use super::LlmClientError;
+
+/// Splits a streamed response body into its non empty lines, as sent by NDJSON and SSE endpoints.
+pub(crate) fn response_lines(
+    response: reqwest::Response,
+) -> impl Stream<Item = Result<String, LlmClientError>> {
+    try_stream! {
+        let mut body = response.bytes_stream();
+        let mut buffer = Vec::new();
+        while let Some(chunk) = body.next().await {
+            buffer.extend_from_slice(&chunk?);
+            while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line = buffer.drain(..=end).collect::<Vec<_>>();
+                let line = std::str::from_utf8(&line)?.trim();
+                if !line.is_empty() {
+                    yield line.to_string();
+                }
+            }
+        }
+        let line = std::str::from_utf8(&buffer)?.trim();
+        if !line.is_empty() {
+            yield line.to_string();
+        }
+    }
+}
+
+/// Resolves `path` below `base`, keeping any prefix the server is mounted under: `Url::join`
+/// alone replaces the last segment of a base without a trailing `/`.
+pub(crate) fn endpoint(base: &Url, path: &str) -> Result<Url, url::ParseError> {
+    let mut base = base.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    base.join(path)
+}
+
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use super::endpoint;
+
+    #[test]
+    fn endpoint_keeps_base_path() {
+        for base in [
+            "http://host:11434",
+            "http://host/ollama",
+            "http://host/ollama/",
+        ] {
+            let base = Url::parse(base).unwrap();
+            let url = endpoint(&base, "api/generate").unwrap();
+            assert_eq!(
+                url.path(),
+                format!("{}/api/generate", base.path().trim_end_matches('/'))
+            );
+        }
+    }
+}
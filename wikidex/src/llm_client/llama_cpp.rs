@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc::UnboundedSender, RwLock};
+use url::Url;
+
+use super::{
+    error::LlmClientError,
+    http_helper::{endpoint, response_lines},
+    LanguageServiceArguments, LlmClient, LlmClientBackend, LlmClientBackendKind, LlmFinishReason,
+    LlmUsage, PromptTemplates,
+};
+
+/// The llama.cpp server hosts a single model, so no model name is sent.
+pub(crate) struct LlamaCppClient {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl LlamaCppClient {
+    pub(crate) fn new(url: Url) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+}
+
+impl LlmClient<LlamaCppClient> {
+    pub(crate) fn new(client: LlamaCppClient, templates: Arc<RwLock<PromptTemplates>>) -> Self {
+        Self { client, templates }
+    }
+}
+
+#[derive(Serialize)]
+struct CompletionRequest<'a> {
+    prompt: String,
+    n_predict: u16,
+    stop: &'a [String],
+    stream: bool,
+    cache_prompt: bool,
+}
+
+#[derive(Deserialize)]
+struct CompletionResponse {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    stop: bool,
+    #[serde(default)]
+    stopped_eos: bool,
+    #[serde(default)]
+    stopped_word: bool,
+    #[serde(default)]
+    stopped_limit: bool,
+    tokens_evaluated: Option<u32>,
+    tokens_predicted: Option<u32>,
+}
+
+impl CompletionResponse {
+    fn usage(&self) -> LlmUsage {
+        let finish_reason = if self.stopped_limit {
+            LlmFinishReason::Length
+        } else if self.stopped_word {
+            LlmFinishReason::Stop
+        } else if self.stopped_eos {
+            LlmFinishReason::EndOfSequence
+        } else {
+            LlmFinishReason::Unknown
+        };
+        LlmUsage {
+            prompt_tokens: self.tokens_evaluated,
+            completion_tokens: self.tokens_predicted,
+            finish_reason,
+        }
+    }
+}
+
+impl LlmClient<LlamaCppClient> {
+    /// Calls `/completion`, streaming server sent events when `tx` is given and forwarding every
+    /// fragment to it.
+    async fn complete(
+        &self,
+        arguments: &LanguageServiceArguments,
+        tx: Option<&UnboundedSender<String>>,
+    ) -> Result<(String, LlmUsage), LlmClientError> {
+        let prompt = self.format_rag_template(arguments).await?;
+        let request = CompletionRequest {
+            prompt,
+            n_predict: arguments.max_tokens,
+            stop: &arguments.stop_phrases,
+            stream: tx.is_some(),
+            cache_prompt: true,
+        };
+
+        let response = self
+            .client
+            .client
+            .post(endpoint(&self.client.url, "completion")?)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let Some(tx) = tx else {
+            let response: CompletionResponse = response.json().await?;
+            let usage = response.usage();
+            return Ok((response.content, usage));
+        };
+
+        let mut lines = std::pin::pin!(response_lines(response));
+        let mut contents = String::new();
+        while let Some(line) = lines.next().await {
+            let line = line?;
+            let Some(data) = line.strip_prefix("data:") else {
+                continue;
+            };
+            let fragment: CompletionResponse = serde_json::from_str(data.trim())?;
+
+            let usage = fragment.stop.then(|| fragment.usage());
+            if !fragment.content.is_empty() {
+                contents.push_str(&fragment.content);
+                let _ = tx.send(fragment.content);
+            }
+
+            if let Some(usage) = usage {
+                return Ok((contents, usage));
+            }
+        }
+
+        Err(LlmClientError::EmptyResponse)
+    }
+}
+
+impl LlmClientBackendKind for LlamaCppClient {}
+impl LlmClientBackend for LlmClient<LlamaCppClient> {
+    async fn get_response(
+        &self,
+        arguments: LanguageServiceArguments,
+    ) -> Result<(String, LlmUsage), LlmClientError> {
+        self.complete(&arguments, None).await
+    }
+
+    async fn stream_response(
+        &self,
+        arguments: LanguageServiceArguments,
+        tx: UnboundedSender<String>,
+    ) -> Result<LlmUsage, LlmClientError> {
+        let (_, usage) = self.complete(&arguments, Some(&tx)).await?;
+        Ok(usage)
+    }
+}
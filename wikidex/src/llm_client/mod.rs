@@ -1,22 +1,28 @@
 mod arguments;
 mod endpoint;
 mod error;
+mod http_helper;
 mod kind;
+mod llama_cpp;
+mod ollama;
 mod openai;
 mod protocol;
+mod templates;
 mod triton;
 mod triton_helper;
 
-use std::{sync::Arc, time::SystemTime};
+use std::sync::Arc;
 
-use chrono::{DateTime, Utc};
 pub(crate) use endpoint::ModelEndpoint;
+pub(crate) use llama_cpp::LlamaCppClient;
+pub(crate) use ollama::OllamaClient;
 pub(crate) use openai::OpenAiInstructClient;
 
-use tera::{Context, Tera};
 use tonic::transport::Channel;
 
-pub(crate) use trtllm::triton::grpc_inference_service_client::GrpcInferenceServiceClient;
+pub(crate) use trtllm::triton::{
+    grpc_inference_service_client::GrpcInferenceServiceClient, ModelReadyRequest,
+};
 
 use tokio::sync::{
     mpsc::{unbounded_channel, UnboundedSender},
@@ -24,9 +30,13 @@ use tokio::sync::{
 };
 
 pub(crate) use arguments::{LanguageServiceArguments, LanguageServiceDocument};
-pub(crate) use error::LlmClientError;
+pub(crate) use error::{LlmClientError, PromptTemplateError};
 pub(crate) use kind::ModelKind;
-pub(crate) use protocol::{LlmMessage, LlmRole, PartialLlmMessage};
+pub(crate) use protocol::{LlmFinishReason, LlmMessage, LlmRole, LlmUsage, PartialLlmMessage};
+#[cfg(feature = "server")]
+pub(crate) use templates::watch_templates;
+pub(crate) use templates::{PromptTemplates, TemplateKind, TemplateNames};
+pub(crate) use triton_helper::{create_embedding_request, deserialize_fp32_tensor};
 
 pub(crate) type TritonClient = GrpcInferenceServiceClient<Channel>;
 
@@ -35,42 +45,31 @@ pub(crate) trait LlmClientBackend {
     async fn get_response(
         &self,
         arguments: LanguageServiceArguments,
-    ) -> Result<String, LlmClientError>;
+    ) -> Result<(String, LlmUsage), LlmClientError>;
 
     async fn stream_response(
         &self,
         arguments: LanguageServiceArguments,
         tx: UnboundedSender<String>,
-    ) -> Result<(), LlmClientError>;
+    ) -> Result<LlmUsage, LlmClientError>;
 }
 
-impl LlmClient<TritonClient> {
+impl<Backend: LlmClientBackendKind> LlmClient<Backend> {
     async fn format_rag_template(
         &self,
-        messages: &Vec<LlmMessage>,
-        documents: &Vec<LanguageServiceDocument>,
-        user_query: &String,
+        arguments: &LanguageServiceArguments,
     ) -> Result<String, LlmClientError> {
-        let mut system_context = Context::new();
-        system_context.insert("documents", documents);
-        system_context.insert("user_query", user_query);
-        system_context.insert(
-            "current_time",
-            &DateTime::<Utc>::from(SystemTime::now()).to_rfc3339(),
-        );
-        let system_message = self
-            .tera
-            .read()
-            .await
-            .render("markdown.md.j2", &system_context)?;
-
-        let mut prompt_context = Context::new();
-        prompt_context.insert("system_message", &system_message);
-        prompt_context.insert("messages", &messages);
-        prompt_context.insert("bos_token", "<s>");
-        prompt_context.insert("eos_token", "</s>");
-
-        let prompt = self.tera.read().await.render("chat.j2", &prompt_context)?;
+        let templates = self.templates.read().await;
+        let system_message = templates.render_system(
+            arguments.system_template.as_deref(),
+            &arguments.documents,
+            &arguments.user_query,
+        )?;
+        let prompt = templates.render_chat(
+            arguments.chat_template.as_deref(),
+            &system_message,
+            &arguments.messages,
+        )?;
         log::info!("{prompt}");
         Ok(prompt)
     }
@@ -81,18 +80,21 @@ pub(crate) trait LlmClientService: LlmClientBackend {
     async fn get_llm_answer(
         &self,
         arguments: LanguageServiceArguments,
-    ) -> Result<LlmMessage, LlmClientError> {
-        let message = self.get_response(arguments).await?;
-        Ok(LlmMessage {
-            role: LlmRole::Assistant,
-            content: message,
-        })
+    ) -> Result<(LlmMessage, LlmUsage), LlmClientError> {
+        let (message, usage) = self.get_response(arguments).await?;
+        Ok((
+            LlmMessage {
+                role: LlmRole::Assistant,
+                content: message,
+            },
+            usage,
+        ))
     }
     async fn stream_llm_answer(
         &self,
         arguments: LanguageServiceArguments,
         tx: UnboundedSender<PartialLlmMessage>,
-    ) -> Result<(), LlmClientError> {
+    ) -> Result<LlmUsage, LlmClientError> {
         let (tx_s, mut rx_s) = unbounded_channel();
 
         tokio::spawn(async move {
@@ -109,24 +111,31 @@ pub(crate) trait LlmClientService: LlmClientBackend {
 
 pub(crate) struct LlmClient<Backend: LlmClientBackendKind> {
     client: Backend,
-    tera: Arc<RwLock<Tera>>,
-    // prompt_template: tera::Template
+    templates: Arc<RwLock<PromptTemplates>>,
 }
 
 pub(crate) enum LlmClientImpl {
     Triton(LlmClient<TritonClient>),
 
     OpenAiInstruct(LlmClient<OpenAiInstructClient>),
+
+    Ollama(LlmClient<OllamaClient>),
+
+    LlamaCpp(LlmClient<LlamaCppClient>),
 }
 impl LlmClientBackend for LlmClientImpl {
     async fn get_response(
         &self,
         arguments: LanguageServiceArguments,
-    ) -> Result<String, LlmClientError> {
+    ) -> Result<(String, LlmUsage), LlmClientError> {
         match self {
             LlmClientImpl::Triton(t) => t.get_response(arguments).await,
 
             LlmClientImpl::OpenAiInstruct(o) => o.get_response(arguments).await,
+
+            LlmClientImpl::Ollama(o) => o.get_response(arguments).await,
+
+            LlmClientImpl::LlamaCpp(l) => l.get_response(arguments).await,
         }
     }
 
@@ -134,11 +143,15 @@ impl LlmClientBackend for LlmClientImpl {
         &self,
         arguments: LanguageServiceArguments,
         tx: UnboundedSender<String>,
-    ) -> Result<(), LlmClientError> {
+    ) -> Result<LlmUsage, LlmClientError> {
         match self {
             LlmClientImpl::Triton(t) => t.stream_response(arguments, tx).await,
 
             LlmClientImpl::OpenAiInstruct(o) => o.stream_response(arguments, tx).await,
+
+            LlmClientImpl::Ollama(o) => o.stream_response(arguments, tx).await,
+
+            LlmClientImpl::LlamaCpp(l) => l.stream_response(arguments, tx).await,
         }
     }
 }
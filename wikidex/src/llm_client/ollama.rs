@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc::UnboundedSender, RwLock};
+use url::Url;
+
+use super::{
+    error::LlmClientError,
+    http_helper::{endpoint, response_lines},
+    LanguageServiceArguments, LlmClient, LlmClientBackend, LlmClientBackendKind, LlmFinishReason,
+    LlmUsage, PromptTemplates,
+};
+
+pub(crate) struct OllamaClient {
+    client: reqwest::Client,
+    url: Url,
+    model_name: String,
+}
+
+impl OllamaClient {
+    pub(crate) fn new(url: Url, model_name: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            model_name,
+        }
+    }
+}
+
+impl LlmClient<OllamaClient> {
+    pub(crate) fn new(client: OllamaClient, templates: Arc<RwLock<PromptTemplates>>) -> Self {
+        Self { client, templates }
+    }
+}
+
+#[derive(Serialize)]
+struct GenerateRequest<'a> {
+    model: &'a str,
+    prompt: String,
+    /// The prompt is already formatted by the chat template.
+    raw: bool,
+    stream: bool,
+    options: GenerateOptions<'a>,
+}
+
+#[derive(Serialize)]
+struct GenerateOptions<'a> {
+    num_predict: u16,
+    stop: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct GenerateResponse {
+    #[serde(default)]
+    response: String,
+    #[serde(default)]
+    done: bool,
+    done_reason: Option<String>,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+    error: Option<String>,
+}
+
+impl GenerateResponse {
+    fn usage(&self) -> LlmUsage {
+        let finish_reason = match self.done_reason.as_deref() {
+            Some("length") => LlmFinishReason::Length,
+            Some("stop") => LlmFinishReason::Stop,
+            _ => LlmFinishReason::Unknown,
+        };
+        LlmUsage {
+            prompt_tokens: self.prompt_eval_count,
+            completion_tokens: self.eval_count,
+            finish_reason,
+        }
+    }
+}
+
+impl LlmClient<OllamaClient> {
+    /// Calls `/api/generate`, streaming when `tx` is given and forwarding every fragment to it.
+    async fn generate(
+        &self,
+        arguments: &LanguageServiceArguments,
+        tx: Option<&UnboundedSender<String>>,
+    ) -> Result<(String, LlmUsage), LlmClientError> {
+        let prompt = self.format_rag_template(arguments).await?;
+        let request = GenerateRequest {
+            model: &self.client.model_name,
+            prompt,
+            raw: true,
+            stream: tx.is_some(),
+            options: GenerateOptions {
+                num_predict: arguments.max_tokens,
+                stop: &arguments.stop_phrases,
+            },
+        };
+
+        let response = self
+            .client
+            .client
+            .post(endpoint(&self.client.url, "api/generate")?)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let Some(tx) = tx else {
+            let response: GenerateResponse = response.json().await?;
+            if let Some(error) = response.error {
+                return Err(LlmClientError::ServerError(error));
+            }
+            let usage = response.usage();
+            return Ok((response.response, usage));
+        };
+
+        let mut lines = std::pin::pin!(response_lines(response));
+        let mut contents = String::new();
+        while let Some(line) = lines.next().await {
+            let fragment: GenerateResponse = serde_json::from_str(&line?)?;
+            if let Some(error) = fragment.error {
+                return Err(LlmClientError::ServerError(error));
+            }
+
+            let usage = fragment.done.then(|| fragment.usage());
+            if !fragment.response.is_empty() {
+                contents.push_str(&fragment.response);
+                let _ = tx.send(fragment.response);
+            }
+
+            if let Some(usage) = usage {
+                return Ok((contents, usage));
+            }
+        }
+
+        Err(LlmClientError::EmptyResponse)
+    }
+}
+
+impl LlmClientBackendKind for OllamaClient {}
+impl LlmClientBackend for LlmClient<OllamaClient> {
+    async fn get_response(
+        &self,
+        arguments: LanguageServiceArguments,
+    ) -> Result<(String, LlmUsage), LlmClientError> {
+        self.generate(&arguments, None).await
+    }
+
+    async fn stream_response(
+        &self,
+        arguments: LanguageServiceArguments,
+        tx: UnboundedSender<String>,
+    ) -> Result<LlmUsage, LlmClientError> {
+        let (_, usage) = self.generate(&arguments, Some(&tx)).await?;
+        Ok(usage)
+    }
+}
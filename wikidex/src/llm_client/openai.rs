@@ -10,12 +10,11 @@ use async_openai::{
     Client,
 };
 use futures::StreamExt;
-use tera::Tera;
 use tokio::sync::{mpsc::UnboundedSender, RwLock};
 
 use super::{
     error::LlmClientError, LanguageServiceArguments, LlmClient, LlmClientBackend,
-    LlmClientBackendKind, LlmMessage, LlmRole,
+    LlmClientBackendKind, LlmFinishReason, LlmMessage, LlmRole, LlmUsage, PromptTemplates,
 };
 
 pub(crate) struct OpenAiInstructClient {
@@ -32,9 +31,25 @@ impl OpenAiInstructClient {
 impl LlmClient<OpenAiInstructClient> {
     pub(crate) async fn new(
         client: OpenAiInstructClient,
-        tera: Arc<RwLock<Tera>>,
+        templates: Arc<RwLock<PromptTemplates>>,
     ) -> Result<Self, LlmClientError> {
-        Ok(Self { client, tera })
+        Ok(Self { client, templates })
+    }
+
+    /// The chat format is applied by the server, so only the system template is rendered here.
+    async fn system_message(
+        &self,
+        arguments: &LanguageServiceArguments,
+    ) -> Result<LlmMessage, LlmClientError> {
+        let content = self.templates.read().await.render_system(
+            arguments.system_template.as_deref(),
+            &arguments.documents,
+            &arguments.user_query,
+        )?;
+        Ok(LlmMessage {
+            role: LlmRole::System,
+            content,
+        })
     }
 }
 
@@ -44,10 +59,10 @@ impl LlmClientBackend for LlmClient<OpenAiInstructClient> {
     async fn get_response(
         &self,
         arguments: LanguageServiceArguments,
-    ) -> Result<String, LlmClientError> {
-        let prompt = arguments
-            .messages
-            .into_iter()
+    ) -> Result<(String, LlmUsage), LlmClientError> {
+        let system_message = self.system_message(&arguments).await?;
+        let prompt = std::iter::once(system_message)
+            .chain(arguments.messages)
             .map(|LlmMessage { role, content }| match role {
                 LlmRole::Assistant => {
                     let message = ChatCompletionRequestAssistantMessage {
@@ -84,25 +99,40 @@ impl LlmClientBackend for LlmClient<OpenAiInstructClient> {
 
         let response = self.client.client.chat().create(request).await?;
 
-        let response = response
+        let choice = response
             .choices
             .into_iter()
             .next()
-            .ok_or(LlmClientError::EmptyResponse)?
+            .ok_or(LlmClientError::EmptyResponse)?;
+
+        let finish_reason = choice
+            .finish_reason
+            .map(LlmFinishReason::from)
+            .unwrap_or(LlmFinishReason::Unknown);
+        let content = choice
             .message
             .content
             .ok_or(LlmClientError::EmptyResponse)?;
-        Ok(response)
+
+        let usage = match response.usage {
+            Some(usage) => LlmUsage {
+                prompt_tokens: Some(usage.prompt_tokens),
+                completion_tokens: Some(usage.completion_tokens),
+                finish_reason,
+            },
+            None => LlmUsage::unreported(finish_reason),
+        };
+        Ok((content, usage))
     }
 
     async fn stream_response(
         &self,
         arguments: LanguageServiceArguments,
         tx: UnboundedSender<String>,
-    ) -> Result<(), LlmClientError> {
-        let prompt = arguments
-            .messages
-            .into_iter()
+    ) -> Result<LlmUsage, LlmClientError> {
+        let system_message = self.system_message(&arguments).await?;
+        let prompt = std::iter::once(system_message)
+            .chain(arguments.messages)
             .map(|LlmMessage { role, content }| match role {
                 LlmRole::Assistant => {
                     let message = ChatCompletionRequestAssistantMessage {
@@ -139,15 +169,27 @@ impl LlmClientBackend for LlmClient<OpenAiInstructClient> {
 
         let mut stream = self.client.client.chat().create_stream(request).await?;
 
-        while let Some(Ok(fragment)) = stream.next().await {
-            let delta = fragment
+        // The client can not request `stream_options.include_usage`, so streamed chunks carry no
+        // usage and only the finish reason is known.
+        let mut finish_reason = LlmFinishReason::Unknown;
+        while let Some(fragment) = stream.next().await {
+            let fragment = fragment?;
+            let choice = fragment
                 .choices
                 .into_iter()
                 .next()
-                .ok_or(LlmClientError::EmptyResponse)?
-                .delta;
+                .ok_or(LlmClientError::EmptyResponse)?;
+
+            let finished = match choice.finish_reason {
+                Some(reason) => {
+                    finish_reason = LlmFinishReason::from(reason);
+                    true
+                }
+                None => false,
+            };
+            let delta = choice.delta;
 
-            if delta.role.is_none() || delta.content.is_none() {
+            if !finished && (delta.role.is_none() || delta.content.is_none()) {
                 return Err(LlmClientError::EmptyResponse);
             }
 
@@ -156,6 +198,6 @@ impl LlmClientBackend for LlmClient<OpenAiInstructClient> {
             }
         }
 
-        Ok(())
+        Ok(LlmUsage::unreported(finish_reason))
     }
 }
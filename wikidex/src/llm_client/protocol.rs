@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use async_openai::types::Role;
+use async_openai::types::{FinishReason, Role};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -48,3 +48,54 @@ impl From<&Role> for LlmRole {
         }
     }
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum LlmFinishReason {
+    /// A stop phrase was generated. OpenAI compatible servers also report end of sequence this way.
+    Stop,
+    /// The `max_tokens` budget was exhausted.
+    Length,
+    /// The model emitted its end of sequence token.
+    EndOfSequence,
+    Unknown,
+}
+
+impl Display for LlmFinishReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LlmFinishReason::Stop => write!(f, "stop"),
+            LlmFinishReason::Length => write!(f, "length"),
+            LlmFinishReason::EndOfSequence => write!(f, "end_of_sequence"),
+            LlmFinishReason::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl From<FinishReason> for LlmFinishReason {
+    fn from(value: FinishReason) -> Self {
+        match value {
+            FinishReason::Stop => LlmFinishReason::Stop,
+            FinishReason::Length => LlmFinishReason::Length,
+            _ => LlmFinishReason::Unknown,
+        }
+    }
+}
+
+/// Token counts are only those the backend reports; a backend that reports none leaves them unset.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub(crate) struct LlmUsage {
+    pub(crate) prompt_tokens: Option<u32>,
+    pub(crate) completion_tokens: Option<u32>,
+    pub(crate) finish_reason: LlmFinishReason,
+}
+
+impl LlmUsage {
+    pub(crate) fn unreported(finish_reason: LlmFinishReason) -> Self {
+        Self {
+            prompt_tokens: None,
+            completion_tokens: None,
+            finish_reason,
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Utc};
+use tera::{Context, Template, Tera};
+
+use super::{error::PromptTemplateError, LanguageServiceDocument, LlmMessage, LlmRole};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TemplateKind {
+    /// Renders the retrieved documents and the question into a system message.
+    System,
+    /// Renders the system message and the conversation into a model specific prompt.
+    Chat,
+}
+
+impl TemplateKind {
+    const ALL: [TemplateKind; 2] = [TemplateKind::System, TemplateKind::Chat];
+
+    fn directory(&self) -> &'static str {
+        match self {
+            TemplateKind::System => "system",
+            TemplateKind::Chat => "chat",
+        }
+    }
+
+    fn qualified_name(&self, name: &str) -> String {
+        format!("{}/{name}", self.directory())
+    }
+}
+
+pub(crate) struct TemplateNames {
+    pub(crate) system: Vec<String>,
+    pub(crate) chat: Vec<String>,
+    pub(crate) default_system: String,
+    pub(crate) default_chat: String,
+}
+
+/// Named prompt templates, loaded from `system/<name>.*.j2` and `chat/<name>.*.j2` below the
+/// root of the template glob.
+///
+/// Every template is rendered against sample arguments when it is loaded. Templates may include,
+/// extend or import macros from one another by name, e.g. `{% extends "system/base" %}`. A
+/// template that fails to parse or render is rejected and the last good version of it stays in
+/// service; templates depending on a rejected template without a good version are rejected too.
+pub(crate) struct PromptTemplates {
+    pattern: String,
+    tera: Tera,
+    sources: BTreeMap<String, String>,
+    default_system: String,
+    default_chat: String,
+}
+
+impl PromptTemplates {
+    pub(crate) fn new(
+        pattern: &str,
+        default_system: &str,
+        default_chat: &str,
+    ) -> Result<Self, PromptTemplateError> {
+        let mut templates = Self {
+            pattern: pattern.to_string(),
+            tera: Tera::default(),
+            sources: BTreeMap::new(),
+            default_system: default_system.to_string(),
+            default_chat: default_chat.to_string(),
+        };
+        templates.reload()?;
+        Ok(templates)
+    }
+
+    pub(crate) fn root(&self) -> PathBuf {
+        pattern_root(&self.pattern)
+    }
+
+    pub(crate) fn reload(&mut self) -> Result<(), PromptTemplateError> {
+        let root = self.root();
+        let mut sources = BTreeMap::new();
+
+        for path in glob::glob(&self.pattern)? {
+            let path = path?;
+            let Some(name) = template_name(&root, &path) else {
+                log::warn!(
+                    "Ignoring {}: templates belong in the system/ or chat/ directory",
+                    path.display()
+                );
+                continue;
+            };
+            let source = std::fs::read_to_string(&path)?;
+            sources.insert(name, source);
+        }
+
+        let mut fallen_back = BTreeSet::new();
+        while let Err((name, e)) = validate(&sources) {
+            log::error!("Rejected template {name}: {e:?}");
+            match self.sources.get(&name) {
+                Some(previous) if fallen_back.insert(name.clone()) => {
+                    sources.insert(name, previous.clone());
+                }
+                _ => {
+                    sources.remove(&name);
+                }
+            }
+        }
+
+        for (kind, default) in [
+            (TemplateKind::System, &self.default_system),
+            (TemplateKind::Chat, &self.default_chat),
+        ] {
+            let name = kind.qualified_name(default);
+            if !sources.contains_key(&name) {
+                return Err(PromptTemplateError::UnknownTemplate(name));
+            }
+        }
+
+        let mut tera = Tera::default();
+        tera.add_raw_templates(&sources)?;
+
+        self.tera = tera;
+        self.sources = sources;
+        Ok(())
+    }
+
+    pub(crate) fn names(&self) -> TemplateNames {
+        let [system, chat] = TemplateKind::ALL.map(|kind| {
+            let prefix = format!("{}/", kind.directory());
+            self.sources
+                .keys()
+                .filter_map(|name| name.strip_prefix(&prefix))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        });
+        TemplateNames {
+            system,
+            chat,
+            default_system: self.default_system.clone(),
+            default_chat: self.default_chat.clone(),
+        }
+    }
+
+    /// Resolves a requested template, falling back to the default for its kind.
+    pub(crate) fn resolve(
+        &self,
+        kind: TemplateKind,
+        name: Option<&str>,
+    ) -> Result<String, PromptTemplateError> {
+        let name = kind.qualified_name(name.unwrap_or(match kind {
+            TemplateKind::System => &self.default_system,
+            TemplateKind::Chat => &self.default_chat,
+        }));
+        if self.sources.contains_key(&name) {
+            Ok(name)
+        } else {
+            Err(PromptTemplateError::UnknownTemplate(name))
+        }
+    }
+
+    pub(crate) fn render_system(
+        &self,
+        name: Option<&str>,
+        documents: &Vec<LanguageServiceDocument>,
+        user_query: &str,
+    ) -> Result<String, PromptTemplateError> {
+        let name = self.resolve(TemplateKind::System, name)?;
+        Ok(self
+            .tera
+            .render(&name, &system_context(documents, user_query))?)
+    }
+
+    pub(crate) fn render_chat(
+        &self,
+        name: Option<&str>,
+        system_message: &str,
+        messages: &Vec<LlmMessage>,
+    ) -> Result<String, PromptTemplateError> {
+        let name = self.resolve(TemplateKind::Chat, name)?;
+        Ok(self
+            .tera
+            .render(&name, &chat_context(system_message, messages))?)
+    }
+}
+
+fn system_context(documents: &Vec<LanguageServiceDocument>, user_query: &str) -> Context {
+    let mut context = Context::new();
+    context.insert("documents", documents);
+    context.insert("user_query", user_query);
+    context.insert(
+        "current_time",
+        &DateTime::<Utc>::from(SystemTime::now()).to_rfc3339(),
+    );
+    context
+}
+
+fn chat_context(system_message: &str, messages: &Vec<LlmMessage>) -> Context {
+    let mut context = Context::new();
+    context.insert("system_message", system_message);
+    context.insert("messages", messages);
+    context.insert("bos_token", "<s>");
+    context.insert("eos_token", "</s>");
+    context
+}
+
+/// Parses the templates and renders each with the same variables the engine provides, resolving
+/// includes, parents and macro imports against the other templates. Returns the first template
+/// found invalid.
+fn validate(sources: &BTreeMap<String, String>) -> Result<(), (String, tera::Error)> {
+    let mut templates = BTreeMap::new();
+    for (name, source) in sources {
+        let template = Template::new(name, None, source).map_err(|e| (name.clone(), e))?;
+        templates.insert(name.as_str(), template);
+    }
+
+    // Tera builds the inheritance chains of all templates at once, so a missing or circular
+    // parent is attributed to its template before the set is built.
+    for (name, template) in &templates {
+        let mut parent = template.parent.as_deref();
+        let mut depth = 0;
+        while let Some(current) = parent {
+            depth += 1;
+            let next = templates.get(current).filter(|_| depth <= templates.len());
+            let Some(next) = next else {
+                return Err((
+                    name.to_string(),
+                    tera::Error::msg(format!("extends unknown or circular template `{current}`")),
+                ));
+            };
+            parent = next.parent.as_deref();
+        }
+        if let Some((file, _)) = template
+            .imported_macro_files
+            .iter()
+            .find(|(file, _)| !templates.contains_key(file.as_str()))
+        {
+            return Err((
+                name.to_string(),
+                tera::Error::msg(format!("imports macros from unknown template `{file}`")),
+            ));
+        }
+    }
+
+    let mut tera = Tera::default();
+    tera.add_raw_templates(sources).map_err(|e| {
+        let name = sources.keys().next().cloned().unwrap_or_default();
+        (name, e)
+    })?;
+
+    for name in sources.keys() {
+        let context = if name.starts_with(TemplateKind::Chat.directory()) {
+            let messages = vec![
+                LlmMessage {
+                    role: LlmRole::User,
+                    content: String::from("What is the capital of France?"),
+                },
+                LlmMessage {
+                    role: LlmRole::Assistant,
+                    content: String::from("Paris is the capital of France. [0]"),
+                },
+                LlmMessage {
+                    role: LlmRole::User,
+                    content: String::from("How many people live there?"),
+                },
+            ];
+            chat_context("You are a helpful assistant.", &messages)
+        } else {
+            let documents = (0..4)
+                .map(|index| LanguageServiceDocument {
+                    key: format!("wikipedia:{index}"),
+                    corpus: String::from("wikipedia"),
+                    index,
+                    text: format!("Document {index}"),
+                })
+                .collect();
+            system_context(&documents, "What is the capital of France?")
+        };
+
+        tera.render(name, &context).map_err(|e| (name.clone(), e))?;
+    }
+    Ok(())
+}
+
+/// The longest leading part of the pattern without glob meta characters.
+fn pattern_root(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        })
+        .collect()
+}
+
+/// `system/concise.md.j2` is the system template `system/concise`.
+fn template_name(root: &Path, path: &Path) -> Option<String> {
+    let mut components = path.strip_prefix(root).ok()?.components();
+    let directory = match components.next()? {
+        Component::Normal(directory) => directory.to_str()?,
+        _ => None?,
+    };
+    let kind = TemplateKind::ALL
+        .into_iter()
+        .find(|kind| kind.directory() == directory)?;
+    let file_name = match (components.next()?, components.next()) {
+        (Component::Normal(file_name), None) => file_name.to_str()?,
+        _ => None?,
+    };
+    let name = file_name.split('.').next()?;
+    Some(kind.qualified_name(name))
+}
+
+#[cfg(feature = "server")]
+pub(crate) use watch::watch_templates;
+
+#[cfg(feature = "server")]
+mod watch {
+    use std::{sync::Arc, time::Duration};
+
+    use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+    use tokio::sync::{mpsc::unbounded_channel, RwLock};
+
+    use super::{PromptTemplateError, PromptTemplates};
+
+    /// Reloads the templates whenever a file below the template root changes. The returned
+    /// watcher stops watching when dropped.
+    pub(crate) async fn watch_templates(
+        templates: Arc<RwLock<PromptTemplates>>,
+    ) -> Result<RecommendedWatcher, PromptTemplateError> {
+        let root = templates.read().await.root();
+        let (tx, mut rx) = unbounded_channel();
+
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let _ = tx.send(event);
+            })?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                match event {
+                    Ok(notify::Event {
+                        kind: EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_),
+                        ..
+                    }) => {}
+                    Ok(_) => continue,
+                    Err(e) => {
+                        log::error!("Template watcher: {e}");
+                        continue;
+                    }
+                }
+
+                // Editors emit a burst of events for a single save.
+                tokio::time::sleep(Duration::from_millis(250)).await;
+                while rx.try_recv().is_ok() {}
+
+                match templates.write().await.reload() {
+                    Ok(()) => log::info!("Reloaded prompt templates"),
+                    Err(e) => log::error!("Could Not Reload Templates! {e}"),
+                }
+            }
+        });
+
+        Ok(watcher)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    };
+
+    use super::{pattern_root, template_name, validate};
+
+    fn templates(sources: &[(&str, &str)]) -> BTreeMap<String, String> {
+        sources
+            .iter()
+            .map(|(name, source)| (name.to_string(), source.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn pattern_root_stops_at_glob() {
+        assert_eq!(pattern_root("/prompt/**/*.j2"), PathBuf::from("/prompt"));
+    }
+
+    #[test]
+    fn template_name_from_path() {
+        let root = Path::new("/prompt");
+        assert_eq!(
+            template_name(root, Path::new("/prompt/system/concise.md.j2")),
+            Some(String::from("system/concise"))
+        );
+        assert_eq!(
+            template_name(root, Path::new("/prompt/chat/chatml.j2")),
+            Some(String::from("chat/chatml"))
+        );
+        assert_eq!(template_name(root, Path::new("/prompt/chat.j2")), None);
+    }
+
+    #[test]
+    fn undefined_variable_is_rejected() {
+        assert!(validate(&templates(&[("system/broken", "{{ not_a_variable }}")])).is_err());
+        assert!(validate(&templates(&[(
+            "system/fine",
+            "{{ user_query }} [{{ documents[0].key }}]"
+        )]))
+        .is_ok());
+    }
+
+    #[test]
+    fn includes_and_parents_resolve_against_the_other_templates() {
+        let base = (
+            "system/base",
+            "{% block intro %}{% endblock intro %} {{ user_query }}",
+        );
+        let child = (
+            "system/child",
+            r#"{% extends "system/base" %}{% block intro %}{% include "system/intro" %}{% endblock intro %}"#,
+        );
+        let intro = (
+            "system/intro",
+            "Answer from {{ documents | length }} documents.",
+        );
+        assert!(validate(&templates(&[base, child, intro])).is_ok());
+
+        let (name, _) = validate(&templates(&[base, child])).unwrap_err();
+        assert_eq!(name, "system/child");
+        let (name, _) = validate(&templates(&[child, intro])).unwrap_err();
+        assert_eq!(name, "system/child");
+    }
+}
@@ -1,34 +1,29 @@
 use std::sync::Arc;
 
 use anyhow::Context;
-use tera::Tera;
 use tokio::sync::{mpsc::UnboundedSender, RwLock};
 
 use super::{
     error::LlmClientError,
     triton_helper::{create_request, deserialize_bytes_tensor},
-    LanguageServiceArguments, LlmClient, LlmClientBackend, LlmClientBackendKind, TritonClient,
+    LanguageServiceArguments, LlmClient, LlmClientBackend, LlmClientBackendKind, LlmFinishReason,
+    LlmUsage, PromptTemplates, TritonClient,
 };
 use async_stream::stream;
 
 impl LlmClient<TritonClient> {
-    pub(crate) fn new(client: TritonClient, tera: Arc<RwLock<Tera>>) -> Self {
-        Self { client, tera }
+    pub(crate) fn new(client: TritonClient, templates: Arc<RwLock<PromptTemplates>>) -> Self {
+        Self { client, templates }
     }
 }
+
 impl LlmClientBackendKind for TritonClient {}
 impl LlmClientBackend for LlmClient<TritonClient> {
     async fn get_response(
         &self,
         arguments: LanguageServiceArguments,
-    ) -> Result<String, LlmClientError> {
-        let prompt = self
-            .format_rag_template(
-                &arguments.messages,
-                &arguments.documents,
-                &arguments.user_query,
-            )
-            .await?;
+    ) -> Result<(String, LlmUsage), LlmClientError> {
+        let prompt = self.format_rag_template(&arguments).await?;
         let request = create_request(prompt, false, arguments.max_tokens, arguments.stop_phrases)?;
         let request = stream! { yield request };
         let request = tonic::Request::new(request);
@@ -56,21 +51,16 @@ impl LlmClientBackend for LlmClient<TritonClient> {
             contents = content;
         }
 
-        Ok(contents)
+        // The ensemble model reports neither token counts nor why generation stopped.
+        Ok((contents, LlmUsage::unreported(LlmFinishReason::Unknown)))
     }
 
     async fn stream_response(
         &self,
         arguments: LanguageServiceArguments,
         tx: UnboundedSender<String>,
-    ) -> Result<(), LlmClientError> {
-        let prompt = self
-            .format_rag_template(
-                &arguments.messages,
-                &arguments.documents,
-                &arguments.user_query,
-            )
-            .await?;
+    ) -> Result<LlmUsage, LlmClientError> {
+        let prompt = self.format_rag_template(&arguments).await?;
         let request = create_request(prompt, true, arguments.max_tokens, arguments.stop_phrases)?;
         let request = stream! { yield request };
         let request = tonic::Request::new(request);
@@ -81,6 +71,7 @@ impl LlmClientBackend for LlmClient<TritonClient> {
             .await
             .context("failed to call triton grpc method model_stream_infer")?
             .into_inner();
+
         while let Some(response) = stream.message().await? {
             if !response.error_message.is_empty() {
                 break;
@@ -98,6 +89,6 @@ impl LlmClientBackend for LlmClient<TritonClient> {
                 let _ = tx.send(content.to_string());
             }
         }
-        Ok(())
+        Ok(LlmUsage::unreported(LlmFinishReason::Unknown))
     }
 }
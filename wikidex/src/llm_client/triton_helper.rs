@@ -21,6 +21,38 @@ pub fn deserialize_bytes_tensor(encoded_tensor: Vec<u8>) -> Result<Vec<String>,
     Ok(strs)
 }
 
+pub(crate) fn deserialize_fp32_tensor(encoded_tensor: Vec<u8>) -> Vec<f32> {
+    let mut bytes = Bytes::from(encoded_tensor);
+    let mut floats = Vec::with_capacity(bytes.remaining() / 4);
+    while bytes.remaining() >= 4 {
+        floats.push(bytes.get_f32_le());
+    }
+    floats
+}
+
+pub(crate) fn create_embedding_request<S: AsRef<str>>(
+    model_name: &str,
+    input_name: &str,
+    output_name: &str,
+    texts: &[S],
+) -> Result<trtllm::triton::ModelInferRequest, anyhow::Error> {
+    Builder::default()
+        .model_name(model_name.to_string())
+        .input(
+            input_name,
+            [texts.len() as i64, 1],
+            IFT::Bytes(
+                texts
+                    .iter()
+                    .map(|s| s.as_ref().as_bytes().to_vec())
+                    .collect(),
+            ),
+        )
+        .output(output_name)
+        .build()
+        .context("Failed")
+}
+
 pub(crate) fn create_request<S: AsRef<str>>(
     prompt: S,
     stream: bool,
@@ -5,15 +5,16 @@ mod cli_args;
 mod config;
 mod embedding_client;
 mod llm_client;
-use std::{ops::DerefMut, time::Duration};
-
-use futures::FutureExt;
-
+#[cfg(all(feature = "sqlite", any(feature = "ingest", feature = "migrate")))]
+mod recompress;
+mod schema;
+mod text_codec;
 use {
     async_openai::{config::OpenAIConfig, Client},
     clap::Parser,
     cli_args::{Cli, Commands},
-    embedding_client::EmbeddingClient,
+    embedding_client::{EmbeddingClientImpl, OpenAiEmbeddingClient, TritonEmbeddingClient},
+    llm_client::{GrpcInferenceServiceClient, ModelEndpoint},
 };
 
 #[cfg(test)]
@@ -28,7 +29,9 @@ use {
 };
 
 #[cfg(feature = "server")]
-use {std::sync::Arc, tera::Tera, tokio::sync::RwLock};
+use {std::sync::Arc, tokio::sync::RwLock};
+#[cfg(feature = "server")]
+mod corpus;
 #[cfg(feature = "server")]
 mod docstore;
 #[cfg(feature = "server")]
@@ -39,15 +42,35 @@ mod index;
 mod inference;
 #[cfg(feature = "server")]
 mod server;
+#[cfg(all(feature = "sqlite", any(feature = "faiss", feature = "pgvector")))]
+use cli_args::{IndexArgs, IndexCommands};
+#[cfg(all(feature = "migrate", feature = "sqlite"))]
+use {cli_args::DocstoreMigrateArgs, docstore::migrate_docstore};
+#[cfg(all(feature = "faiss", feature = "sqlite"))]
+use {cli_args::IndexBuildArgs, ingest::pipeline::IndexBuilder};
+#[cfg(all(feature = "pgvector", feature = "sqlite"))]
+use {
+    cli_args::PgVectorArgs,
+    index::{write_pgvector, PgVectorIndexParameters},
+};
+#[cfg(all(feature = "sqlite", any(feature = "ingest", feature = "migrate")))]
+use {
+    cli_args::{DocstoreArgs, DocstoreCommands, DocstoreRecompressArgs},
+    recompress::recompress_docstore,
+};
 #[cfg(feature = "server")]
 use {
     config::server::Config as ServerConfig,
-    docstore::{Docstore, DocumentStoreImpl},
-    index::FaceIndex,
+    corpus::{Corpus, CorpusRegistry},
+    docstore::{DocumentCacheKind, SharedDocumentCache},
+    embedding_client::EmbeddingCache,
+    index::SearchServiceImpl,
     inference::Engine,
-    llm_client::{LlmClient, LlmClientImpl, ModelEndpoint, OpenAiInstructClient, TritonClient},
+    llm_client::{
+        watch_templates, LlamaCppClient, LlmClient, LlmClientImpl, OllamaClient,
+        OpenAiInstructClient, PromptTemplates, TritonClient,
+    },
     server::run_server,
-    trtllm::triton::grpc_inference_service_client::GrpcInferenceServiceClient,
 };
 
 #[tokio::main]
@@ -69,12 +92,37 @@ async fn main() -> anyhow::Result<()> {
 
             log::info!("\n{config}");
 
-            let openai_config = OpenAIConfig::new().with_api_base(config.embed_url.as_ref());
-            let open_ai_client: Client<OpenAIConfig> = Client::with_config(openai_config);
-            let embedding_client = EmbeddingClient::new(
-                open_ai_client,
-                config.embed_name.to_string_lossy().to_string(),
-            );
+            let embedding_client = match config.embed_endpoint {
+                ModelEndpoint::Triton => {
+                    let client = GrpcInferenceServiceClient::connect(String::from(
+                        config.embed_url.as_ref(),
+                    ))
+                    .await?;
+                    EmbeddingClientImpl::Triton(TritonEmbeddingClient::new(
+                        client,
+                        config.embed_name.to_string_lossy().to_string(),
+                        config.embed_input_name,
+                        config.embed_output_name,
+                        config.embed_batch_size,
+                    ))
+                }
+                // Ollama and llama.cpp serve embeddings through their OpenAI compatible API.
+                ModelEndpoint::OpenAi | ModelEndpoint::Ollama | ModelEndpoint::LlamaCpp => {
+                    let openai_config =
+                        OpenAIConfig::new().with_api_base(config.embed_url.as_ref());
+                    let open_ai_client: Client<OpenAIConfig> = Client::with_config(openai_config);
+                    EmbeddingClientImpl::OpenAi(OpenAiEmbeddingClient::new(
+                        open_ai_client,
+                        config.embed_name.to_string_lossy().to_string(),
+                    ))
+                }
+            };
+
+            #[cfg(feature = "server")]
+            let index = match config.index_url {
+                Some(index_url) => Some(SearchServiceImpl::new(index_url).await?),
+                None => None,
+            };
 
             let pipeline = PipelineProcessor;
 
@@ -84,6 +132,15 @@ async fn main() -> anyhow::Result<()> {
                     config.wiki_xml,
                     config.output_directory,
                     embedding_client,
+                    config.embed_profile,
+                    config.incremental,
+                    config.resume,
+                    config.quantization,
+                    config.binary,
+                    config.compression,
+                    config.limits,
+                    #[cfg(feature = "server")]
+                    index,
                 )
                 .await
                 .map_err(anyhow::Error::from)?;
@@ -97,52 +154,46 @@ async fn main() -> anyhow::Result<()> {
 
             log::info!("\n{config}");
 
-            let docstore = match config.docstore_url.scheme() {
-                #[cfg(feature = "sqlite")]
-                "sqlite" => {
-                    let docstore =
-                        Docstore::<sqlx::Sqlite>::new(&config.docstore_url, &config.redis_url)
-                            .await?;
-                    DocumentStoreImpl::Sqlite(docstore)
-                }
-                #[cfg(feature = "postgres")]
-                "postgres" => {
-                    let docstore =
-                        Docstore::<sqlx::Postgres>::new(&config.docstore_url, &config.redis_url)
-                            .await?;
-
-                    DocumentStoreImpl::Postgres(docstore)
-                }
-                _ => todo!(),
+            let redis = match &config.redis_url {
+                Some(redis_url) => match EmbeddingCache::redis_connection(redis_url).await {
+                    Ok(redis) => Some(redis),
+                    Err(e) if config.document_cache == DocumentCacheKind::Redis => {
+                        return Err(e.into())
+                    }
+                    Err(e) => {
+                        log::warn!("Caching query embeddings in process only: {e}");
+                        None
+                    }
+                },
+                None => None,
             };
+            let document_cache = Arc::new(SharedDocumentCache::new(
+                config.document_cache,
+                config.document_cache_ttl,
+                config.document_cache_bytes,
+                redis.clone(),
+            )?);
+
+            let mut corpora = vec![];
+            for corpus in config.corpora {
+                corpora.push(Corpus::new(corpus, &document_cache).await?);
+            }
+            let corpora = CorpusRegistry::new(corpora)?;
+
+            let templates = Arc::new(RwLock::new(PromptTemplates::new(
+                &config.system_prompt_template_path.to_string_lossy(),
+                &config.default_system_template,
+                &config.default_chat_template,
+            )?));
+            let _template_watcher = watch_templates(templates.clone()).await?;
 
-            let index = FaceIndex::new(config.index_url);
-
-            let tera_engine = Arc::new(RwLock::new(
-                Tera::new(config.system_prompt_template_path.to_str().unwrap()).unwrap(),
-            ));
-            let tera = tera_engine.clone();
-            tokio::spawn(async move {
-                loop {
-                    tokio::time::sleep(Duration::from_secs(2)).await;
-                    tera.write()
-                        .map(|mut t| match t.deref_mut().full_reload() {
-                            Ok(_) => (),
-                            Err(e) => {
-                                log::error!("Could Not Reload Template! {e}");
-                            }
-                        })
-                        .await;
-                }
-            });
-            let tera = tera_engine.clone();
             let llm_client = match config.llm_endpoint {
                 ModelEndpoint::Triton => {
                     let client =
                         GrpcInferenceServiceClient::connect(String::from(config.llm_url.as_ref()))
                             .await?;
 
-                    LlmClientImpl::Triton(LlmClient::<TritonClient>::new(client, tera))
+                    LlmClientImpl::Triton(LlmClient::<TritonClient>::new(client, templates.clone()))
                 }
                 ModelEndpoint::OpenAi => {
                     let openai_config = OpenAIConfig::new().with_api_base(config.llm_url);
@@ -152,31 +203,141 @@ async fn main() -> anyhow::Result<()> {
                         config.llm_name.display().to_string(),
                     );
                     let openai_client =
-                        LlmClient::<OpenAiInstructClient>::new(client, tera).await?;
+                        LlmClient::<OpenAiInstructClient>::new(client, templates.clone()).await?;
 
                     LlmClientImpl::OpenAiInstruct(openai_client)
                 }
+                ModelEndpoint::Ollama => {
+                    let client =
+                        OllamaClient::new(config.llm_url, config.llm_name.display().to_string());
+                    LlmClientImpl::Ollama(LlmClient::<OllamaClient>::new(client, templates.clone()))
+                }
+                ModelEndpoint::LlamaCpp => {
+                    let client = LlamaCppClient::new(config.llm_url);
+                    LlmClientImpl::LlamaCpp(LlmClient::<LlamaCppClient>::new(
+                        client,
+                        templates.clone(),
+                    ))
+                }
             };
 
             let embed_client = match config.embed_endpoint {
-                ModelEndpoint::Triton => todo!(),
-                ModelEndpoint::OpenAi => {
+                ModelEndpoint::Triton => {
+                    let client = GrpcInferenceServiceClient::connect(String::from(
+                        config.embed_url.as_ref(),
+                    ))
+                    .await?;
+                    EmbeddingClientImpl::Triton(TritonEmbeddingClient::new(
+                        client,
+                        config.embed_name.to_string_lossy().to_string(),
+                        config.embed_input_name,
+                        config.embed_output_name,
+                        config.embed_batch_size,
+                    ))
+                }
+                // Ollama and llama.cpp serve embeddings through their OpenAI compatible API.
+                ModelEndpoint::OpenAi | ModelEndpoint::Ollama | ModelEndpoint::LlamaCpp => {
                     let openai_config =
                         OpenAIConfig::new().with_api_base(config.embed_url.as_ref());
                     let open_ai_client: Client<OpenAIConfig> = Client::with_config(openai_config);
-                    EmbeddingClient::new(
+                    EmbeddingClientImpl::OpenAi(OpenAiEmbeddingClient::new(
                         open_ai_client,
                         config.embed_name.to_string_lossy().to_string(),
-                    )
+                    ))
                 }
             };
 
-            let engine = Engine::new(index, embed_client, llm_client, docstore).await;
+            let embedding_cache = EmbeddingCache::new(
+                config.embed_name.to_string_lossy().to_string(),
+                config.embedding_cache_ttl,
+                config.embedding_cache_capacity,
+                redis,
+            );
+
+            let engine = Engine::new(
+                corpora,
+                embed_client,
+                embedding_cache,
+                document_cache,
+                llm_client,
+                templates,
+            )
+            .await;
 
             let run_server = run_server(engine, config.host, config.port);
             let server: actix_web::dev::Server = run_server?;
 
             server.await.map_err(anyhow::Error::from)
         }
+
+        #[cfg(all(feature = "sqlite", any(feature = "faiss", feature = "pgvector")))]
+        Commands::Index(IndexArgs { command }) => {
+            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+                .init();
+            match command {
+                #[cfg(feature = "faiss")]
+                IndexCommands::Build(IndexBuildArgs {
+                    index_database_url,
+                    output,
+                    factory,
+                    metric,
+                    train_sample,
+                    chunk_size,
+                }) => {
+                    let builder = IndexBuilder {
+                        index_database_url,
+                        output,
+                        factory,
+                        metric,
+                        train_sample,
+                        chunk_size,
+                    };
+                    builder.build().await
+                }
+                #[cfg(feature = "pgvector")]
+                IndexCommands::Pgvector(PgVectorArgs {
+                    index_database_url,
+                    index_url,
+                    kind,
+                    m,
+                    ef_construction,
+                    lists,
+                }) => {
+                    let parameters = PgVectorIndexParameters {
+                        kind,
+                        m,
+                        ef_construction,
+                        lists,
+                    };
+                    write_pgvector(&index_database_url, &index_url, parameters)
+                        .await
+                        .map_err(anyhow::Error::from)
+                }
+            }
+        }
+
+        #[cfg(all(feature = "sqlite", any(feature = "ingest", feature = "migrate")))]
+        Commands::Docstore(DocstoreArgs { command }) => {
+            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+                .init();
+            match command {
+                #[cfg(feature = "migrate")]
+                DocstoreCommands::Migrate(DocstoreMigrateArgs {
+                    docstore_database_url,
+                    docstore_url,
+                    batch_size,
+                }) => migrate_docstore(&docstore_database_url, &docstore_url, batch_size)
+                    .await
+                    .map_err(anyhow::Error::from),
+                DocstoreCommands::Recompress(DocstoreRecompressArgs {
+                    docstore_database_url,
+                    codec,
+                    train_sample,
+                    batch_size,
+                }) => recompress_docstore(&docstore_database_url, codec, train_sample, batch_size)
+                    .await
+                    .map_err(anyhow::Error::from),
+            }
+        }
     }
 }
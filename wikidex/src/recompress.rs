@@ -0,0 +1,164 @@
+use std::fmt::{self, Display, Formatter};
+
+use sqlx::{Row, SqlitePool};
+use url::Url;
+
+use crate::{
+    schema::{SchemaVersionError, Store},
+    text_codec::{TextCodec, TextCompressor},
+};
+
+/// Rewrites the documents of an ingest's `wikipedia_docstore.sqlite` compressed with `codec`.
+///
+/// A docstore written before documents recorded their codec gets a `codec` column marking every
+/// document as gzip. The docstore is then brought to the current schema version, which records
+/// one in a docstore written before versions were. Without a dictionary one is trained on
+/// `train_sample` documents evenly spaced over the table; a recorded one is kept, since documents
+/// may already be compressed with it.
+///
+/// Documents are converted `batch_size` at a time in id order, each batch in its own transaction,
+/// so an interrupted run continues with the documents it had not converted yet. The docstore is
+/// vacuumed at the end to return the space saved.
+pub(crate) async fn recompress_docstore(
+    docstore_database_url: &Url,
+    codec: TextCodec,
+    train_sample: usize,
+    batch_size: usize,
+) -> Result<(), DocstoreRecompressError> {
+    let pool = SqlitePool::connect(docstore_database_url.as_str()).await?;
+    let batch_size = batch_size.max(1);
+
+    let has_codec = sqlx::query(
+        "SELECT COUNT(*) AS count FROM pragma_table_info('document') WHERE name = 'codec'",
+    )
+    .fetch_one(&pool)
+    .await?
+    .get::<i64, _>("count")
+        > 0;
+    if !has_codec {
+        log::info!("Recording the codec of every document as gzip");
+        sqlx::query("ALTER TABLE document ADD COLUMN codec TEXT NOT NULL DEFAULT 'gzip'")
+            .execute(&pool)
+            .await?;
+    }
+    Store::Docstore.migrate_sqlite(&pool).await?;
+
+    let recorded = TextCompressor::load(&mut *pool.acquire().await?).await?;
+    let compressor = match (codec, recorded) {
+        (_, Some(dictionary)) => TextCompressor::new(codec, Some(dictionary)),
+        (TextCodec::Gzip, None) => TextCompressor::new(codec, None),
+        (TextCodec::Zstd, None) => {
+            let reader = TextCompressor::new(codec, None);
+            let sample = sample_documents(&pool, &reader, train_sample).await?;
+            log::info!("Training a dictionary on {} documents", sample.len());
+            let trained = TextCompressor::train(codec, &sample);
+            trained.save(&mut *pool.acquire().await?).await?;
+            trained
+        }
+    };
+
+    let codec_name = codec.to_string();
+    let mut converted = 0usize;
+    let mut after = -1i64;
+    loop {
+        let rows = sqlx::query(
+            "SELECT id, text, codec FROM document WHERE id > ?1 AND codec != ?2 ORDER BY id LIMIT ?3",
+        )
+        .bind(after)
+        .bind(&codec_name)
+        .bind(batch_size as i64)
+        .fetch_all(&pool)
+        .await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        after = last.get::<i64, _>("id");
+
+        let mut transaction = pool.begin().await?;
+        for row in &rows {
+            let text = decompress_row(&compressor, row)?;
+            sqlx::query("UPDATE document SET text = ?1, codec = ?2 WHERE id = ?3")
+                .bind(compressor.compress(&text)?)
+                .bind(&codec_name)
+                .bind(row.get::<i64, _>("id"))
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        converted += rows.len();
+        log::info!("Recompressed {converted} documents");
+    }
+
+    log::info!("Vacuuming");
+    sqlx::query("VACUUM").execute(&pool).await?;
+    pool.close().await;
+    Ok(())
+}
+
+/// The text of up to `size` documents evenly spaced over the table.
+async fn sample_documents(
+    pool: &SqlitePool,
+    reader: &TextCompressor,
+    size: usize,
+) -> Result<Vec<String>, DocstoreRecompressError> {
+    let count = sqlx::query("SELECT COUNT(*) AS count FROM document")
+        .fetch_one(pool)
+        .await?
+        .get::<i64, _>("count");
+    let step = (count / size.max(1) as i64).max(1);
+    sqlx::query("SELECT text, codec FROM document WHERE id % ?1 = 0 LIMIT ?2")
+        .bind(step)
+        .bind(size as i64)
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| decompress_row(reader, row))
+        .collect()
+}
+
+fn decompress_row(
+    compressor: &TextCompressor,
+    row: &sqlx::sqlite::SqliteRow,
+) -> Result<String, DocstoreRecompressError> {
+    let codec = row
+        .get::<String, _>("codec")
+        .parse::<TextCodec>()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    Ok(compressor.decompress(codec, row.get("text"))?)
+}
+
+#[derive(Debug)]
+pub(crate) enum DocstoreRecompressError {
+    Database(sqlx::error::Error),
+    Io(std::io::Error),
+    Schema(SchemaVersionError),
+}
+
+impl From<sqlx::error::Error> for DocstoreRecompressError {
+    fn from(value: sqlx::error::Error) -> Self {
+        Self::Database(value)
+    }
+}
+impl From<std::io::Error> for DocstoreRecompressError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+impl From<SchemaVersionError> for DocstoreRecompressError {
+    fn from(value: SchemaVersionError) -> Self {
+        Self::Schema(value)
+    }
+}
+
+impl std::error::Error for DocstoreRecompressError {}
+impl Display for DocstoreRecompressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DocstoreRecompressError::Database(e) => {
+                write!(f, "DocstoreRecompressError: Database: {e}")
+            }
+            DocstoreRecompressError::Io(e) => write!(f, "DocstoreRecompressError: Io: {e}"),
+            DocstoreRecompressError::Schema(e) => write!(f, "DocstoreRecompressError: {e}"),
+        }
+    }
+}
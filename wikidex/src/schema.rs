@@ -0,0 +1,283 @@
+use std::{error::Error, fmt::Display};
+
+use sqlx::migrate::MigrateError;
+#[cfg(any(feature = "sqlite", feature = "postgres", feature = "migrate"))]
+use sqlx::migrate::Migrator;
+#[cfg(any(feature = "postgres", feature = "migrate"))]
+use sqlx::PgPool;
+#[cfg(feature = "sqlite")]
+use {
+    sqlx::{Connection, Executor, SqliteConnection, SqlitePool},
+    std::collections::HashSet,
+    url::Url,
+};
+
+#[cfg(feature = "sqlite")]
+static SQLITE_DOCSTORE: Migrator = sqlx::migrate!("./migrations/sqlite/docstore");
+#[cfg(feature = "sqlite")]
+static SQLITE_INDEX: Migrator = sqlx::migrate!("./migrations/sqlite/index");
+/// The schema `wikidex docstore migrate` copies a SQLite docstore into.
+#[cfg(any(feature = "postgres", feature = "migrate"))]
+static POSTGRES_DOCSTORE: Migrator = sqlx::migrate!("./migrations/postgres/docstore");
+
+const VERSION_QUERY: &str = "SELECT MAX(version) FROM schema_version";
+/// Every column of every table, by table.
+#[cfg(feature = "sqlite")]
+const SQLITE_COLUMNS_QUERY: &str = "SELECT m.name, p.name FROM sqlite_master AS m JOIN pragma_table_info(m.name) AS p WHERE m.type = 'table'";
+
+/// A database the ingest writes, each with its own `schema_version` table.
+///
+/// Every migration under `migrations/` ends by recording the version it brings the database to,
+/// so a store can be checked without knowing which migrator wrote it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Store {
+    Docstore,
+    Index,
+}
+
+impl Display for Store {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Store::Docstore => write!(f, "docstore"),
+            Store::Index => write!(f, "index database"),
+        }
+    }
+}
+
+impl Store {
+    /// The schema version this build reads and writes.
+    pub(crate) fn version(&self) -> i64 {
+        match self {
+            Store::Docstore => 4,
+            Store::Index => 2,
+        }
+    }
+
+    /// Fails unless `found`, the version a store records, is the one this build reads.
+    pub(crate) fn check(&self, found: Option<i64>) -> Result<(), SchemaVersionError> {
+        match found {
+            None => Err(SchemaVersionError::Unversioned(*self)),
+            Some(found) if found != self.version() => Err(SchemaVersionError::Incompatible {
+                store: *self,
+                found,
+                expected: self.version(),
+            }),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Checks the version recorded in the SQLite database at `url`.
+    #[cfg(feature = "sqlite")]
+    pub(crate) async fn verify(&self, url: &Url) -> Result<(), SchemaVersionError> {
+        let pool = SqlitePool::connect(url.as_str()).await?;
+        let found = sqlite_version(&pool).await;
+        pool.close().await;
+        self.check(found?)
+    }
+
+    #[cfg(feature = "sqlite")]
+    fn sqlite_migrator(&self) -> &'static Migrator {
+        match self {
+            Store::Docstore => &SQLITE_DOCSTORE,
+            Store::Index => &SQLITE_INDEX,
+        }
+    }
+
+    /// Applies the migrations a SQLite store written by an earlier version is missing, or creates
+    /// the schema in an empty database.
+    ///
+    /// A store written before versions were recorded is upgraded when its tables have the columns
+    /// the first migration creates them with: that migration only creates the tables it is
+    /// missing and records version 1. Refuses any other store with tables but no recorded
+    /// version, whose shape the migrations can not know, and a store written by a newer version.
+    #[cfg(feature = "sqlite")]
+    pub(crate) async fn migrate_sqlite(&self, pool: &SqlitePool) -> Result<(), SchemaVersionError> {
+        match sqlite_version(pool).await? {
+            None => {
+                let tables = sqlx::query_scalar::<_, i64>(
+                    "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'",
+                )
+                .fetch_one(pool)
+                .await?;
+                if tables > 0 && !self.has_initial_columns(pool).await? {
+                    return Err(SchemaVersionError::Unversioned(*self));
+                }
+            }
+            Some(found) if found > self.version() => {
+                return Err(SchemaVersionError::Incompatible {
+                    store: *self,
+                    found,
+                    expected: self.version(),
+                })
+            }
+            Some(_) => {}
+        }
+        self.sqlite_migrator().run(pool).await?;
+        Ok(())
+    }
+
+    /// Whether every table of the store that the first migration creates has all the columns it
+    /// creates it with.
+    #[cfg(feature = "sqlite")]
+    async fn has_initial_columns(&self, pool: &SqlitePool) -> Result<bool, SchemaVersionError> {
+        let Some(initial) = self.sqlite_migrator().iter().next() else {
+            return Ok(false);
+        };
+        let mut reference = SqliteConnection::connect("sqlite::memory:").await?;
+        reference.execute(&*initial.sql).await?;
+        let expected = sqlx::query_as::<_, (String, String)>(SQLITE_COLUMNS_QUERY)
+            .fetch_all(&mut reference)
+            .await?;
+        reference.close().await?;
+        let found = sqlx::query_as::<_, (String, String)>(SQLITE_COLUMNS_QUERY)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let tables = found
+            .iter()
+            .map(|(table, _)| table.as_str())
+            .collect::<HashSet<_>>();
+        Ok(expected
+            .iter()
+            .filter(|(table, _)| tables.contains(table.as_str()))
+            .all(|column| found.contains(column)))
+    }
+}
+
+/// Applies the migrations a Postgres docstore copied by an earlier version is missing, or creates
+/// the schema in an empty database.
+///
+/// Refuses a database with tables but no recorded version, and one written by a newer version.
+#[cfg(any(feature = "postgres", feature = "migrate"))]
+pub(crate) async fn migrate_postgres_docstore(pool: &PgPool) -> Result<(), SchemaVersionError> {
+    let store = Store::Docstore;
+    match postgres_version(pool).await? {
+        None => {
+            let tables = sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = current_schema()",
+            )
+            .fetch_one(pool)
+            .await?;
+            if tables > 0 {
+                return Err(SchemaVersionError::Unversioned(store));
+            }
+        }
+        Some(found) if found > store.version() => {
+            return Err(SchemaVersionError::Incompatible {
+                store,
+                found,
+                expected: store.version(),
+            })
+        }
+        Some(_) => {}
+    }
+    POSTGRES_DOCSTORE.run(pool).await?;
+    Ok(())
+}
+
+/// The version recorded in `schema_version`, `None` when the table is missing.
+#[cfg(feature = "sqlite")]
+pub(crate) async fn sqlite_version(pool: &SqlitePool) -> Result<Option<i64>, sqlx::Error> {
+    let recorded = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+    )
+    .fetch_one(pool)
+    .await?
+        > 0;
+    if !recorded {
+        return Ok(None);
+    }
+    sqlx::query_scalar::<_, Option<i64>>(VERSION_QUERY)
+        .fetch_one(pool)
+        .await
+}
+
+/// The version recorded in `schema_version`, `None` when the table is missing.
+#[cfg(any(feature = "postgres", feature = "migrate"))]
+pub(crate) async fn postgres_version(pool: &PgPool) -> Result<Option<i64>, sqlx::Error> {
+    let recorded =
+        sqlx::query_scalar::<_, bool>("SELECT to_regclass('schema_version') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+    if !recorded {
+        return Ok(None);
+    }
+    sqlx::query_scalar::<_, Option<i64>>(VERSION_QUERY)
+        .fetch_one(pool)
+        .await
+}
+
+#[derive(Debug)]
+pub(crate) enum SchemaVersionError {
+    /// Written before stores recorded their schema version.
+    Unversioned(Store),
+    Incompatible {
+        store: Store,
+        found: i64,
+        expected: i64,
+    },
+    Database(sqlx::Error),
+    Migrate(MigrateError),
+}
+
+impl From<sqlx::Error> for SchemaVersionError {
+    fn from(value: sqlx::Error) -> Self {
+        Self::Database(value)
+    }
+}
+impl From<MigrateError> for SchemaVersionError {
+    fn from(value: MigrateError) -> Self {
+        Self::Migrate(value)
+    }
+}
+
+impl Error for SchemaVersionError {}
+impl Display for SchemaVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaVersionError::Unversioned(store) => write!(
+                f,
+                "SchemaVersionError: The {store} records no schema version, it was written by an older wikidex. An incremental ingest records it, as does `wikidex docstore recompress` for a docstore; ingest again if its tables differ from what that wikidex wrote."
+            ),
+            SchemaVersionError::Incompatible {
+                store,
+                found,
+                expected,
+            } if found < expected => write!(
+                f,
+                "SchemaVersionError: The {store} has schema version {found}, this wikidex reads version {expected}. Upgrade it with an incremental ingest, or migrate a SQLite docstore into Postgres again."
+            ),
+            SchemaVersionError::Incompatible {
+                store,
+                found,
+                expected,
+            } => write!(
+                f,
+                "SchemaVersionError: The {store} has schema version {found}, written by a newer wikidex than this one, which reads version {expected}."
+            ),
+            SchemaVersionError::Database(e) => write!(f, "SchemaVersionError: Database: {e}"),
+            SchemaVersionError::Migrate(e) => write!(f, "SchemaVersionError: Migrate: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SchemaVersionError, Store};
+
+    #[test]
+    fn check_version() {
+        assert!(Store::Docstore
+            .check(Some(Store::Docstore.version()))
+            .is_ok());
+        assert!(matches!(
+            Store::Index.check(None),
+            Err(SchemaVersionError::Unversioned(Store::Index))
+        ));
+        assert!(matches!(
+            Store::Docstore.check(Some(Store::Docstore.version() + 1)),
+            Err(SchemaVersionError::Incompatible { found, expected, .. }) if found == expected + 1
+        ));
+    }
+}
@@ -1,63 +1,160 @@
 use actix_web::{
+    get,
+    http::header::AUTHORIZATION,
     post,
-    web::{Data, Json},
-    HttpResponse, Responder,
+    web::{self, Data, Json, Path},
+    HttpRequest, HttpResponse, Responder,
 };
 
 use std::sync::Arc;
 use utoipa::OpenApi;
 
 use crate::{
+    formatter::{bibliography as write_bibliography, ReferenceFormat},
     inference::{Engine, QueryEngineError},
-    server::client::Client,
+    llm_client::PromptTemplateError,
+    server::{client::Client, metrics::Metrics},
 };
 
-use super::{Answer, Conversation, Message, PartialMessage, Query, Source};
+use super::{
+    Answer, Article, ArticleSearchParams, Bibliography, Conversation, ConversationResponse,
+    Corpora, CorpusParams, Filter, Message, PartialMessage, Passage, Query, Source, Templates,
+    Usage,
+};
+
+const DEFAULT_ARTICLE_LIMIT: usize = 10;
+const MAX_ARTICLE_LIMIT: usize = 100;
 
 #[derive(OpenApi)]
 #[openapi(
-    paths(conversation, streaming_conversation),
+    paths(
+        conversation,
+        conversation_v2,
+        streaming_conversation,
+        templates,
+        corpora,
+        passage,
+        article_passages,
+        articles,
+        bibliography,
+        metrics_report
+    ),
     components(
         schemas(Message),
         schemas(Source),
         schemas(PartialMessage),
         schemas(Conversation),
+        schemas(Filter),
+        schemas(ConversationResponse),
+        schemas(Usage),
+        schemas(Templates),
+        schemas(Corpora),
+        schemas(Passage),
+        schemas(Article),
+        schemas(Bibliography),
+        schemas(ReferenceFormat),
         schemas(Query),
         schemas(Answer)
     )
 )]
 pub(crate) struct ApiDoc;
 
+fn api_key(request: &HttpRequest) -> Option<String> {
+    request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .strip_prefix("Bearer ")
+                .unwrap_or(value)
+                .trim()
+                .to_string()
+        })
+}
+
 #[utoipa::path(
     request_body(content = Conversation, content_type = "application/json"),
     responses(
         (status = 200, description = "AI Response", body = Message, content_type = "application/json"),
         (status = 204, description = "No user input"),
-        (status = 400, description = "Empty Request")
+        (status = 400, description = "Empty Request, unknown template or unknown corpus")
     )
 )]
 #[post("/conversation")]
 async fn conversation(
+    request: HttpRequest,
+    Json(conversation): Json<Conversation>,
+    query_engine: Data<Arc<Engine>>,
+    metrics: Data<Arc<Metrics>>,
+) -> impl Responder {
+    match answer(&request, conversation, &query_engine, &metrics).await {
+        Ok(ConversationResponse { message, .. }) => HttpResponse::Ok().json(message),
+        Err(response) => response,
+    }
+}
+
+#[utoipa::path(
+    request_body(content = Conversation, content_type = "application/json"),
+    responses(
+        (status = 200, description = "AI Response with its sources and token usage", body = ConversationResponse, content_type = "application/json"),
+        (status = 204, description = "No user input"),
+        (status = 400, description = "Empty Request, unknown template or unknown corpus")
+    )
+)]
+#[post("/v2/conversation")]
+async fn conversation_v2(
+    request: HttpRequest,
     Json(conversation): Json<Conversation>,
     query_engine: Data<Arc<Engine>>,
+    metrics: Data<Arc<Metrics>>,
 ) -> impl Responder {
+    match answer(&request, conversation, &query_engine, &metrics).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(response) => response,
+    }
+}
+
+async fn answer(
+    request: &HttpRequest,
+    conversation: Conversation,
+    query_engine: &Engine,
+    metrics: &Metrics,
+) -> Result<ConversationResponse, HttpResponse> {
     match query_engine
         .conversation(conversation, vec!["References:".to_string()])
         .await
     {
-        Ok(message) => HttpResponse::Ok().json(message),
+        Ok((message, source_map, usage, conversation_id)) => {
+            metrics.record_usage(api_key(request).as_deref(), &usage);
+            Ok(ConversationResponse {
+                message,
+                source_map,
+                usage,
+                conversation_id,
+            })
+        }
         Err(e) => {
             log::error!("{e}");
+            if let QueryEngineError::LlmError(_) = e {
+                metrics.record_usage(api_key(request).as_deref(), &Usage::failed());
+            }
             match e {
-                QueryEngineError::LastMessageIsNotUser | QueryEngineError::EmptyConversation => {
-                    HttpResponse::BadRequest().into()
+                QueryEngineError::LastMessageIsNotUser
+                | QueryEngineError::EmptyConversation
+                | QueryEngineError::TooManySources(_)
+                | QueryEngineError::UnknownConversation(_)
+                | QueryEngineError::UnknownCorpus(_)
+                | QueryEngineError::Template(PromptTemplateError::UnknownTemplate(_)) => {
+                    Err(HttpResponse::BadRequest().into())
                 }
                 QueryEngineError::InvalidAgentResponse
+                | QueryEngineError::Template(_)
                 | QueryEngineError::LlmError(_)
                 | QueryEngineError::IndexError(_)
                 | QueryEngineError::DocstoreError(_)
                 | QueryEngineError::EmbeddingServiceError(_)
-                | QueryEngineError::Tera(_) => HttpResponse::InternalServerError().into(),
+                | QueryEngineError::Tera(_) => Err(HttpResponse::InternalServerError().into()),
             }
         }
     }
@@ -66,22 +163,42 @@ async fn conversation(
 #[utoipa::path(
     request_body(content = Conversation, content_type = "application/json"),
     responses(
-        (status = 200, description = "AI Response", body = PartialMessage, content_type = "application/json"),
+        (status = 200, description = "The retrieved sources as a `message` event, the AI Response as `message` events, followed by a `usage` event, whose finish reason is `error` when generation failed", body = PartialMessage, content_type = "application/json"),
         (status = 204, description = "No user input"),
-        (status = 400, description = "Empty Request")
+        (status = 400, description = "Empty Request, unknown template or unknown corpus")
     )
 )]
 #[post("/streaming_conversation")]
 async fn streaming_conversation(
+    request: HttpRequest,
     Json(conversation_1): Json<Conversation>,
     query_engine: Data<Arc<Engine>>,
+    metrics: Data<Arc<Metrics>>,
 ) -> impl Responder {
+    if let Err(e) = query_engine.check_templates(&conversation_1).await {
+        log::error!("{e}");
+        return HttpResponse::BadRequest().finish();
+    }
+    if let Err(e) = query_engine.check_corpora(&conversation_1) {
+        log::error!("{e}");
+        return HttpResponse::BadRequest().finish();
+    }
+
     let (client, sender) = Client::new();
+    let api_key = api_key(&request);
     tokio::spawn(async move {
-        let _ = query_engine
+        match query_engine
             .streaming_conversation(conversation_1, sender, vec!["References".to_string()])
             .await
-            .map_err(|e| log::error!("{e}"));
+        {
+            Ok(usage) => metrics.record_usage(api_key.as_deref(), &usage),
+            Err(e) => {
+                log::error!("{e}");
+                if let QueryEngineError::LlmError(_) = e {
+                    metrics.record_usage(api_key.as_deref(), &Usage::failed());
+                }
+            }
+        }
     });
 
     HttpResponse::Ok()
@@ -90,3 +207,161 @@ async fn streaming_conversation(
         .append_header(("cache-control", "no-cache"))
         .streaming(client)
 }
+
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Prompt templates selectable per conversation", body = Templates, content_type = "application/json")
+    )
+)]
+#[get("/templates")]
+async fn templates(query_engine: Data<Arc<Engine>>) -> impl Responder {
+    HttpResponse::Ok().json(query_engine.templates().await)
+}
+
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Corpora selectable per conversation", body = Corpora, content_type = "application/json")
+    )
+)]
+#[get("/corpora")]
+async fn corpora(query_engine: Data<Arc<Engine>>) -> impl Responder {
+    HttpResponse::Ok().json(query_engine.corpora())
+}
+
+#[utoipa::path(
+    params(("id" = i64, Path, description = "The id of the passage"), CorpusParams),
+    responses(
+        (status = 200, description = "The passage", body = Passage, content_type = "application/json"),
+        (status = 400, description = "Unknown corpus"),
+        (status = 404, description = "No passage with this id")
+    )
+)]
+#[get("/documents/{id}")]
+async fn passage(
+    id: Path<i64>,
+    params: web::Query<CorpusParams>,
+    query_engine: Data<Arc<Engine>>,
+) -> impl Responder {
+    match query_engine
+        .passage(params.corpus.as_ref(), id.into_inner())
+        .await
+    {
+        Ok(Some(passage)) => HttpResponse::Ok().json(passage),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => lookup_error(e),
+    }
+}
+
+#[utoipa::path(
+    params(("id" = i64, Path, description = "The id of the article"), CorpusParams),
+    responses(
+        (status = 200, description = "The passages of the article in section order", body = [Passage], content_type = "application/json"),
+        (status = 400, description = "Unknown corpus"),
+        (status = 404, description = "No article with this id")
+    )
+)]
+#[get("/articles/{id}/documents")]
+async fn article_passages(
+    id: Path<i64>,
+    params: web::Query<CorpusParams>,
+    query_engine: Data<Arc<Engine>>,
+) -> impl Responder {
+    match query_engine
+        .article_passages(params.corpus.as_ref(), id.into_inner())
+        .await
+    {
+        Ok(passages) if passages.is_empty() => HttpResponse::NotFound().finish(),
+        Ok(passages) => HttpResponse::Ok().json(passages),
+        Err(e) => lookup_error(e),
+    }
+}
+
+#[utoipa::path(
+    params(ArticleSearchParams),
+    responses(
+        (status = 200, description = "Articles with the title, or with titles containing its words when fuzzy", body = [Article], content_type = "application/json"),
+        (status = 400, description = "Empty title or unknown corpus")
+    )
+)]
+#[get("/articles")]
+async fn articles(
+    params: web::Query<ArticleSearchParams>,
+    query_engine: Data<Arc<Engine>>,
+) -> impl Responder {
+    let title = params.title.trim();
+    if title.is_empty() {
+        return HttpResponse::BadRequest().finish();
+    }
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_ARTICLE_LIMIT)
+        .clamp(1, MAX_ARTICLE_LIMIT);
+    match query_engine
+        .find_articles(params.corpus.as_ref(), title, params.fuzzy, limit)
+        .await
+    {
+        Ok(articles) => HttpResponse::Ok().json(articles),
+        Err(e) => lookup_error(e),
+    }
+}
+
+#[utoipa::path(
+    request_body = Bibliography,
+    responses(
+        (status = 200, description = "One reference per cited article in BibTeX, CSL-JSON or RIS", body = String, content_type = ["application/x-bibtex", "application/vnd.citationstyles.csl+json", "application/x-research-info-systems"]),
+        (status = 400, description = "No sources, more than 1000 sources or unknown corpus"),
+        (status = 404, description = "Unknown or forgotten conversation")
+    )
+)]
+#[post("/bibliography")]
+async fn bibliography(
+    Json(request): Json<Bibliography>,
+    query_engine: Data<Arc<Engine>>,
+) -> impl Responder {
+    if request.sources.is_empty() && request.conversation_id.is_none() {
+        return HttpResponse::BadRequest().finish();
+    }
+    let format = request.format;
+    match query_engine
+        .bibliography(
+            request.corpus.as_ref(),
+            &request.sources,
+            request.conversation_id.as_deref(),
+        )
+        .await
+    {
+        Ok(provenances) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .body(write_bibliography(&provenances, format)),
+        Err(e) => lookup_error(e),
+    }
+}
+
+fn lookup_error(e: QueryEngineError) -> HttpResponse {
+    log::error!("{e}");
+    match e {
+        QueryEngineError::UnknownCorpus(_) | QueryEngineError::TooManySources(_) => {
+            HttpResponse::BadRequest().finish()
+        }
+        QueryEngineError::UnknownConversation(_) => HttpResponse::NotFound().finish(),
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Token usage per API key, query embedding cache and document cache hits in the Prometheus text format", body = String, content_type = "text/plain")
+    )
+)]
+#[get("/metrics")]
+async fn metrics_report(
+    metrics: Data<Arc<Metrics>>,
+    query_engine: Data<Arc<Engine>>,
+) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.render(
+            &query_engine.embedding_cache_stats(),
+            &query_engine.document_cache_stats(),
+        ))
+}
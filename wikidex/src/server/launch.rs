@@ -8,7 +8,10 @@ use utoipa_swagger_ui::SwaggerUi;
 
 use crate::inference::Engine;
 
-use super::{conversation, streaming_conversation, ApiDoc};
+use super::{
+    article_passages, articles, bibliography, conversation, conversation_v2, corpora,
+    metrics::Metrics, metrics_report, passage, streaming_conversation, templates, ApiDoc,
+};
 
 pub(crate) fn run_server<S: AsRef<str>>(
     engine: Engine,
@@ -18,17 +21,27 @@ pub(crate) fn run_server<S: AsRef<str>>(
     let openapi = ApiDoc::openapi();
 
     let engine = Arc::new(engine);
+    let metrics = Arc::new(Metrics::default());
 
     let mut server = HttpServer::new(move || {
         App::new()
             .wrap(middleware::Logger::default())
             .wrap(Cors::permissive())
             .app_data(Data::new(engine.clone()))
+            .app_data(Data::new(metrics.clone()))
             .service(
                 SwaggerUi::new("/swagger-ui/{_:.*}").url("/api-docs/openapi.json", openapi.clone()),
             )
             .service(streaming_conversation)
             .service(conversation)
+            .service(conversation_v2)
+            .service(templates)
+            .service(corpora)
+            .service(passage)
+            .service(article_passages)
+            .service(articles)
+            .service(bibliography)
+            .service(metrics_report)
             .service(Redoc::with_url("/api-doc", openapi.clone()))
     });
 
@@ -0,0 +1,182 @@
+use std::{collections::HashMap, fmt::Write, sync::Mutex};
+
+use sha2::{Digest, Sha256};
+
+use crate::{docstore::DocumentCacheStats, embedding_client::EmbeddingCacheStats};
+
+use super::Usage;
+
+const ANONYMOUS: &str = "anonymous";
+
+#[derive(Default, Clone, Copy)]
+struct TokenCount {
+    requests: u64,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    unreported: u64,
+}
+
+/// Token usage per API key and finish reason, rendered in the Prometheus text format.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    usage: Mutex<HashMap<(String, String), TokenCount>>,
+}
+
+impl Metrics {
+    pub(crate) fn record_usage(&self, api_key: Option<&str>, usage: &Usage) {
+        let key = (mask_api_key(api_key), usage.finish_reason.to_string());
+        if let Ok(mut map) = self.usage.lock() {
+            let count = map.entry(key).or_default();
+            count.requests += 1;
+            count.prompt_tokens += usage.prompt_tokens.unwrap_or_default() as u64;
+            count.completion_tokens += usage.completion_tokens.unwrap_or_default() as u64;
+            if usage.completion_tokens.is_none() {
+                count.unreported += 1;
+            }
+        }
+    }
+
+    pub(crate) fn render(
+        &self,
+        embedding_cache: &EmbeddingCacheStats,
+        document_cache: &DocumentCacheStats,
+    ) -> String {
+        let usage = match self.usage.lock() {
+            Ok(map) => map
+                .iter()
+                .map(|(key, count)| (key.clone(), *count))
+                .collect::<Vec<_>>(),
+            Err(_) => vec![],
+        };
+
+        let mut output = String::new();
+        for (name, help, value) in [
+            (
+                "wikidex_llm_requests_total",
+                "LLM generations, including those that failed.",
+                (|c: &TokenCount| c.requests) as fn(&TokenCount) -> u64,
+            ),
+            (
+                "wikidex_llm_prompt_tokens_total",
+                "Prompt tokens reported by the LLM backend.",
+                |c: &TokenCount| c.prompt_tokens,
+            ),
+            (
+                "wikidex_llm_completion_tokens_total",
+                "Completion tokens generated by the LLM backend.",
+                |c: &TokenCount| c.completion_tokens,
+            ),
+            (
+                "wikidex_llm_requests_without_usage_total",
+                "LLM generations whose backend reported no token counts.",
+                |c: &TokenCount| c.unreported,
+            ),
+        ] {
+            let _ = writeln!(output, "# HELP {name} {help}");
+            let _ = writeln!(output, "# TYPE {name} counter");
+            for ((api_key, finish_reason), count) in usage.iter() {
+                let _ = writeln!(
+                    output,
+                    "{name}{{api_key=\"{api_key}\",finish_reason=\"{finish_reason}\"}} {}",
+                    value(count)
+                );
+            }
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP wikidex_embedding_cache_hits_total Query embeddings served from the cache."
+        );
+        let _ = writeln!(output, "# TYPE wikidex_embedding_cache_hits_total counter");
+        for (tier, hits) in [
+            ("memory", embedding_cache.memory_hits),
+            ("redis", embedding_cache.redis_hits),
+        ] {
+            let _ = writeln!(
+                output,
+                "wikidex_embedding_cache_hits_total{{tier=\"{tier}\"}} {hits}"
+            );
+        }
+        let _ = writeln!(
+            output,
+            "# HELP wikidex_embedding_cache_misses_total Query embeddings computed by the embedding service."
+        );
+        let _ = writeln!(
+            output,
+            "# TYPE wikidex_embedding_cache_misses_total counter"
+        );
+        let _ = writeln!(
+            output,
+            "wikidex_embedding_cache_misses_total {}",
+            embedding_cache.misses
+        );
+
+        let backend = document_cache.kind;
+        for (name, kind, help, value) in [
+            (
+                "wikidex_document_cache_hits_total",
+                "counter",
+                "Documents served from the document cache.",
+                document_cache.hits,
+            ),
+            (
+                "wikidex_document_cache_misses_total",
+                "counter",
+                "Documents read from the docstore.",
+                document_cache.misses,
+            ),
+            (
+                "wikidex_document_cache_evictions_total",
+                "counter",
+                "Documents evicted from the in-process document cache to stay within its size.",
+                document_cache.evictions,
+            ),
+            (
+                "wikidex_document_cache_entries",
+                "gauge",
+                "Documents in the in-process document cache.",
+                document_cache.entries,
+            ),
+            (
+                "wikidex_document_cache_bytes",
+                "gauge",
+                "Bytes of documents in the in-process document cache.",
+                document_cache.bytes,
+            ),
+        ] {
+            let _ = writeln!(output, "# HELP {name} {help}");
+            let _ = writeln!(output, "# TYPE {name} {kind}");
+            let _ = writeln!(output, "{name}{{backend=\"{backend}\"}} {value}");
+        }
+        output
+    }
+}
+
+/// Keys are labelled with the first twelve hex digits of their SHA-256, stable and distinct per
+/// key, so usage is attributed to each key without exposing the secret on the metrics endpoint.
+fn mask_api_key(api_key: Option<&str>) -> String {
+    match api_key {
+        Some(key) if !key.is_empty() => {
+            let digest = format!("{:x}", Sha256::digest(key));
+            format!("sha256:{}", &digest[..12])
+        }
+        _ => ANONYMOUS.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{mask_api_key, ANONYMOUS};
+
+    #[test]
+    fn keys_sharing_a_suffix_are_distinct() {
+        let a = mask_api_key(Some("team-a-secret-1234"));
+        let b = mask_api_key(Some("team-b-secret-1234"));
+        assert_ne!(a, b);
+        assert_eq!(a, mask_api_key(Some("team-a-secret-1234")));
+        assert_eq!(a.len(), "sha256:".len() + 12);
+        assert!(!a.contains("1234"));
+        assert_eq!(mask_api_key(None), ANONYMOUS);
+        assert_eq!(mask_api_key(Some("")), ANONYMOUS);
+    }
+}
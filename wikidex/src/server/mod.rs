@@ -1,10 +1,13 @@
 mod api;
 mod client;
 mod launch;
+mod metrics;
 mod protocol;
 
 pub(crate) use api::*;
 pub(crate) use launch::run_server;
 pub(super) use protocol::{
-    Answer, Conversation, Message, PartialMessage, Query, Source,
+    Answer, Article, ArticleSearchParams, Bibliography, Conversation, ConversationResponse,
+    Corpora, CorpusParams, Filter, Message, PartialMessage, Passage, Query, Source, SourceKey,
+    Templates, Usage,
 };
@@ -1,20 +1,85 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
 use bytes::Bytes;
-use chrono::DateTime;
-use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use chrono::{DateTime, NaiveDate};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::formatter::{Cite, Provenance};
+use crate::{
+    docstore::DocumentFilter,
+    formatter::{Cite, Provenance, ReferenceFormat, Wiki},
+    llm_client::{LlmUsage, TemplateNames},
+};
+
+/// Identifies a source among the corpora of a conversation, written `corpus:index`. Document ids
+/// are only unique within their corpus.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct SourceKey {
+    pub(crate) corpus: String,
+    pub(crate) index: i64,
+}
+
+impl Display for SourceKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.corpus, self.index)
+    }
+}
+
+impl FromStr for SourceKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (corpus, index) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("source key {s} is not `corpus:index`"))?;
+        let index = index
+            .parse()
+            .map_err(|_| format!("source key {s} has no numeric index"))?;
+        Ok(Self {
+            corpus: corpus.to_string(),
+            index,
+        })
+    }
+}
+
+impl Serialize for SourceKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SourceKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
 
 // type Source = (String, String, String, String);
 #[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
 #[schema(example = assistant_message_schema_example)]
 pub(crate) struct Source {
     pub(crate) index: i64,
+    /// The article the source was split from, whose passages `/articles/{id}/documents` lists.
+    pub(crate) article: i64,
     pub(crate) citation: String,
+    /// Links to the section the source was taken from when the corpus has public links.
     pub(crate) url: String,
+    /// Links to the revision of the article the source was taken from, empty when the corpus has
+    /// no public links.
+    pub(crate) revision_url: String,
+    /// The article title followed by the path of section headings, separated by `:`.
+    pub(crate) heading: String,
+    /// The position of the section in its article, the lead section is 0.
+    pub(crate) section_ordinal: i64,
     pub(crate) origin_text: String,
+    /// Similarity to the query reported by the index, higher is more similar. Scores of corpora
+    /// embedded with the same model are comparable. Null when the index reports only the order
+    /// of its results.
+    pub(crate) score: Option<f32>,
+    /// The corpus the source was retrieved from.
+    pub(crate) corpus: String,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Debug)]
@@ -22,14 +87,16 @@ pub(crate) struct Source {
 pub(crate) enum Message {
     User(String),
     Assistant(String),
-    SourceMap(HashMap<i64, Source>),
+    SourceMap(HashMap<SourceKey, Source>),
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Debug)]
 #[schema(example = assistant_partial_message_schema_example)]
 pub(crate) struct PartialMessage {
     pub(crate) content: Option<String>,
-    pub(crate) source_map: Option<HashMap<i64, Source>>,
+    pub(crate) source_map: Option<HashMap<SourceKey, Source>>,
+    /// The id of the stored conversation, sent along with the sources.
+    pub(crate) conversation_id: Option<String>,
     pub(crate) finished: Option<String>,
 }
 
@@ -38,14 +105,16 @@ impl PartialMessage {
         Self {
             content: None,
             source_map: None,
+            conversation_id: None,
             finished: Some(String::from("DONE")),
         }
     }
 
-    pub(crate) fn source(source: HashMap<i64, Source>) -> Self {
+    pub(crate) fn source(source: HashMap<SourceKey, Source>, conversation_id: String) -> Self {
         Self {
             content: None,
             source_map: Some(source),
+            conversation_id: Some(conversation_id),
             finished: None,
         }
     }
@@ -54,6 +123,7 @@ impl PartialMessage {
         Self {
             content: Some(content),
             source_map: None,
+            conversation_id: None,
             finished: None,
         }
     }
@@ -65,10 +135,199 @@ impl PartialMessage {
     }
 }
 
+/// Token counts the LLM backend did not report are null. A generation that failed part way has
+/// the finish reason `error`.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+#[schema(example = usage_schema_example)]
+pub(crate) struct Usage {
+    pub(crate) prompt_tokens: Option<u32>,
+    pub(crate) completion_tokens: Option<u32>,
+    pub(crate) total_tokens: Option<u32>,
+    pub(crate) finish_reason: String,
+}
+
+impl Usage {
+    pub(crate) fn failed() -> Self {
+        Self {
+            prompt_tokens: None,
+            completion_tokens: None,
+            total_tokens: None,
+            finish_reason: String::from("error"),
+        }
+    }
+
+    pub(crate) fn message(&self) -> Bytes {
+        let message_string = &serde_json::to_string(&self).unwrap();
+
+        Bytes::from(["event: usage\ndata: ", message_string, "\n\n"].concat())
+    }
+}
+
+impl From<LlmUsage> for Usage {
+    fn from(value: LlmUsage) -> Self {
+        Self {
+            prompt_tokens: value.prompt_tokens,
+            completion_tokens: value.completion_tokens,
+            total_tokens: value
+                .prompt_tokens
+                .zip(value.completion_tokens)
+                .map(|(prompt_tokens, completion_tokens)| prompt_tokens + completion_tokens),
+            finish_reason: value.finish_reason.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+#[schema(example = conversation_response_schema_example)]
+pub(crate) struct ConversationResponse {
+    pub(crate) message: Message,
+    pub(crate) source_map: HashMap<SourceKey, Source>,
+    pub(crate) usage: Usage,
+    /// The id of the stored conversation, which `/bibliography` cites and a later turn continues.
+    pub(crate) conversation_id: String,
+}
+
 #[derive(Serialize, Deserialize, ToSchema, Debug)]
 #[schema(example = conversation_schema_example)]
 pub(crate) struct Conversation {
+    /// A conversation stored by an earlier answer, which this answer's sources are added to. A
+    /// new conversation is stored when absent, unknown or forgotten.
+    #[serde(default)]
+    pub(crate) id: Option<String>,
     pub(crate) messages: Vec<Message>,
+    /// System prompt template listed by `/templates`, the server default when absent.
+    #[serde(default)]
+    pub(crate) system_template: Option<String>,
+    /// Chat format template listed by `/templates`, the server default when absent.
+    #[serde(default)]
+    pub(crate) chat_template: Option<String>,
+    /// Restricts the documents retrieved for the answer.
+    #[serde(default)]
+    pub(crate) filter: Option<Filter>,
+    /// Corpora listed by `/corpora` to search, the server default when absent.
+    #[serde(default)]
+    pub(crate) corpora: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Default)]
+#[schema(example = filter_schema_example)]
+pub(crate) struct Filter {
+    #[serde(default)]
+    pub(crate) article_ids: Option<Vec<i64>>,
+    /// Earliest modification date of the article, inclusive.
+    #[serde(default)]
+    #[schema(value_type = Option<String>, format = Date)]
+    pub(crate) modified_since: Option<NaiveDate>,
+    /// Latest modification date of the article, inclusive.
+    #[serde(default)]
+    #[schema(value_type = Option<String>, format = Date)]
+    pub(crate) modified_until: Option<NaiveDate>,
+    /// A Wikipedia category, with or without the `Category:` prefix.
+    #[serde(default)]
+    pub(crate) category: Option<String>,
+}
+
+impl From<Filter> for DocumentFilter {
+    fn from(value: Filter) -> Self {
+        Self {
+            article_ids: value.article_ids,
+            modified_since: value.modified_since,
+            modified_until: value.modified_until,
+            category: value.category,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+#[schema(example = templates_schema_example)]
+pub(crate) struct Templates {
+    pub(crate) system: Vec<String>,
+    pub(crate) chat: Vec<String>,
+    pub(crate) default_system: String,
+    pub(crate) default_chat: String,
+}
+
+impl From<TemplateNames> for Templates {
+    fn from(value: TemplateNames) -> Self {
+        Self {
+            system: value.system,
+            chat: value.chat,
+            default_system: value.default_system,
+            default_chat: value.default_chat,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+#[schema(example = corpora_schema_example)]
+pub(crate) struct Corpora {
+    pub(crate) corpora: Vec<String>,
+    pub(crate) default: String,
+}
+
+/// A passage of a corpus, looked up by its id or its article.
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+#[schema(example = passage_schema_example)]
+pub(crate) struct Passage {
+    pub(crate) index: i64,
+    pub(crate) article: i64,
+    pub(crate) citation: String,
+    pub(crate) url: String,
+    pub(crate) revision_url: String,
+    /// The article title followed by the path of section headings, separated by `:`.
+    pub(crate) heading: String,
+    /// The position of the section in its article, the lead section is 0.
+    pub(crate) section_ordinal: i64,
+    pub(crate) text: String,
+    pub(crate) corpus: String,
+}
+
+/// An article of a corpus, found by its title.
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+#[schema(example = article_schema_example)]
+pub(crate) struct Article {
+    pub(crate) id: i64,
+    pub(crate) title: String,
+    pub(crate) citation: String,
+    pub(crate) url: String,
+    pub(crate) revision_url: String,
+    pub(crate) corpus: String,
+}
+
+/// The sources a bibliography cites, by their ids, from a stored conversation, or both.
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+#[schema(example = bibliography_schema_example)]
+pub(crate) struct Bibliography {
+    pub(crate) format: ReferenceFormat,
+    /// Source ids, the `index` of a source, of `corpus`.
+    #[serde(default)]
+    pub(crate) sources: Vec<i64>,
+    /// The corpus of `sources`, the default corpus when absent.
+    #[serde(default)]
+    pub(crate) corpus: Option<String>,
+    /// A conversation stored by the server, whose sources are cited from the corpus each was
+    /// retrieved from.
+    #[serde(default)]
+    pub(crate) conversation_id: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams, Debug)]
+pub(crate) struct CorpusParams {
+    /// The corpus to look in, the default corpus when omitted.
+    pub(crate) corpus: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams, Debug)]
+pub(crate) struct ArticleSearchParams {
+    pub(crate) title: String,
+    /// Matches titles containing the words of `title` in order regardless of case, instead of
+    /// the exact title.
+    #[serde(default)]
+    pub(crate) fuzzy: bool,
+    /// At most this many articles, 10 when omitted and never more than 100.
+    pub(crate) limit: Option<usize>,
+    /// The corpus to look in, the default corpus when omitted.
+    pub(crate) corpus: Option<String>,
 }
 
 pub(crate) trait CountSources {
@@ -95,14 +354,19 @@ fn assistant_partial_message_schema_example() -> PartialMessage {
     PartialMessage {
         content: Some(String::from(" fragment")),
         source_map: Some(source_map_example()),
+        conversation_id: Some(String::from("6f1c2a9e0b7d4c3f8e5a1b2c3d4e5f60")),
         finished: Some(String::new()),
     }
 }
 
-fn source_map_example() -> HashMap<i64, Source> {
+fn source_map_example() -> HashMap<SourceKey, Source> {
     let source = source_schema_example();
     let mut source_map = HashMap::new();
-    let _ = source_map.insert(source.index, source);
+    let key = SourceKey {
+        corpus: source.corpus.clone(),
+        index: source.index,
+    };
+    let _ = source_map.insert(key, source);
     source_map
 }
 fn source_schema_example() -> Source {
@@ -110,16 +374,58 @@ fn source_schema_example() -> Source {
         "Bogonam-Foulbé".to_string(),
         DateTime::from_timestamp_millis(0).unwrap().date_naive(),
         DateTime::from_timestamp_millis(0).unwrap().date_naive(),
+        1178374855,
+        Wiki::default(),
     );
     p.format(&crate::formatter::CitationStyle::Mla);
     Source {
         index: 987087,
+        article: 61234,
         citation: p.format(&crate::formatter::CitationStyle::Mla),
         url: p.url(),
+        revision_url: p.revision_url(),
+        heading: p.title(),
+        section_ordinal: 0,
         origin_text: p.title(),
+        score: Some(0.87),
+        corpus: String::from("wikipedia"),
     }
 }
 
+fn passage_schema_example() -> Passage {
+    let source = source_schema_example();
+    Passage {
+        index: source.index,
+        article: source.article,
+        citation: source.citation,
+        url: source.url,
+        revision_url: source.revision_url,
+        heading: source.heading,
+        section_ordinal: source.section_ordinal,
+        text: source.origin_text,
+        corpus: source.corpus,
+    }
+}
+fn article_schema_example() -> Article {
+    let source = source_schema_example();
+    Article {
+        id: source.article,
+        title: source.heading,
+        citation: source.citation,
+        url: source.url,
+        revision_url: source.revision_url,
+        corpus: source.corpus,
+    }
+}
+
+fn bibliography_schema_example() -> Bibliography {
+    Bibliography {
+        format: ReferenceFormat::Bibtex,
+        sources: vec![1],
+        corpus: None,
+        conversation_id: None,
+    }
+}
 fn user_message_schema_example() -> Message {
     Message::User(String::from("String"))
 }
@@ -136,11 +442,73 @@ fn answer_schema_example() -> Answer {
         message: String::from("String"),
     }
 }
+fn usage_schema_example() -> Usage {
+    Usage {
+        prompt_tokens: Some(1024),
+        completion_tokens: Some(256),
+        total_tokens: Some(1280),
+        finish_reason: String::from("stop"),
+    }
+}
+fn conversation_response_schema_example() -> ConversationResponse {
+    ConversationResponse {
+        message: assistant_message_schema_example(),
+        source_map: source_map_example(),
+        usage: usage_schema_example(),
+        conversation_id: String::from("6f1c2a9e0b7d4c3f8e5a1b2c3d4e5f60"),
+    }
+}
 fn conversation_schema_example() -> Conversation {
     Conversation {
+        id: None,
         messages: vec![
             user_message_schema_example(),
             assistant_message_schema_example(),
         ],
+        system_template: Some(String::from("concise")),
+        chat_template: None,
+        filter: Some(filter_schema_example()),
+        corpora: Some(vec![String::from("wikipedia"), String::from("handbook")]),
+    }
+}
+fn filter_schema_example() -> Filter {
+    Filter {
+        modified_since: NaiveDate::from_ymd_opt(2023, 1, 1),
+        category: Some(String::from("Living people")),
+        ..Default::default()
+    }
+}
+fn templates_schema_example() -> Templates {
+    Templates {
+        system: vec![
+            String::from("concise"),
+            String::from("eli5"),
+            String::from("markdown"),
+        ],
+        chat: vec![String::from("chatml"), String::from("mistral")],
+        default_system: String::from("markdown"),
+        default_chat: String::from("mistral"),
+    }
+}
+fn corpora_schema_example() -> Corpora {
+    Corpora {
+        corpora: vec![String::from("wikipedia"), String::from("handbook")],
+        default: String::from("wikipedia"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SourceKey;
+
+    #[test]
+    fn source_key_round_trips_through_its_string() {
+        let key = SourceKey {
+            corpus: String::from("wiki:de"),
+            index: 42,
+        };
+        assert_eq!(key.to_string(), "wiki:de:42");
+        assert_eq!("wiki:de:42".parse::<SourceKey>(), Ok(key));
+        assert!("42".parse::<SourceKey>().is_err());
     }
 }
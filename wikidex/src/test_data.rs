@@ -711,7 +711,7 @@ Under the [[Judiciary Act of 1789]] the federal court structure at the time comp
 {{DEFAULTSORT:United States Supreme Court cases by volume}}
 [[Category:1889 in United States case law]]"#;
 
-pub(crate) const SUPREME_COURT_VOL_129_PARSE_RESULT: &str = r#"This is a list of cases reported in volume 129 of United States Reports, decided by the Supreme Court of the United States in 1889.###HEADING_START###:Justices of the Supreme Court at the time of volume 129 U.S.###HEADING_END###
+pub(crate) const SUPREME_COURT_VOL_129_PARSE_RESULT: &str = r#"This is a list of cases reported in volume 129 of United States Reports, decided by the Supreme Court of the United States in 1889.###HEADING_START######HEADING_SEPARATOR###Justices of the Supreme Court at the time of volume 129 U.S.###HEADING_END###
 
 The Supreme Court is established by Article III, Section 1 of the Constitution of the United States, which says: "The judicial Power of the United States, shall be vested in one supreme Court . . .". The size of the Court is not specified; the Constitution leaves it to Congress to set the number of justices. Under the Judiciary Act of 1789 Congress originally fixed the number of justices at six (one chief justice and five associate justices). Since 1789 Congress has varied the size of the Court from six to seven, nine, ten, and back to nine justices (always including one chief justice).
 
@@ -727,7 +727,7 @@ When the cases in volume 129 U.S. were decided the Court comprised the following
 | |Samuel Blatchford|Associate Justice|New York|Ward Hunt|March 22, 1882(Acclamation)|April 3, 1882–July 7, 1893(Died)|
 | |Lucius Quintus Cincinnatus Lamar|Associate Justice|Mississippi|William Burnham Woods|January 16, 1888(32–28)|January 18, 1888–January 23, 1893(Died)|
 
-###HEADING_START###:Notable Case in 129 U.S.###HEADING_END######HEADING_START###:Notable Case in 129 U.S.:Dent v. West Virginia###HEADING_END###In Dent v. West Virginia,       129 U.S. 114 (1889), the Supreme Court upheld a state physician licensing law. A practitioner with insufficient credentials to obtain a medical license sued West Virginia, claiming a violation of his rights under the due process clause of the 14th Amendment. The Supreme Court upheld the statute noting that, while each citizen had a right to follow any lawful calling, they were subject to reasonable state restrictions. Because of the nature of medical training, the large amount of knowledge required, and the life-and-death circumstances with which physicians dealt, patients needed to rely on the assurance of a license requiring physicians to meet a minimum set of standards.###HEADING_START###:Citation style###HEADING_END###
+###HEADING_START######HEADING_SEPARATOR###Notable Case in 129 U.S.###HEADING_END######HEADING_START######HEADING_SEPARATOR###Notable Case in 129 U.S.###HEADING_SEPARATOR###Dent v. West Virginia###HEADING_END###In Dent v. West Virginia,       129 U.S. 114 (1889), the Supreme Court upheld a state physician licensing law. A practitioner with insufficient credentials to obtain a medical license sued West Virginia, claiming a violation of his rights under the due process clause of the 14th Amendment. The Supreme Court upheld the statute noting that, while each citizen had a right to follow any lawful calling, they were subject to reasonable state restrictions. Because of the nature of medical training, the large amount of knowledge required, and the life-and-death circumstances with which physicians dealt, patients needed to rely on the assurance of a license requiring physicians to meet a minimum set of standards.###HEADING_START######HEADING_SEPARATOR###Citation style###HEADING_END###
 Under the Judiciary Act of 1789 the federal court structure at the time comprised District Courts, which had general trial jurisdiction; Circuit Courts, which had mixed trial and appellate (from the US District Courts) jurisdiction; and the United States Supreme Court, which had appellate jurisdiction over the federal District and Circuit courts—and for certain issues over state courts. The Supreme Court also had limited original jurisdiction (i.e., in which cases could be filed directly with the Supreme Court without first having been heard by a lower federal or state court). There were one or more federal District Courts and/or Circuit Courts in each state, territory, or other geographical region.
 
 Bluebook citation style is used for case names, citations, and jurisdictions. - "C.C.D." = United States Circuit Court for the District of . . . - e.g.,"C.C.D.N.J." = United States Circuit Court for the District of New Jersey
@@ -736,7 +736,7 @@ Bluebook citation style is used for case names, citations, and jurisdictions. -
  - e.g.,"M.D. Ala." = United States District Court for the Middle District of Alabama
  - "Ct. Cl." = United States Court of Claims
  - The abbreviation of a state's name alone indicates the highest appellate court in that state's judiciary at the time. - e.g.,"Pa." = Supreme Court of Pennsylvania
- - e.g.,"Me." = Supreme Judicial Court of Maine###HEADING_START###:List of cases in volume 129 U.S.###HEADING_END###
+ - e.g.,"Me." = Supreme Judicial Court of Maine###HEADING_START######HEADING_SEPARATOR###List of cases in volume 129 U.S.###HEADING_END###
 ||Case Name||Page & year||Opinion of the Court||Concurring opinion(s)||Dissenting opinion(s)||Lower Court||Disposition||
 |McCormick v. Graham's Adm'r|1 (1889)|Blatchford|none|none|C.C.N.D. Ill.|reversed|
 |Sargent v. Burgess|19 (1889)|Blatchford|none|none|C.C.E.D. Pa.|affirmed|
@@ -807,12 +807,12 @@ Bluebook citation style is used for case names, citations, and jurisdictions. -
 |Schraeder et al. Co. v. Packer|688 (1889)|Lamar|none|none|C.C.W.D. Pa.|affirmed|"#;
 
 pub(crate)const SUPREME_COURT_VOL_129_SPLIT_RESULT: &[&str; 12] = &[
-    "This is a list of cases reported in volume 129 of United States Reports, decided by the Supreme Court of the United States in 1889.###HEADING_START###:Justices of the Supreme Court at the time of volume 129 U.S.###HEADING_END###\n\nThe Supreme Court is established by Article III, Section 1 of the Constitution of the United States, which says: \"The judicial Power of the United States, shall be vested in one supreme Court . . .\". The size of the Court is not specified; the Constitution leaves it to Congress to set the number of justices. Under the Judiciary Act of 1789 Congress originally fixed the number of justices at six (one chief justice and five associate justices). Since 1789 Congress has varied the size of the Court from six to seven, nine, ten, and back to nine justices (always including one chief justice).\n\n",
+    "This is a list of cases reported in volume 129 of United States Reports, decided by the Supreme Court of the United States in 1889.###HEADING_START######HEADING_SEPARATOR###Justices of the Supreme Court at the time of volume 129 U.S.###HEADING_END###\n\nThe Supreme Court is established by Article III, Section 1 of the Constitution of the United States, which says: \"The judicial Power of the United States, shall be vested in one supreme Court . . .\". The size of the Court is not specified; the Constitution leaves it to Congress to set the number of justices. Under the Judiciary Act of 1789 Congress originally fixed the number of justices at six (one chief justice and five associate justices). Since 1789 Congress has varied the size of the Court from six to seven, nine, ten, and back to nine justices (always including one chief justice).\n\n",
     "When the cases in volume 129 U.S. were decided the Court comprised the following nine members:\n||Portrait||Justice||Office||Home State||Succeeded||Date confirmed by the Senate(Vote)||Tenure on Supreme Court||\n| |Melville Fuller|Chief Justice|Illinois|Morrison Waite|July 20, 1888(41–20)|October 8, 1888–July 4, 1910(Died)|\n| |Samuel Freeman Miller|Associate Justice|Iowa|Peter Vivian Daniel|July 16, 1862(Acclamation)|July 21, 1862–October 13, 1890(Died)|\n| |Stephen Johnson Field|Associate Justice|California|newly-created seat|March 10, 1863(Acclamation)|May 10, 1863–December 1, 1897(Retired)|\n| |Joseph P. Bradley|Associate Justice|New Jersey|newly-created seat|March 21, 1870(46–9)|March 23, 1870–January 22, 1892(Died)|\n| |John Marshall Harlan|Associate Justice|Kentucky|David Davis|November 29, 1877(Acclamation)|December 10, 1877–October 14, 1911(Died)|\n| |Stanley Matthews|Associate Justice|Ohio|Noah Haynes Swayne|May 12, 1881(24–23)|May 17, 1881–March 22, 1889(Died)|\n",
     "| |Horace Gray|Associate Justice|Massachusetts|Nathan Clifford|December 20, 1881(51–5)|January 9, 1882–September 15, 1902(Died)|\n| |Samuel Blatchford|Associate Justice|New York|Ward Hunt|March 22, 1882(Acclamation)|April 3, 1882–July 7, 1893(Died)|\n| |Lucius Quintus Cincinnatus Lamar|Associate Justice|Mississippi|William Burnham Woods|January 16, 1888(32–28)|January 18, 1888–January 23, 1893(Died)|\n\n",
-    "###HEADING_START###:Notable Case in 129 U.S.###HEADING_END######HEADING_START###:Notable Case in 129 U.S.:Dent v. West Virginia###HEADING_END###In Dent v. West Virginia,       129 U.S. 114 (1889), the Supreme Court upheld a state physician licensing law. A practitioner with insufficient credentials to obtain a medical license sued West Virginia, claiming a violation of his rights under the due process clause of the 14th Amendment. The Supreme Court upheld the statute noting that, while each citizen had a right to follow any lawful calling, they were subject to reasonable state restrictions. Because of the nature of medical training, the large amount of knowledge required, and the life-and-death circumstances with which physicians dealt, patients needed to rely on the assurance of a license requiring physicians to meet a minimum set of standards.###HEADING_START###:Citation style###HEADING_END###\n",
+    "###HEADING_START######HEADING_SEPARATOR###Notable Case in 129 U.S.###HEADING_END######HEADING_START######HEADING_SEPARATOR###Notable Case in 129 U.S.###HEADING_SEPARATOR###Dent v. West Virginia###HEADING_END###In Dent v. West Virginia,       129 U.S. 114 (1889), the Supreme Court upheld a state physician licensing law. A practitioner with insufficient credentials to obtain a medical license sued West Virginia, claiming a violation of his rights under the due process clause of the 14th Amendment. The Supreme Court upheld the statute noting that, while each citizen had a right to follow any lawful calling, they were subject to reasonable state restrictions. Because of the nature of medical training, the large amount of knowledge required, and the life-and-death circumstances with which physicians dealt, patients needed to rely on the assurance of a license requiring physicians to meet a minimum set of standards.###HEADING_START######HEADING_SEPARATOR###Citation style###HEADING_END###\n",
     "Under the Judiciary Act of 1789 the federal court structure at the time comprised District Courts, which had general trial jurisdiction; Circuit Courts, which had mixed trial and appellate (from the US District Courts) jurisdiction; and the United States Supreme Court, which had appellate jurisdiction over the federal District and Circuit courts—and for certain issues over state courts. The Supreme Court also had limited original jurisdiction (i.e., in which cases could be filed directly with the Supreme Court without first having been heard by a lower federal or state court). There were one or more federal District Courts and/or Circuit Courts in each state, territory, or other geographical region.\n\n",
-    "Bluebook citation style is used for case names, citations, and jurisdictions. - \"C.C.D.\" = United States Circuit Court for the District of . . . - e.g.,\"C.C.D.N.J.\" = United States Circuit Court for the District of New Jersey\n - \"D.\" = United States District Court for the District of . . . - e.g.,\"D. Mass.\" = United States District Court for the District of Massachusetts\n - \"E.\" = Eastern; \"M.\" = Middle; \"N.\" = Northern; \"S.\" = Southern; \"W.\" = Western - e.g.,\"C.C.S.D.N.Y.\" = United States Circuit Court for the Southern District of New York\n - e.g.,\"M.D. Ala.\" = United States District Court for the Middle District of Alabama\n - \"Ct. Cl.\" = United States Court of Claims\n - The abbreviation of a state's name alone indicates the highest appellate court in that state's judiciary at the time. - e.g.,\"Pa.\" = Supreme Court of Pennsylvania\n - e.g.,\"Me.\" = Supreme Judicial Court of Maine###HEADING_START###:List of cases in volume 129 U.S.###HEADING_END###\n",
+    "Bluebook citation style is used for case names, citations, and jurisdictions. - \"C.C.D.\" = United States Circuit Court for the District of . . . - e.g.,\"C.C.D.N.J.\" = United States Circuit Court for the District of New Jersey\n - \"D.\" = United States District Court for the District of . . . - e.g.,\"D. Mass.\" = United States District Court for the District of Massachusetts\n - \"E.\" = Eastern; \"M.\" = Middle; \"N.\" = Northern; \"S.\" = Southern; \"W.\" = Western - e.g.,\"C.C.S.D.N.Y.\" = United States Circuit Court for the Southern District of New York\n - e.g.,\"M.D. Ala.\" = United States District Court for the Middle District of Alabama\n - \"Ct. Cl.\" = United States Court of Claims\n - The abbreviation of a state's name alone indicates the highest appellate court in that state's judiciary at the time. - e.g.,\"Pa.\" = Supreme Court of Pennsylvania\n - e.g.,\"Me.\" = Supreme Judicial Court of Maine###HEADING_START######HEADING_SEPARATOR###List of cases in volume 129 U.S.###HEADING_END###\n",
     "||Case Name||Page & year||Opinion of the Court||Concurring opinion(s)||Dissenting opinion(s)||Lower Court||Disposition||\n|McCormick v. Graham's Adm'r|1 (1889)|Blatchford|none|none|C.C.N.D. Ill.|reversed|\n|Sargent v. Burgess|19 (1889)|Blatchford|none|none|C.C.E.D. Pa.|affirmed|\n|Minneapolis et al. Ry. Co. v. Beckwith|26 (1889)|Field|none|none|Kossuth Cnty. Cir. Ct.|affirmed|\n|City of Shreveport v. Cole|36 (1889)|Fuller|none|none|C.C.W.D. La.|reversed|\n|City of Shreveport v. United States ex rel. Jacobs|44 (1889)|Fuller|none|none|C.C.W.D. La.|reversed|\n|City of New Orleans v. Louisiana C. Co.|45 (1889)|Fuller|none|none|C.C.E.D. La.|dismissal denied|\n|Rosenwasser v. Spieth|47 (1889)|Gray|none|none|C.C.D. Me.|affirmed|\n|Baldwin v. Kansas|52 (1889)|Blatchford|none|Harlan|Kan.|dismissed|\n|Wallace v. Johnstone|58 (1889)|Lamar|none|none|C.C.S.D. Iowa|affirmed|\n|Noble v. Hammond & B.|65 (1889)|Lamar|none|none|Vt.|reversed|\n",
     "|Anderson v. H.T. Miller & Co.|70 (1889)|Lamar|none|none|C.C.E.D. Va.|affirmed|\n|Camden v. Mayhew|73 (1889)|Harlan|none|none|C.C.D.W. Va.|affirmed|\n|Arrowsmith v. Gleason|86 (1889)|Harlan|none|none|C.C.N.D. Ohio|reversed|\n|Tillson v. United States|101 (1889)|Gray|none|none|Ct. Cl.|affirmed|\n|Farnsworth v. Montana|104 (1889)|Blatchford|none|none|Sup. Ct. Terr. Mont.|dismissed|\n|Dent v. West Virginia|114 (1889)|Field|none|none|W. Va.|affirmed|\n|Inman S. & Co. v. South Carolina Ry. Co.|128 (1889)|Fuller|none|none|C.C.D.S.C.|reversed|\n|Stoutenburgh v. Hennick|141 (1889)|Fuller|none|Miller|Sup. Ct. D.C.|affirmed|\n|Bate R. Co. v. G.H. Hammond Co.|151 (1889)|Blatchford|none|none|C.C.D. Mass.|reversed|\n|Hill v. Chicago & E.R.R. Co.|170 (1889)|Blatchford|none|none|C.C.N.D. Ill.|dismissal denied|\n|Hanover F. Ins. Co. v. Kinneard|176 (1889)|Fuller|none|none|C.C.D. Kan.|dismissed|\n|Marrow v. Brinkley|178 (1889)|Fuller|none|none|Va.|dismissed|\n",
     "|Probst v. Domestic Missions|182 (1889)|Miller|none|none|Sup. Ct. Terr. N.M.|reversed|\n|Seibert v. United States ex rel. Harshman|192 (1889)|Field|none|none|C.C.E.D. Mo.|affirmed|\n|Galigher v. Jones|193 (1889)|Bradley|none|none|Sup. Ct. Terr. Utah|reversed|\n|Wade v. Metcalf|202 (1889)|Gray|none|none|C.C.D. Mass.|affirmed|\n|In re Farmers' L. & T. Co.|206 (1889)|Miller|Bradley|none|C.C.N.D. Tex.|mandamus granted|\n|Kimmish v. Ball|217 (1889)|Field|none|none|C.C.S.D. Iowa|reversed|\n|National S. Bank v. Butler|223 (1889)|Blatchford|none|none|C.C.D. Mass.|affirmed|\n|Robertson v. Perkins|233 (1889)|Blatchford|none|none|C.C.S.D.N.Y.|reversed|\n|Brown v. Sutton|238 (1889)|Miller|none|none|C.C.E.D. Wis.|affirmed|\n|Barton v. United States|249 (1889)|Fuller|none|none|Ct. Cl.|affirmed|\n|Carr v. Hamilton|252 (1889)|Bradley|none|none|C.C.W.D. La.|affirmed|\n|Morley S.M. Co v. Lancaster|263 (1889)|Blatchford|none|none|C.C.D. Mass.|reversed|\n",
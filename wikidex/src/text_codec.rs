@@ -0,0 +1,207 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{self, Read, Write},
+    str::FromStr,
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder};
+#[cfg(feature = "sqlite")]
+use sqlx::{Row, SqliteConnection};
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
+
+const GZIP_LEVEL: u32 = 9;
+const ZSTD_LEVEL: i32 = 19;
+/// zstd's default dictionary size.
+const DICTIONARY_SIZE: usize = 112_640;
+
+/// How the text of a document is compressed, recorded with every document in the docstore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TextCodec {
+    Gzip,
+    /// With the docstore's dictionary when it has one.
+    Zstd,
+}
+
+impl Display for TextCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextCodec::Gzip => write!(f, "gzip"),
+            TextCodec::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ParseTextCodecError(String);
+impl Error for ParseTextCodecError {}
+impl Display for ParseTextCodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unable to parse codec {}. Must be one of [gzip, zstd]",
+            self.0
+        )
+    }
+}
+
+impl FromStr for TextCodec {
+    type Err = ParseTextCodecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" => Ok(TextCodec::Gzip),
+            "zstd" => Ok(TextCodec::Zstd),
+            _ => Err(ParseTextCodecError(s.to_string())),
+        }
+    }
+}
+
+/// Compresses document text with one codec and decompresses text written with any.
+///
+/// Passages are compressed one at a time, too small for zstd to learn much from each. A
+/// dictionary trained on a sample of the corpus and stored in the `compression_dictionary` table
+/// of the docstore gives it that context up front.
+pub(crate) struct TextCompressor {
+    pub(crate) codec: TextCodec,
+    dictionary: Option<Vec<u8>>,
+    encoder: Option<EncoderDictionary<'static>>,
+    decoder: Option<DecoderDictionary<'static>>,
+}
+
+impl TextCompressor {
+    pub(crate) fn new(codec: TextCodec, dictionary: Option<Vec<u8>>) -> Self {
+        let encoder = dictionary
+            .as_deref()
+            .map(|dictionary| EncoderDictionary::copy(dictionary, ZSTD_LEVEL));
+        let decoder = dictionary.as_deref().map(DecoderDictionary::copy);
+        Self {
+            codec,
+            dictionary,
+            encoder,
+            decoder,
+        }
+    }
+
+    /// Trains a zstd dictionary on `sample`. Without one when compressing with gzip, or when the
+    /// sample is too small to train on.
+    pub(crate) fn train<S: AsRef<[u8]>>(codec: TextCodec, sample: &[S]) -> Self {
+        let dictionary = match codec {
+            TextCodec::Gzip => None,
+            TextCodec::Zstd => match zstd::dict::from_samples(sample, DICTIONARY_SIZE) {
+                Ok(dictionary) => Some(dictionary),
+                Err(e) => {
+                    log::warn!("Compressing without a dictionary, training failed: {e}");
+                    None
+                }
+            },
+        };
+        Self::new(codec, dictionary)
+    }
+
+    pub(crate) fn dictionary(&self) -> Option<&[u8]> {
+        self.dictionary.as_deref()
+    }
+
+    pub(crate) fn compress(&self, text: &str) -> Result<Vec<u8>, io::Error> {
+        match self.codec {
+            TextCodec::Gzip => {
+                let mut encoder = GzEncoder::new(vec![], flate2::Compression::new(GZIP_LEVEL));
+                encoder.write_all(text.as_bytes())?;
+                encoder.finish()
+            }
+            TextCodec::Zstd => match &self.encoder {
+                Some(dictionary) => {
+                    let mut encoder =
+                        zstd::stream::Encoder::with_prepared_dictionary(vec![], dictionary)?;
+                    encoder.write_all(text.as_bytes())?;
+                    encoder.finish()
+                }
+                None => zstd::stream::encode_all(text.as_bytes(), ZSTD_LEVEL),
+            },
+        }
+    }
+
+    pub(crate) fn decompress(&self, codec: TextCodec, bytes: &[u8]) -> Result<String, io::Error> {
+        let mut text = String::new();
+        match codec {
+            TextCodec::Gzip => {
+                GzDecoder::new(bytes).read_to_string(&mut text)?;
+            }
+            TextCodec::Zstd => match &self.decoder {
+                Some(dictionary) => {
+                    zstd::stream::Decoder::with_prepared_dictionary(bytes, dictionary)?
+                        .read_to_string(&mut text)?;
+                }
+                None => {
+                    zstd::stream::Decoder::new(bytes)?.read_to_string(&mut text)?;
+                }
+            },
+        }
+        Ok(text)
+    }
+
+    /// The dictionary recorded in the docstore, `None` before one was trained.
+    #[cfg(feature = "sqlite")]
+    pub(crate) async fn load(
+        connection: &mut SqliteConnection,
+    ) -> Result<Option<Vec<u8>>, sqlx::Error> {
+        let has_table = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'compression_dictionary'",
+        )
+        .fetch_one(&mut *connection)
+        .await?
+            > 0;
+        if !has_table {
+            return Ok(None);
+        }
+        let row = sqlx::query("SELECT dictionary FROM compression_dictionary LIMIT 1")
+            .fetch_optional(&mut *connection)
+            .await?;
+        Ok(row.map(|row| row.get("dictionary")))
+    }
+
+    #[cfg(feature = "sqlite")]
+    pub(crate) async fn save(&self, connection: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM compression_dictionary")
+            .execute(&mut *connection)
+            .await?;
+        if let Some(dictionary) = self.dictionary() {
+            sqlx::query("INSERT INTO compression_dictionary (dictionary) VALUES (?1)")
+                .bind(dictionary)
+                .execute(&mut *connection)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TextCodec, TextCompressor};
+
+    #[test]
+    fn round_trip() {
+        let sample = (0..20_000)
+            .map(|i| {
+                format!(
+                    "Village {i}\n\nVillage {i} lies in district {} of province {}. It has a population of {}.",
+                    i % 17,
+                    i % 5,
+                    i * 13
+                )
+            })
+            .collect::<Vec<_>>();
+        let text = "Village 1234\n\nVillage 1234 lies in district 10 of province 4. It has a population of 16042.";
+
+        let gzip = TextCompressor::new(TextCodec::Gzip, None);
+        let zstd = TextCompressor::train(TextCodec::Zstd, &sample);
+        assert!(zstd.dictionary().is_some());
+
+        let gzipped = gzip.compress(text).unwrap();
+        let zstded = zstd.compress(text).unwrap();
+        assert!(zstded.len() < gzipped.len());
+        assert_eq!(zstd.decompress(TextCodec::Gzip, &gzipped).unwrap(), text);
+        assert_eq!(zstd.decompress(TextCodec::Zstd, &zstded).unwrap(), text);
+    }
+}